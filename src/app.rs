@@ -0,0 +1,3226 @@
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Instant;
+
+use crate::cli::{self, Format, Opt, OptSpec};
+use crate::doctor::{self, Outcome};
+use crate::failure::Failure;
+use crate::header::{parse_header, platform_dep, setting_key};
+use crate::log::{self, log};
+use crate::project::{
+    self, link_side_files, refresh_project, relink_source, side_files, verify_checksums, BuildDir,
+    CreateError, SingleProject,
+};
+use crate::scaffold::{self, DepSpec};
+use crate::timings::{self, Timings};
+use crate::{
+    audit, bench, buildstd, cache, cfg, codesign, collection, completions, config, container,
+    debugger, deps, detach, doctest, edit, embed, eval, fuzz, gitstamp, headerfmt, hooks, import,
+    index, isolate, json, library, licenses, lints, metadata, minimal, musl, native, net, pgo,
+    plugin, proctree, profile, progress, record, remote, repl, requires, resources, restart,
+    runall, sandbox, sanitize, sbom, search, selfupdate, settings, shim, sidefiles, suggest,
+    upgrade, wasm, watch, wsl,
+};
+use crate::{header_setting, header_settings, tool_available};
+
+// Exits on a failure which doesn't fall into any of the classes of Failure.
+fn fatal_exit(message: &str) -> ! {
+    Failure::Other(message.to_owned()).exit()
+}
+
+pub fn main() {
+    let mut timings = Timings::new(Instant::now());
+    log::init();
+    let mut args = env::args();
+    args.nth(1);
+    let mut cmd = args.next();
+    if cmd.as_deref() == Some("--format") {
+        let format = args.next().unwrap_or_default();
+        if let Err(e) = cli::set_format(&format) {
+            Failure::Usage(format!("cargo-single: {}", e)).exit();
+        }
+        cmd = args.next();
+    }
+    let cmd = match cmd {
+        Some(cmd) => cmd,
+        None => Failure::Usage(cli::usage()).exit(),
+    };
+    let sub = match cli::find_subcommand(&cmd) {
+        Some(sub) => sub,
+        None if plugin::find(&cmd).is_some() => return plugin_main(&cmd, args),
+        None => match suggest::closest(&cmd, cli::SUBCOMMANDS.iter().map(|sub| sub.name)) {
+            Some(name) => Failure::Usage(format!(
+                "cargo-single: unknown command \"{}\"; did you mean \"{}\"?",
+                cmd, name
+            ))
+            .exit(),
+            None => Failure::Usage(cli::usage()).exit(),
+        },
+    };
+    let mut refresh_deps = false;
+    match cmd.as_str() {
+        "bench-cmd" | "build" | "check" | "debug" | "deny" | "doc" | "embed" | "flamegraph"
+        | "fmt" | "fuzz" | "licenses" | "nextest" | "pgo" | "profile" | "shim" | "run" | "sbom"
+        | "test" | "upgrade" => (),
+        "add" | "edit" | "new" | "refresh" => refresh_deps = true,
+        "import" => return import_main(args),
+        "completions" => return completions_main(args),
+        "search" => return search_main(args),
+        "run-all" => return run_all_main(args),
+        "ps" => return ps_main(args),
+        "stop" => return stop_main(args),
+        "fmt-header" => return fmt_header_main(args),
+        "eval" => return eval_main(args),
+        "repl" => return repl_main(args),
+        "watch" => return watch_main(args),
+        "wasm" => return wasm_main(args),
+        "doctor" => return doctor_main(args),
+        "self" => return self_main(args),
+        "cargo" => return cargo_main(args),
+        "config" => return config_main(args),
+        _ => Failure::Usage(cli::usage()).exit(),
+    }
+    let mut cargo_args = vec![];
+    let mut opts_seen = HashSet::new();
+    let mut rest = vec![];
+    let mut is_quiet = true;
+    let mut show_progress = true;
+    let mut cargo_toolchain = None;
+    let mut cargo_target = None;
+    let mut use_cross = false;
+    let mut container_image = None;
+    let mut project_dir = None;
+    let mut target_dir = None;
+    let mut profile_settings = vec![];
+    let mut watch = false;
+    let mut test_threads = None;
+    let mut sanitizer = None;
+    let mut no_network = false;
+    let mut template = None;
+    let mut preset = None;
+    let mut crate_type = None;
+    let mut build_std = None;
+    let mut cfgs = vec![];
+    let mut rustdoc_flags = None;
+    let mut stdio_files = vec![];
+    let mut restart = None;
+    let mut checksum = None;
+    let mut rustfmt_args = vec![];
+    let mut rustfmt_config = None;
+    while let Some(arg) = args.next() {
+        let spec = match cli::find_option(cli::SOURCE_OPTIONS, &arg) {
+            Some(spec) => spec,
+            None => {
+                rest.extend(args.collect::<Vec<_>>());
+                rest.push(arg);
+                break;
+            }
+        };
+        let value = option_value(spec, &mut args, &mut opts_seen);
+        match spec.opt {
+            Opt::Toolchain => cargo_toolchain = Some(arg),
+            Opt::Release => cargo_args.push(arg),
+            Opt::Target => {
+                cargo_args.push(arg);
+                cargo_args.push(value.clone().expect("target"));
+                cargo_target = value;
+            }
+            Opt::NoQuiet => is_quiet = false,
+            Opt::Quiet => {
+                is_quiet = true;
+                show_progress = false;
+            }
+            Opt::UseCross => use_cross = true,
+            Opt::Container => container_image = value,
+            Opt::ProjectDir => project_dir = value.map(PathBuf::from),
+            // Relative to the current directory, rather than to the project.
+            Opt::TargetDir => {
+                let dir = PathBuf::from(value.expect("target dir"));
+                target_dir = Some(env::current_dir().map(|cwd| cwd.join(&dir)).unwrap_or(dir));
+            }
+            Opt::Watch => watch = true,
+            Opt::NoRun | Opt::Doc | Opt::DocumentPrivateItems | Opt::NoDeps | Opt::KeepGoing => {
+                cargo_args.push(arg)
+            }
+            Opt::CargoConfig => {
+                cargo_args.push(arg);
+                cargo_args.push(value.expect("config"));
+            }
+            Opt::RustdocFlags => rustdoc_flags = value,
+            Opt::Stdin | Opt::Stdout | Opt::Stderr => {
+                stdio_files.push((spec.opt, PathBuf::from(value.expect("file"))))
+            }
+            Opt::Tty => env::set_var("CARGO_TERM_COLOR", "always"),
+            Opt::NoTty => {
+                env::set_var("CARGO_TERM_COLOR", "never");
+                show_progress = false;
+            }
+            Opt::Restart => match restart::parse(&value.expect("policy")) {
+                Ok(policy) => restart = Some(policy),
+                Err(e) => Failure::Usage(format!("cargo-single: {}", e)).exit(),
+            },
+            Opt::Checksum => checksum = value,
+            Opt::FmtCheck => cargo_args.push(arg),
+            // "cargo fmt" passes the edition of the manifest to rustfmt, which
+            // only lets a --config setting override it.
+            Opt::Edition => {
+                rustfmt_args.push("--config".to_owned());
+                rustfmt_args.push(format!("edition={}", value.expect("edition")));
+            }
+            Opt::ConfigPath => {
+                let path = PathBuf::from(value.expect("config path"));
+                rustfmt_config = Some(
+                    env::current_dir()
+                        .map(|cwd| cwd.join(&path))
+                        .unwrap_or(path),
+                );
+            }
+            Opt::TestThreads => test_threads = value,
+            Opt::Sanitize => {
+                let value = value.expect("sanitizer");
+                if !sanitize::SANITIZERS.contains(&value.as_str()) {
+                    Failure::Usage(format!(
+                        "cargo-single: unknown sanitizer \"{}\", expected one of: {}",
+                        value,
+                        sanitize::SANITIZERS.join(", ")
+                    ))
+                    .exit();
+                }
+                sanitizer = Some(value);
+            }
+            Opt::MinimalVersions
+            | Opt::DenyLints
+            | Opt::Explain
+            | Opt::GitStamp
+            | Opt::Codesign
+            | Opt::Static
+            | Opt::Native
+            | Opt::Sandbox
+            | Opt::Detach
+            | Opt::Yes
+            | Opt::DryRun
+            | Opt::TimingsSelf => (),
+            Opt::BuildStd => {
+                let value = arg[spec.long.len()..].trim_start_matches('=');
+                match buildstd::crates(value) {
+                    Ok(crates) => build_std = Some(crates),
+                    Err(e) => Failure::Usage(format!("cargo-single: {}", e)).exit(),
+                }
+            }
+            Opt::Cfg => match cfg::parse(&value.expect("cfg")) {
+                Ok(cfg) => cfgs.push(cfg),
+                Err(e) => Failure::Usage(format!("cargo-single: {}", e)).exit(),
+            },
+            Opt::Template => template = value,
+            Opt::Preset => preset = value,
+            Opt::LogLevel => {
+                if let Err(e) = log::set_level(&value.expect("log level")) {
+                    Failure::Usage(format!("cargo-single: {}", e)).exit();
+                }
+            }
+            Opt::CrateType => {
+                let value = value.expect("crate type");
+                if !library::CRATE_TYPES.contains(&value.as_str()) {
+                    Failure::Usage(format!(
+                        "cargo-single: unsupported crate type \"{}\", expected one of: {}",
+                        value,
+                        library::CRATE_TYPES.join(", ")
+                    ))
+                    .exit();
+                }
+                crate_type = Some(value);
+            }
+            Opt::NoNetwork => {
+                cargo_args.push("--offline".to_owned());
+                // Also applies to the other Cargo commands run by cargo-single.
+                env::set_var("CARGO_NET_OFFLINE", "true");
+                no_network = true;
+            }
+            Opt::Lto
+            | Opt::Strip
+            | Opt::Optimization
+            | Opt::Panic
+            | Opt::CodegenUnits
+            | Opt::Debuginfo
+            | Opt::SplitDebuginfo => {
+                let key = spec.long.trim_start_matches('-');
+                let value = value.expect("profile setting");
+                if let Err(e) = profile::check(key, &value) {
+                    Failure::Usage(format!("cargo-single: {}", e)).exit();
+                }
+                profile_settings.push((key, value));
+            }
+            Opt::Format | Opt::Output => unreachable!(),
+        }
+    }
+    if rest.is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    for spec in cli::SOURCE_OPTIONS {
+        if opts_seen.contains(&spec.opt) && !sub.options.iter().any(|opt| opt.opt == spec.opt) {
+            Failure::Usage(format!(
+                "cargo-single: {} is not valid with {}",
+                spec.display(),
+                cmd
+            ))
+            .exit();
+        }
+        if opts_seen.contains(&spec.opt)
+            && !spec.only.is_empty()
+            && !spec.only.contains(&cmd.as_str())
+        {
+            Failure::Usage(format!(
+                "cargo-single: {} is only valid with {}",
+                spec.display(),
+                spec.only.join(" or ")
+            ))
+            .exit();
+        }
+    }
+    if use_cross && cmd == "nextest" {
+        Failure::Usage("cargo-single: cross doesn't support nextest".to_owned()).exit();
+    }
+    let detach = opts_seen.contains(&Opt::Detach);
+    if detach {
+        for (opt, name) in [
+            (Opt::Container, "--container"),
+            (Opt::UseCross, "--use-cross"),
+            (Opt::Sandbox, "--sandbox"),
+            (Opt::Watch, "--watch"),
+            (Opt::Stdin, "--stdin"),
+            (Opt::Stdout, "--stdout"),
+            (Opt::Stderr, "--stderr"),
+        ] {
+            if opts_seen.contains(&opt) {
+                Failure::Usage(format!(
+                    "cargo-single: --detach and {} are mutually exclusive",
+                    name
+                ))
+                .exit();
+            }
+        }
+    }
+    if restart.is_some() {
+        for (opt, name) in [
+            (Opt::Container, "--container"),
+            (Opt::UseCross, "--use-cross"),
+            (Opt::Sandbox, "--sandbox"),
+            (Opt::Watch, "--watch"),
+            (Opt::Detach, "--detach"),
+        ] {
+            if opts_seen.contains(&opt) {
+                Failure::Usage(format!(
+                    "cargo-single: --restart and {} are mutually exclusive",
+                    name
+                ))
+                .exit();
+            }
+        }
+    }
+    let codesign = opts_seen.contains(&Opt::Codesign);
+    if codesign {
+        if !cfg!(target_os = "macos") {
+            Failure::Usage("cargo-single: --codesign is only supported on macOS".to_owned()).exit();
+        }
+        // The program must be a binary, built where cargo-single can find it.
+        for (opt, name) in [
+            (Opt::Container, "--container"),
+            (Opt::UseCross, "--use-cross"),
+            (Opt::CrateType, "--crate-type"),
+        ] {
+            if opts_seen.contains(&opt) {
+                Failure::Usage(format!(
+                    "cargo-single: --codesign and {} are mutually exclusive",
+                    name
+                ))
+                .exit();
+            }
+        }
+    }
+    if opts_seen.contains(&Opt::Tty) && opts_seen.contains(&Opt::NoTty) {
+        Failure::Usage("cargo-single: --tty and --no-tty are mutually exclusive".to_owned()).exit();
+    }
+    if opts_seen.contains(&Opt::MinimalVersions) && opts_seen.contains(&Opt::Doc) {
+        Failure::Usage(
+            "cargo-single: --minimal-versions and --doc are mutually exclusive".to_owned(),
+        )
+        .exit();
+    }
+    if opts_seen.contains(&Opt::MinimalVersions) && crate_type.is_some() {
+        Failure::Usage(
+            "cargo-single: --minimal-versions and --crate-type are mutually exclusive".to_owned(),
+        )
+        .exit();
+    }
+    if opts_seen.contains(&Opt::Static) {
+        for (opt, name) in [
+            (Opt::Target, "--target"),
+            (Opt::Sanitize, "--sanitize"),
+            (Opt::CrateType, "--crate-type"),
+        ] {
+            if opts_seen.contains(&opt) {
+                Failure::Usage(format!(
+                    "cargo-single: --static and {} are mutually exclusive",
+                    name
+                ))
+                .exit();
+            }
+        }
+    }
+    if container_image.is_some() {
+        if use_cross {
+            Failure::Usage(
+                "cargo-single: --container and --use-cross are mutually exclusive".to_owned(),
+            )
+            .exit();
+        }
+        if target_dir.is_some() {
+            Failure::Usage(
+                "cargo-single: --container and --target-dir are mutually exclusive".to_owned(),
+            )
+            .exit();
+        }
+        if cargo_toolchain.is_some() {
+            Failure::Usage(
+                "cargo-single: the toolchain is determined by the container image".to_owned(),
+            )
+            .exit();
+        }
+    }
+    let dry_run = opts_seen.contains(&Opt::DryRun);
+    let mut orig_src = rest.pop().expect("orig src");
+    if remote::is_url(&orig_src) {
+        match remote::fetch(
+            &orig_src,
+            checksum.as_deref(),
+            opts_seen.contains(&Opt::Yes),
+        ) {
+            Ok(path) => orig_src = path.to_string_lossy().into_owned(),
+            Err(e) => fatal_exit(&format!("cargo-single: {}", e)),
+        }
+    } else if checksum.is_some() || opts_seen.contains(&Opt::Yes) {
+        Failure::Usage("cargo-single: --checksum and --yes are only valid with a URL".to_owned())
+            .exit();
+    }
+    // A program named in cargo-single.toml.
+    let mut collection_settings = vec![];
+    if let Some(name) = orig_src.strip_prefix('@') {
+        let script = collection::resolve(name)
+            .unwrap_or_else(|e| Failure::SourceNotFound(format!("cargo-single: {}", e)).exit());
+        if !script.features.is_empty()
+            && matches!(
+                cmd.as_str(),
+                "build" | "check" | "doc" | "nextest" | "run" | "test"
+            )
+        {
+            cargo_args.push("--features".to_owned());
+            cargo_args.push(script.features.join(","));
+        }
+        // The arguments are the program's, so they're no use to the commands
+        // which pass theirs to Cargo or rustfmt.
+        if rest.is_empty() && !matches!(cmd.as_str(), "build" | "check" | "doc" | "fmt" | "nextest")
+        {
+            rest = script.args;
+        }
+        collection_settings = script.settings;
+        orig_src = script.path.to_string_lossy().into_owned();
+    }
+    if orig_src == "-" {
+        // The program is read from the standard input, and kept in the cache.
+        let mut source = String::new();
+        let stored = io::stdin()
+            .read_to_string(&mut source)
+            .map_err(Into::into)
+            .and_then(|_| cache::store("stdin", &source));
+        match stored {
+            Ok(path) => orig_src = path.to_string_lossy().into_owned(),
+            Err(e) => fatal_exit(&format!(
+                "cargo-single: error reading the standard input: {}",
+                e
+            )),
+        }
+    }
+    if cmd == "new" {
+        new_main(
+            &orig_src,
+            std::mem::take(&mut rest),
+            template,
+            preset.as_deref(),
+        );
+    }
+    // A source file in a WSL distribution is built there.
+    if cfg!(windows) {
+        let in_distro = wsl::distro_path(&orig_src).or_else(|| {
+            env::current_dir()
+                .ok()
+                .filter(|_| Path::new(&orig_src).is_relative())
+                .and_then(|dir| wsl::distro_path(&dir.to_string_lossy()))
+        });
+        if let Some((distro, _)) = in_distro {
+            return wsl_main(&distro);
+        }
+    }
+    let mut src = PathBuf::from(&orig_src);
+    let mut file_src = src.clone();
+    match fs::metadata(&src) {
+        Err(e) => {
+            let mut passed = false;
+            if src.extension().unwrap_or_default() != "rs" {
+                file_src.set_extension("rs");
+                if let Ok(md) = fs::metadata(&file_src) {
+                    passed = md.is_file();
+                }
+            }
+            if !passed {
+                source_not_found(&orig_src, sub, e);
+            }
+        }
+        Ok(md) if md.is_dir() => {
+            if !file_src.set_extension("rs") {
+                Failure::SourceNotFound(format!(
+                    "cargo-single: fatal: {}: cannot set extension",
+                    orig_src
+                ))
+                .exit();
+            }
+            match fs::metadata(&file_src) {
+                Err(e) => Failure::SourceNotFound(format!(
+                    "cargo-single: fatal: {}: {}",
+                    file_src.to_str().expect("source file"),
+                    e
+                ))
+                .exit(),
+                Ok(md) if !md.is_file() => {
+                    Failure::SourceNotFound(format!(
+                        "cargo-single: fatal: {}: not a regular file",
+                        file_src.to_str().expect("source file")
+                    ))
+                    .exit();
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+    // Settings not given on the command line may come from the environment,
+    // the header or the configuration file.
+    let mut layers = settings::Layers::load(Some(&file_src))
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let mut command_line = vec![];
+    if let Some(toolchain) = &cargo_toolchain {
+        command_line.push((
+            "toolchain".to_owned(),
+            toolchain.trim_start_matches('+').to_owned(),
+        ));
+    }
+    if let Some(target) = &cargo_target {
+        command_line.push(("target".to_owned(), target.clone()));
+    }
+    if opts_seen.contains(&Opt::Quiet) || opts_seen.contains(&Opt::NoQuiet) {
+        command_line.push(("quiet".to_owned(), is_quiet.to_string()));
+    }
+    layers.set_command_line(command_line);
+    layers.set_collection(collection_settings);
+    if container_image.is_none() {
+        cargo_toolchain = layers
+            .resolve("toolchain")
+            .map(|(toolchain, _)| format!("+{}", toolchain.trim_start_matches('+')));
+    }
+    // A target from the command line is already among the Cargo arguments.
+    if !opts_seen.contains(&Opt::Static) && cmd != "fmt" {
+        match layers.resolve("target") {
+            Some((target, source)) if source != settings::Source::CommandLine => {
+                cargo_args.push("--target".to_owned());
+                cargo_args.push(target.clone());
+                cargo_target = Some(target);
+            }
+            _ => (),
+        }
+    }
+    match layers.quiet() {
+        Ok(Some(true)) => show_progress = false,
+        Ok(Some(false)) => is_quiet = false,
+        Ok(None) => (),
+        Err(e) => Failure::Usage(format!("cargo-single: {}", e)).exit(),
+    }
+    src.set_extension("");
+    if let Some(dir) = project_dir {
+        src = dir;
+    }
+    let phase = Instant::now();
+    match fs::metadata(&src) {
+        Ok(md) if !md.is_dir() => {
+            Failure::SourceNotFound(format!(
+                "cargo-single: fatal: {}: not a directory",
+                src.to_str().expect("source dir")
+            ))
+            .exit();
+        }
+        Ok(_) => (),
+        Err(_) if dry_run => {
+            println!("would create the project in {}", src.display());
+            refresh_deps = true;
+        }
+        Err(_) => {
+            let status = progress::Status::start(show_progress, "creating the project");
+            let result = project::create(&file_src, &src, is_quiet);
+            drop(status);
+            match result {
+                Err(e @ CreateError::Cargo(_)) => {
+                    Failure::Cargo(format!("cargo-single: {}", e)).exit()
+                }
+                Err(e) => Failure::ProjectSetup(format!("cargo-single: {}", e)).exit(),
+                Ok(()) => (),
+            }
+            refresh_deps = true;
+        }
+    }
+    timings.add("project setup", phase);
+    if cmd == "add" {
+        add_main(&file_src, std::mem::take(&mut rest), preset.as_deref());
+    }
+    if dry_run {
+        if let Err(e) = print_planned_links(&file_src, &src, refresh_deps) {
+            fatal_exit(&format!("cargo-single: {}", e));
+        }
+    } else if refresh_deps {
+        log!(Info, "refreshing the manifest of {}", src.display());
+        let phase = Instant::now();
+        let status = progress::Status::start(show_progress, "refreshing the dependencies");
+        let result = refresh_project(&file_src, &src);
+        drop(status);
+        if let Err(e) = result {
+            Failure::ManifestRefresh(format!(
+                "cargo-single: error refreshing dependencies: {}",
+                e
+            ))
+            .exit();
+        }
+        timings.add("manifest refresh", phase);
+    }
+    if !dry_run {
+        let phase = Instant::now();
+        if let Err(e) = link_side_files(&file_src, &src) {
+            Failure::ProjectSetup(format!("cargo-single: error linking the side files: {}", e))
+                .exit();
+        }
+        timings.add("project setup", phase);
+    }
+    // The helper commands build the program themselves, and need to know
+    // where the binary ends up.
+    let build_dir = || BuildDir::new(&src, target_dir.clone(), cargo_target.clone());
+    match cmd.as_str() {
+        "add" | "new" => return,
+        "refresh" => {
+            lint_main(&file_src, opts_seen.contains(&Opt::DenyLints));
+            // The checks after a refresh need the crates.io index, and Cargo
+            // can't check the dependencies if one of them isn't there.
+            let found = no_network || dry_run || crate_names_main(&file_src);
+            if opts_seen.contains(&Opt::Explain) && found && !dry_run {
+                explain_main(&src, cargo_toolchain.as_deref());
+            }
+            if !no_network && !dry_run && found {
+                audit_main(&src, cargo_toolchain, is_quiet);
+            }
+            return;
+        }
+        "edit" => {
+            let check_args = watch.then(|| {
+                let mut check_args: Vec<String> = cargo_toolchain.into_iter().collect();
+                check_args.push("check".to_owned());
+                check_args.extend(cargo_args);
+                if is_quiet {
+                    check_args.push("--quiet".to_owned());
+                }
+                check_args.push("--manifest-path".to_owned());
+                check_args.push(
+                    src.join("Cargo.toml")
+                        .to_str()
+                        .expect("source dir")
+                        .to_owned(),
+                );
+                check_args
+            });
+            return edit_main(&file_src, &src, check_args);
+        }
+        "fuzz" => return fuzz_main(&file_src, &src, cargo_toolchain, rest),
+        "pgo" => return pgo_main(&src, cargo_toolchain, target_dir, rest),
+        "bench-cmd" => return bench_main(&src, cargo_toolchain, &build_dir(), rest),
+        "debug" => return debug_main(&src, cargo_toolchain, &build_dir(), rest),
+        "shim" => return shim_main(&file_src, &src, cargo_toolchain, &build_dir(), rest),
+        "profile" => return profile_main(&file_src, &src, cargo_toolchain, &build_dir(), rest),
+        "flamegraph" => return flamegraph_main(&file_src, &src, cargo_toolchain, rest),
+        "embed" => return embed_main(&file_src, &src, cargo_toolchain, cargo_target, rest),
+        "deny" => return deny_main(&file_src, &src, cargo_toolchain, rest),
+        "sbom" => return sbom_main(&src, cargo_toolchain, rest),
+        "licenses" => return licenses_main(&src, cargo_toolchain, rest),
+        "upgrade" => return upgrade_main(&file_src, &src, rest),
+        "run" if env::var_os(requires::SKIP_ENV).is_none() => {
+            requires_main(&file_src, is_quiet, dry_run)
+        }
+        // The project may not be next to the source file, where rustfmt
+        // wouldn't find the configuration.
+        "fmt" => {
+            if rustfmt_config.is_none() {
+                rustfmt_config = fs::canonicalize(&file_src)
+                    .ok()
+                    .and_then(|path| rustfmt_config_file(path.parent()?));
+            }
+            if let Some(path) = rustfmt_config.take() {
+                log!(Debug, "using the rustfmt configuration {}", path.display());
+                rustfmt_args.push("--config-path".to_owned());
+                rustfmt_args.push(path.to_string_lossy().into_owned());
+            }
+        }
+        _ => (),
+    }
+    let mut configs = vec![];
+    if let Some(sanitizer) = sanitizer.as_ref() {
+        // Sanitizers are only available on nightly.
+        if cargo_toolchain.is_none() && container_image.is_none() {
+            cargo_toolchain = Some("+nightly".to_owned());
+        }
+        if cargo_target.is_none() {
+            let host = match sanitize::host_target(cargo_toolchain.as_deref()) {
+                Some(host) => host,
+                None => fatal_exit(
+                    "cargo-single: can't determine the host target, use --target to set it",
+                ),
+            };
+            cargo_args.push("--target".to_owned());
+            cargo_args.push(host.clone());
+            cargo_target = Some(host);
+        }
+        let target = cargo_target.as_ref().expect("target");
+        if dry_run {
+            println!("would write {}", src.join(sanitize::CONFIG).display());
+        } else if let Err(e) = sanitize::write_config(&src, sanitizer, target) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the sanitizer configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(sanitize::CONFIG);
+    }
+    if opts_seen.contains(&Opt::Static) {
+        let target = match sanitize::host_target(cargo_toolchain.as_deref())
+            .as_deref()
+            .map(musl::target)
+        {
+            Some(Some(target)) => target,
+            Some(None) => fatal_exit("cargo-single: no musl target for the host architecture"),
+            None => fatal_exit("cargo-single: can't determine the host target"),
+        };
+        if dry_run {
+            println!("would write {}", src.join(musl::CONFIG).display());
+        } else if let Err(e) = musl::write_config(&src, &target) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the static build configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(musl::CONFIG);
+        cargo_args.push("--target".to_owned());
+        cargo_args.push(target.clone());
+        cargo_target = Some(target);
+        if !opts_seen.contains(&Opt::Release) {
+            cargo_args.push("--release".to_owned());
+        }
+    }
+    if let Some(crates) = build_std.as_ref() {
+        // Building the standard library is only available on nightly.
+        if cargo_toolchain.is_none() && container_image.is_none() {
+            cargo_toolchain = Some("+nightly".to_owned());
+        }
+        // It also needs an explicit target, by default the host.
+        if cargo_target.is_none() {
+            let host = match sanitize::host_target(cargo_toolchain.as_deref()) {
+                Some(host) => host,
+                None => fatal_exit(
+                    "cargo-single: can't determine the host target, use --target to set it",
+                ),
+            };
+            cargo_args.push("--target".to_owned());
+            cargo_args.push(host.clone());
+            cargo_target = Some(host);
+        }
+        if dry_run {
+            println!("would write {}", src.join(buildstd::CONFIG).display());
+        } else if let Err(e) = buildstd::write_config(&src, crates) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the standard library build configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(buildstd::CONFIG);
+    }
+    if !cfgs.is_empty() {
+        if dry_run {
+            println!("would write {}", src.join(cfg::CONFIG).display());
+        } else if let Err(e) = cfg::write_config(&src, &cfgs) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the --cfg configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(cfg::CONFIG);
+    }
+    if cmd != "fmt" && cmd != "nextest" && !use_cross && container_image.is_none() {
+        if let Some(target) = cargo_target.as_ref() {
+            if !target_installed(cargo_toolchain.as_deref(), target) && tool_available("cross") {
+                if !is_quiet {
+                    eprintln!(
+                        "cargo-single: target {} is not installed, using cross",
+                        target
+                    );
+                }
+                log!(Info, "target {} is not installed, using cross", target);
+                use_cross = true;
+            }
+        }
+    }
+    if opts_seen.contains(&Opt::Native) {
+        if use_cross {
+            Failure::Usage("cargo-single: --native can't be used with cross".to_owned()).exit();
+        }
+        // The CPU of the host is only known for its own target, or its musl
+        // one with --static.
+        if let Some(target) = cargo_target.as_ref() {
+            let host = sanitize::host_target(cargo_toolchain.as_deref());
+            let musl = host.as_deref().and_then(musl::target);
+            if host.as_ref() != Some(target) && musl.as_ref() != Some(target) {
+                Failure::Usage(format!(
+                    "cargo-single: --native can't be used when building for {}",
+                    target
+                ))
+                .exit();
+            }
+        }
+        if dry_run {
+            println!("would write {}", src.join(native::CONFIG).display());
+        } else if let Err(e) = native::write_config(&src) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the native build configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(native::CONFIG);
+    }
+    if opts_seen.contains(&Opt::Sandbox) {
+        if container_image.is_some() || use_cross {
+            Failure::Usage(
+                "cargo-single: --sandbox can't be used with a container or cross".to_owned(),
+            )
+            .exit();
+        }
+        let setting = |key| {
+            header_setting(&file_src, key)
+                .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)))
+        };
+        let policy = sandbox::policy(
+            &file_src,
+            setting("sandbox.fs").as_deref(),
+            setting("sandbox.net").as_deref(),
+        )
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+        // The program is built into the project, or the given target directory.
+        let readable: Vec<_> = [Some(src.clone()), target_dir.clone()]
+            .into_iter()
+            .flatten()
+            .map(|dir| fs::canonicalize(&dir).unwrap_or(dir))
+            .collect();
+        let runner = sandbox::runner(&policy, &readable)
+            .unwrap_or_else(|e| Failure::Cargo(format!("cargo-single: {}", e)).exit());
+        if dry_run {
+            println!("would write {}", src.join(sandbox::CONFIG).display());
+        } else if let Err(e) = sandbox::write_config(&src, &runner) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error writing the sandbox configuration: {}",
+                e
+            ))
+            .exit();
+        }
+        configs.push(sandbox::CONFIG);
+    }
+    if cmd == "nextest"
+        && container_image.is_none()
+        && !cargo_subcommand_available(cargo_toolchain.as_deref(), "nextest")
+    {
+        Failure::Cargo(
+            "cargo-single: cargo-nextest is not installed; install it with \"cargo install cargo-nextest\"".to_owned()).exit();
+    }
+    if cmd != "fmt" && dry_run {
+        let pinned = header_settings(&file_src)
+            .unwrap_or_default()
+            .iter()
+            .any(|(key, _)| key.starts_with("checksum."));
+        if pinned {
+            println!("would verify the checksums of the pinned dependencies");
+        }
+    } else if cmd != "fmt" {
+        if let Err(e) = verify_checksums(&file_src, &src, cargo_toolchain.as_deref()) {
+            fatal_exit(&format!(
+                "cargo-single: dependency checksum verification failed: {}",
+                e
+            ));
+        }
+    }
+    let mut manifest = "Cargo.toml";
+    if opts_seen.contains(&Opt::Doc) {
+        if dry_run {
+            println!("would write {}", src.join(doctest::MANIFEST).display());
+        } else if let Err(e) = doctest::prepare(&src) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error preparing the doctest manifest: {}",
+                e
+            ))
+            .exit();
+        }
+        manifest = doctest::MANIFEST;
+    }
+    if opts_seen.contains(&Opt::MinimalVersions) {
+        // The unstable flag resolves the dependencies anew in the absence of a
+        // lock file.
+        if cargo_toolchain.is_none() && container_image.is_none() {
+            cargo_toolchain = Some("+nightly".to_owned());
+        }
+        if dry_run {
+            println!("would write {}", src.join(minimal::MANIFEST).display());
+        } else if let Err(e) = minimal::prepare(&src) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error preparing the minimal versions manifest: {}",
+                e
+            ))
+            .exit();
+        }
+        cargo_args.push("-Zminimal-versions".to_owned());
+        manifest = minimal::MANIFEST;
+    }
+    if let Some(crate_type) = crate_type.as_ref() {
+        if dry_run {
+            println!("would write {}", src.join(library::MANIFEST).display());
+        } else if let Err(e) = library::prepare(&src, crate_type) {
+            Failure::ProjectSetup(format!(
+                "cargo-single: error preparing the library manifest: {}",
+                e
+            ))
+            .exit();
+        }
+        manifest = library::MANIFEST;
+    }
+    if is_quiet {
+        cargo_args.push("--quiet".to_owned());
+    }
+    // Variables from "env.<name>" settings, for env!() in the program. Variables
+    // already set in the environment take precedence, so that the values can be
+    // overridden for a build.
+    let mut build_env: Vec<_> = header_settings(&file_src)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix("env.")?.to_owned(), value)))
+        .filter(|(name, _)| env::var_os(name).is_none())
+        .collect();
+    if opts_seen.contains(&Opt::GitStamp) {
+        match gitstamp::variables(&file_src) {
+            Ok(variables) => build_env.extend(variables),
+            Err(e) => fatal_exit(&format!(
+                "cargo-single: can't get the Git version of {}: {}",
+                file_src.display(),
+                e
+            )),
+        }
+    }
+    // Added to those in the environment, which would otherwise be overridden.
+    if let Some(flags) = rustdoc_flags {
+        let flags = match env::var("RUSTDOCFLAGS") {
+            Ok(existing) if !existing.trim().is_empty() => format!("{} {}", existing, flags),
+            _ => flags,
+        };
+        build_env.push(("RUSTDOCFLAGS".to_owned(), flags));
+    }
+    let build_env_names: Vec<_> = build_env.iter().map(|(name, _)| name.clone()).collect();
+    let (program, mut runner, project_root) = if let Some(image) = container_image.as_ref() {
+        let engine = match container::engine() {
+            Some(engine) => engine,
+            None => {
+                Failure::Cargo("cargo-single: neither docker nor podman is available".to_owned())
+                    .exit()
+            }
+        };
+        let project_dir = match fs::canonicalize(&src) {
+            Ok(dir) => dir,
+            Err(e) => Failure::SourceNotFound(format!(
+                "cargo-single: fatal: {}: {}",
+                src.to_str().expect("source dir"),
+                e
+            ))
+            .exit(),
+        };
+        let mounts = fs::read_to_string(project_dir.join("Cargo.toml"))
+            .map_err(|e| e.to_string())
+            .and_then(|manifest| container::path_dependencies(&manifest))
+            .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+        let runner = container::cargo_command(
+            &engine,
+            image,
+            &project_dir,
+            &mounts,
+            no_network,
+            &build_env_names,
+        );
+        (
+            format!("{} run {} cargo", engine, image),
+            runner,
+            container::PROJECT_MOUNT.to_owned(),
+        )
+    } else if use_cross && cmd != "fmt" {
+        // Cross mounts the directory it's started from into the container, so
+        // run it from the project directory and use relative paths.
+        let mut runner = Command::new("cross");
+        runner.current_dir(&src);
+        if no_network {
+            let mut opts = env::var("CROSS_CONTAINER_OPTS").unwrap_or_default();
+            opts.push_str(" --network none");
+            runner.env("CROSS_CONTAINER_OPTS", opts.trim_start());
+        }
+        if !build_env.is_empty() {
+            let mut names = env::var("CROSS_BUILD_ENV_PASSTHROUGH").unwrap_or_default();
+            for name in &build_env_names {
+                names.push(' ');
+                names.push_str(name);
+            }
+            runner.env("CROSS_BUILD_ENV_PASSTHROUGH", names.trim_start());
+        }
+        ("cross".to_owned(), runner, ".".to_owned())
+    } else {
+        let runner = if no_network && isolate::available() {
+            isolate::command("cargo")
+        } else {
+            if no_network && !is_quiet {
+                eprintln!(
+                    "cargo-single: network namespaces are unavailable, running Cargo offline"
+                );
+            }
+            Command::new("cargo")
+        };
+        (
+            "cargo".to_owned(),
+            runner,
+            src.to_str().expect("source dir").to_owned(),
+        )
+    };
+    runner.envs(build_env);
+    // Output files are appended to, so that the output of runs from cron or
+    // systemd accumulates like a log. They're left alone by a dry run.
+    let redirect = |command: &mut Command| {
+        for (opt, path) in &stdio_files {
+            let file = match opt {
+                Opt::Stdin => File::open(path),
+                _ => fs::OpenOptions::new().create(true).append(true).open(path),
+            };
+            let file = file.unwrap_or_else(|e| {
+                fatal_exit(&format!("cargo-single: {}: {}", path.display(), e))
+            });
+            match opt {
+                Opt::Stdin => command.stdin(file),
+                Opt::Stdout => command.stdout(file),
+                _ => command.stderr(file),
+            };
+        }
+        if opts_seen.contains(&Opt::NoTty) && !opts_seen.contains(&Opt::Stdin) {
+            command.stdin(Stdio::null());
+        }
+        // With --tty, the streams which aren't redirected to a file are the
+        // controlling terminal, even if those of cargo-single aren't.
+        if opts_seen.contains(&Opt::Tty) {
+            for opt in [Opt::Stdin, Opt::Stdout, Opt::Stderr] {
+                if stdio_files.iter().any(|(redirected, _)| *redirected == opt) {
+                    continue;
+                }
+                let (name, write) = match opt {
+                    _ if !cfg!(windows) => ("/dev/tty", opt != Opt::Stdin),
+                    Opt::Stdin => ("CONIN$", false),
+                    _ => ("CONOUT$", true),
+                };
+                let tty = fs::OpenOptions::new()
+                    .read(!write)
+                    .write(write)
+                    .open(name)
+                    .unwrap_or_else(|e| {
+                        fatal_exit(&format!("cargo-single: --tty: no terminal: {}", e))
+                    });
+                match opt {
+                    Opt::Stdin => command.stdin(tty),
+                    Opt::Stdout => command.stdout(tty),
+                    _ => command.stderr(tty),
+                };
+            }
+        }
+    };
+    if !dry_run {
+        redirect(&mut runner);
+    }
+    let project_path = |path: &str| format!("{}/{}", project_root, path);
+    cargo_args.push("--manifest-path".to_owned());
+    cargo_args.push(project_path(manifest));
+    // With "build.separate-targets", each target has a target directory of its
+    // own, so that switching between the host and another target, or between
+    // Cargo and cross, doesn't rebuild the artifacts of the other.
+    let target_subdir = match container_image {
+        None => project::target_subdir(cargo_target.as_deref()),
+        Some(_) => "target".to_owned(),
+    };
+    // Other manifests share the build artifacts with the main one.
+    if let Some(dir) = target_dir.as_ref() {
+        log!(Debug, "using the target directory {}", dir.display());
+        cargo_args.push("--target-dir".to_owned());
+        cargo_args.push(dir.to_str().expect("target dir").to_owned());
+    } else if (manifest != "Cargo.toml" && program == "cargo") || target_subdir != "target" {
+        log!(Debug, "using the target directory {}", target_subdir);
+        cargo_args.push("--target-dir".to_owned());
+        cargo_args.push(project_path(&target_subdir));
+    }
+    for config in configs {
+        log!(Debug, "passing the configuration {}", config);
+        cargo_args.push("--config".to_owned());
+        cargo_args.push(project_path(config));
+    }
+    // The settings apply to the profile of this build only, without changing
+    // the manifest.
+    let release = cargo_args.iter().any(|arg| arg == "--release");
+    for (key, value) in &profile_settings {
+        cargo_args.push("--config".to_owned());
+        cargo_args.push(profile::config_arg(release, key, value));
+    }
+    if is_quiet
+        && show_progress
+        && program == "cargo"
+        && matches!(cmd.as_str(), "build" | "check" | "doc" | "run" | "test")
+    {
+        cargo_args.extend(progress::cargo_args());
+    }
+    let mut first_args = vec![];
+    if let Some(toolchain) = cargo_toolchain.as_ref() {
+        first_args.push(toolchain.as_str());
+    }
+    // A detached program, or one restarted when it fails, is built, and run by
+    // cargo-single itself, which can't tell the failures of "cargo run" from
+    // the program's.
+    let build_only = detach || restart.is_some();
+    first_args.push(if build_only { "build" } else { &cmd });
+    let program_args = if build_only {
+        std::mem::take(&mut rest)
+    } else {
+        vec![]
+    };
+    match cmd.as_str() {
+        "test" => {
+            // Arguments after the source file all go to the test harness, so a
+            // separating "--" is superfluous.
+            if rest.first().map(String::as_str) == Some("--") {
+                rest.remove(0);
+            }
+            if let Some(threads) = test_threads {
+                rest.insert(0, format!("--test-threads={}", threads));
+            }
+            rest.insert(0, "--".to_owned());
+        }
+        // The arguments are nextest's own, which may include a "--" followed
+        // by test binary arguments.
+        "nextest" => {
+            first_args.push("run");
+            if let Some(threads) = test_threads {
+                cargo_args.push("--test-threads".to_owned());
+                cargo_args.push(threads);
+            }
+        }
+        // The program isn't run, so the arguments are Cargo's own, like --open
+        // or --features.
+        "build" | "check" | "doc" if !build_only => {
+            if rest.iter().any(|arg| arg == "--") {
+                Failure::Usage(format!(
+                    "cargo-single: {} doesn't run the program, and passes the arguments \
+                     after the source file to Cargo, which takes no \"--\"",
+                    cmd
+                ))
+                .exit();
+            }
+        }
+        "fmt" => {
+            rustfmt_args.insert(0, "--".to_owned());
+            rest.splice(0..0, rustfmt_args);
+        }
+        _ if build_only => (),
+        _ => rest.insert(0, "--".to_owned()),
+    }
+    let hook = |name| {
+        if cmd != "run" || detach {
+            return None;
+        }
+        header_setting(&file_src, name)
+            .and_then(|setting| hooks::find(name, setting))
+            .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)))
+    };
+    let post_run = hook(hooks::POST_RUN);
+    runner.args(first_args).args(&cargo_args).args(&rest);
+    if dry_run {
+        if let Some(line) = hook(hooks::PRE_RUN) {
+            println!("would run the {} hook \"{}\"", hooks::PRE_RUN, line);
+        }
+        println!("would run {:?}", runner);
+        if codesign {
+            let identity = codesign::identity()
+                .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+            println!("would sign the program as \"{}\"", identity);
+        }
+        if let Some(line) = post_run {
+            println!("would run the {} hook \"{}\"", hooks::POST_RUN, line);
+        }
+        return;
+    }
+    if let Some(line) = hook(hooks::PRE_RUN) {
+        if let Err(e) = hooks::run(hooks::PRE_RUN, &line) {
+            fatal_exit(&format!("cargo-single: {}", e));
+        }
+    }
+    log!(Info, "running {:?}", runner);
+    let phase = Instant::now();
+    let mut status = proctree::status(&mut runner);
+    timings.add(timings::CARGO, phase);
+    // Cross is excluded, so the program is in the target directory used by
+    // Cargo.
+    let name = src.file_name().expect("project name").to_string_lossy();
+    let binary = || {
+        let profile = if cargo_args.iter().any(|arg| arg == "--release") {
+            "release"
+        } else {
+            "debug"
+        };
+        target_dir
+            .clone()
+            .unwrap_or_else(|| src.join(&target_subdir))
+            .join(cargo_target.as_deref().unwrap_or_default())
+            .join(profile)
+            .join(format!("{}{}", name, env::consts::EXE_SUFFIX))
+    };
+    if let Some(policy) = restart.as_ref() {
+        if status.as_ref().is_ok_and(ExitStatus::success) {
+            let mut command = Command::new(binary());
+            command.args(&program_args);
+            redirect(&mut command);
+            log!(Info, "running {:?}", command);
+            status = Ok(
+                restart::supervise(&name, &mut command, policy).unwrap_or_else(|e| {
+                    fatal_exit(&format!("cargo-single: error running {}: {}", name, e))
+                }),
+            );
+        }
+    }
+    // The post-run hook is meant for cleaning up, so it runs even if the
+    // program fails, whose exit status then takes precedence.
+    let post_run = post_run.map(|line| hooks::run(hooks::POST_RUN, &line));
+    if opts_seen.contains(&Opt::TimingsSelf) {
+        timings.report();
+    }
+    match status {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"{} {}\": {}",
+            program, cmd, e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+    if let Some(Err(e)) = post_run {
+        fatal_exit(&format!("cargo-single: {}", e));
+    }
+    if codesign {
+        let signed = codesign::identity()
+            .map_err(Into::into)
+            .and_then(|identity| codesign::sign(&binary(), &identity));
+        if let Err(e) = signed {
+            fatal_exit(&format!("cargo-single: error signing {}: {}", name, e));
+        }
+    }
+    if detach {
+        match detach::start(&name, &file_src, &binary(), &program_args) {
+            Ok(detached) => eprintln!(
+                "cargo-single: started {} with PID {}, logging to {}",
+                name,
+                detached.pid,
+                detached.log.display()
+            ),
+            Err(e) => fatal_exit(&format!("cargo-single: error starting {}: {}", name, e)),
+        }
+        return;
+    }
+    // A library or a static binary is of no use without knowing where it is.
+    // Cross has its own target directory next to the manifest, and a
+    // container's can't be known.
+    if cmd != "build" || (crate_type.is_none() && !opts_seen.contains(&Opt::Static)) {
+        return;
+    }
+    let manifest_dir = src.join(manifest).with_file_name("");
+    let target_dir = match program.as_str() {
+        _ if target_dir.is_some() => target_dir.expect("target dir"),
+        "cargo" => src.join(&target_subdir),
+        "cross" if target_subdir != "target" => src.join(&target_subdir),
+        "cross" => manifest_dir.join("target"),
+        _ => return,
+    };
+    let release = cargo_args.iter().any(|arg| arg == "--release");
+    let artifact = match crate_type {
+        Some(crate_type) => library::artifact(
+            &src,
+            &target_dir,
+            &crate_type,
+            cargo_target.as_deref(),
+            release,
+        )
+        .ok(),
+        None => src.file_name().map(|name| {
+            target_dir
+                .join(cargo_target.as_deref().unwrap_or_default())
+                .join("release")
+                .join(name)
+        }),
+    };
+    match artifact {
+        Some(path) if path.exists() => println!("{}", path.display()),
+        _ => eprintln!("cargo-single: warning: can't find the built file"),
+    }
+}
+
+// rustfmt.toml or .rustfmt.toml in the directory or one of its ancestors,
+// searched like rustfmt does.
+fn rustfmt_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|dir| [dir.join("rustfmt.toml"), dir.join(".rustfmt.toml")])
+        .find(|path| path.is_file())
+}
+
+// If Rustup can't be queried, assume that the target is available.
+fn target_installed(toolchain: Option<&str>, target: &str) -> bool {
+    let mut rustup = Command::new("rustup");
+    if let Some(toolchain) = toolchain {
+        rustup.arg(toolchain);
+    }
+    match rustup.args(["target", "list", "--installed"]).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.trim() == target),
+        _ => true,
+    }
+}
+
+fn cargo_subcommand_available(toolchain: Option<&str>, subcommand: &str) -> bool {
+    let mut cargo = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cargo.arg(toolchain);
+    }
+    cargo
+        .args([subcommand, "--version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// Exits for a source file which can't be found, suggesting an option if it
+// looks like a misspelled one, or else source files with similar names.
+fn source_not_found(orig_src: &str, sub: &cli::Subcommand, e: io::Error) -> ! {
+    if orig_src.starts_with('-') {
+        let options = sub
+            .options
+            .iter()
+            .filter(|spec| spec.long != "+")
+            .map(|spec| spec.long);
+        let name = orig_src.split('=').next().unwrap_or(orig_src);
+        let message = match suggest::closest(name, options) {
+            Some(option) => format!(
+                "cargo-single: unknown option {}; did you mean {}?",
+                name, option
+            ),
+            None => format!("cargo-single: unknown option {}", name),
+        };
+        Failure::Usage(message).exit();
+    }
+    let mut message = format!("cargo-single: fatal: {}: {}", orig_src, e);
+    let mut path = PathBuf::from(orig_src);
+    path.set_extension("rs");
+    let similar = suggest::similar_files(&path);
+    if !similar.is_empty() {
+        message.push_str(&format!(
+            "\nsimilarly named source files: {}",
+            similar.join(", ")
+        ));
+    }
+    Failure::SourceNotFound(message).exit()
+}
+
+// Checks for a duplicate option and fetches its value, if it takes one.
+fn option_value(
+    spec: &OptSpec,
+    args: &mut env::Args,
+    opts_seen: &mut HashSet<Opt>,
+) -> Option<String> {
+    let repeatable = matches!(spec.opt, Opt::Cfg | Opt::CargoConfig);
+    if !opts_seen.insert(spec.opt) && !repeatable {
+        Failure::Usage(format!("cargo-single: {} already seen", spec.display())).exit();
+    }
+    if spec.value.is_none() || spec.opt == Opt::Toolchain {
+        return None;
+    }
+    match args.next() {
+        Some(value) => Some(value),
+        None => Failure::Usage(format!(
+            "cargo-single: {} needs an argument",
+            spec.display()
+        ))
+        .exit(),
+    }
+}
+
+fn import_main(mut args: env::Args) {
+    let mut crate_dir = None;
+    let mut out = None;
+    let mut opts_seen = HashSet::new();
+    while let Some(arg) = args.next() {
+        match cli::find_option(cli::IMPORT_OPTIONS, &arg) {
+            Some(spec) => out = option_value(spec, &mut args, &mut opts_seen).map(PathBuf::from),
+            None if crate_dir.is_none() => crate_dir = Some(PathBuf::from(arg)),
+            None => Failure::Usage(cli::usage()).exit(),
+        }
+    }
+    let (crate_dir, out) = match (crate_dir, out) {
+        (Some(crate_dir), Some(out)) => (crate_dir, out),
+        _ => Failure::Usage(cli::usage()).exit(),
+    };
+    if let Err(e) = import::import_crate(&crate_dir, &out) {
+        fatal_exit(&format!(
+            "cargo-single: error importing {}: {}",
+            crate_dir.to_string_lossy(),
+            e
+        ));
+    }
+}
+
+// Dependencies of a preset, which is a list of dependencies in the form taken
+// by "--deps", in the "presets" table of the configuration file.
+fn preset_deps(name: &str) -> Result<Vec<DepSpec>, Box<dyn Error>> {
+    let config = config::load()?;
+    let specs = match config.get_list(&format!("presets.{}", name)) {
+        Some(specs) => specs,
+        None if config.table_keys("presets").is_empty() => {
+            return Err("no presets in the configuration file".into())
+        }
+        None => {
+            return Err(format!(
+                "no preset named {}, expected one of: {}",
+                name,
+                config.table_keys("presets").join(", ")
+            )
+            .into())
+        }
+    };
+    let mut deps = vec![];
+    for spec in specs {
+        deps.extend(scaffold::parse_deps(spec).map_err(|e| format!("preset {}: {}", name, e))?);
+    }
+    Ok(deps)
+}
+
+// The dependencies of the preset, if any, followed by those given with
+// "--deps". A dependency given more than once is only kept the first time.
+fn requested_deps(
+    preset: Option<&str>,
+    spec: Option<&str>,
+) -> Result<Vec<DepSpec>, Box<dyn Error>> {
+    let mut deps = match preset {
+        Some(name) => preset_deps(name)?,
+        None => vec![],
+    };
+    if let Some(spec) = spec {
+        deps.extend(scaffold::parse_deps(spec)?);
+    }
+    let mut seen = HashSet::new();
+    deps.retain(|dep| seen.insert(dep.name.clone()));
+    Ok(deps)
+}
+
+// Gives each dependency a requirement for its latest version on crates.io.
+fn with_versions(deps: Vec<DepSpec>) -> Vec<(DepSpec, String)> {
+    deps.into_iter()
+        .map(|dep| {
+            let version = match index::latest_release(&dep.name) {
+                Ok(version) => search::requirement(&version.to_string()),
+                Err(e) => {
+                    eprintln!(
+                        "cargo-single: warning: can't look up the version of {}: {}",
+                        dep.name, e
+                    );
+                    "*".to_owned()
+                }
+            };
+            (dep, version)
+        })
+        .collect()
+}
+
+fn add_main(file_src: &Path, rest: Vec<String>, preset: Option<&str>) {
+    let spec = match &rest[..] {
+        [] => None,
+        [option, spec] if option == "--deps" => Some(spec.as_str()),
+        _ => Failure::Usage(
+            "cargo-single: add accepts only \"--deps <crate>[+<feature>...],...\"".to_owned(),
+        )
+        .exit(),
+    };
+    let deps = requested_deps(preset, spec)
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    if deps.is_empty() {
+        Failure::Usage("cargo-single: add needs --deps or --preset".to_owned()).exit();
+    }
+    let source = fs::read_to_string(file_src).unwrap_or_else(|e| {
+        Failure::SourceNotFound(format!(
+            "cargo-single: error reading {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit()
+    });
+    // Dependencies which are already listed are left alone.
+    let present: HashSet<_> = upgrade::find_requirements(&source)
+        .into_iter()
+        .map(|req| req.name)
+        .collect();
+    let (listed, deps): (Vec<_>, Vec<_>) = deps
+        .into_iter()
+        .partition(|dep| present.contains(&dep.name));
+    for dep in listed {
+        eprintln!("cargo-single: {} is already a dependency", dep.name);
+    }
+    if deps.is_empty() {
+        return;
+    }
+    // Written in place, so that the hard link in the project is kept.
+    let source = scaffold::add_deps(&source, &with_versions(deps), None);
+    if let Err(e) = fs::write(file_src, source) {
+        fatal_exit(&format!(
+            "cargo-single: error writing {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ));
+    }
+}
+
+// Writes the source file, from the template if one is given, whose project is
+// then created as usual. The arguments are an optional "--deps <deps>" and
+// "--edition <edition>"; the dependencies of the preset, if given, come first.
+fn new_main(orig_src: &str, rest: Vec<String>, template: Option<String>, preset: Option<&str>) {
+    let mut spec = None;
+    let mut edition = None;
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        match (arg.as_str(), rest.next()) {
+            ("--deps", Some(value)) => spec = Some(value),
+            ("--edition", Some(value)) if scaffold::EDITIONS.contains(&value.as_str()) => {
+                edition = Some(value)
+            }
+            _ => Failure::Usage(
+                "cargo-single: new accepts only \"--deps <crate>[+<feature>...],...\" and \
+                 \"--edition <edition>\""
+                    .to_owned(),
+            )
+            .exit(),
+        }
+    }
+    let mut file_src = PathBuf::from(orig_src);
+    file_src.set_extension("rs");
+    if file_src.exists() || file_src.with_extension("").exists() {
+        fatal_exit(&format!(
+            "cargo-single: {} or its project directory already exists",
+            file_src.to_string_lossy()
+        ));
+    }
+    let deps = requested_deps(preset, spec.as_deref())
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    let deps = with_versions(deps);
+    let script = match template {
+        Some(template) => {
+            let template = read_template(&template)
+                .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+            let vars = [
+                (
+                    "name",
+                    file_src
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+                ("date", sbom::timestamp()[.."YYYY-MM-DD".len()].to_owned()),
+                ("author", author()),
+            ];
+            scaffold::from_template(&template, &vars, &deps, edition.as_deref())
+        }
+        None => scaffold::script(&deps, edition.as_deref()),
+    };
+    if let Err(e) = write_script(&file_src, &script) {
+        fatal_exit(&format!(
+            "cargo-single: error writing {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ));
+    }
+}
+
+// Templates are source files in the "templates" subdirectory of the
+// configuration directory.
+fn read_template(name: &str) -> Result<String, Box<dyn Error>> {
+    let dir = config::config_dir()
+        .ok_or("no configuration directory")?
+        .join("templates");
+    let path = dir.join(format!("{}.rs", name));
+    if path.is_file() {
+        return Ok(fs::read_to_string(path)?);
+    }
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().unwrap_or_default() == "rs")
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    if names.is_empty() {
+        Err(format!("no templates in {}", dir.display()).into())
+    } else {
+        Err(format!(
+            "no template named {}, expected one of: {}",
+            name,
+            names.join(", ")
+        )
+        .into())
+    }
+}
+
+// The author for templates, from the configuration, Git or the user name.
+fn author() -> String {
+    if let Some(author) = config::load()
+        .ok()
+        .and_then(|config| config.get_str("new.author").map(str::to_owned))
+    {
+        return author;
+    }
+    if let Ok(output) = Command::new("git").args(["config", "user.name"]).output() {
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        if output.status.success() && !name.is_empty() {
+            return name;
+        }
+    }
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+// Creates the source file, executable on Unix-like systems.
+fn write_script(file_src: &Path, script: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(file_src)?;
+    file.write_all(script.as_bytes())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = file.metadata()?.permissions();
+        permissions.set_mode(permissions.mode() | 0o111);
+        file.set_permissions(permissions)?;
+    }
+    Ok(())
+}
+
+// Runs the programs required by the program which are out of date, in order.
+fn requires_main(file_src: &Path, is_quiet: bool, dry_run: bool) {
+    let setting = |path: &Path| header_setting(path, "requires");
+    let order = requires::order(file_src, &setting)
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    if order.is_empty() {
+        return;
+    }
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    for path in order {
+        match setting(&path).and_then(|value| requires::is_up_to_date(&path, value.as_deref())) {
+            Ok(true) => {
+                log!(
+                    Debug,
+                    "the required program {} is up to date",
+                    path.display()
+                );
+                continue;
+            }
+            Ok(false) => (),
+            Err(e) => fatal_exit(&format!("cargo-single: {}: {}", path.display(), e)),
+        }
+        if dry_run {
+            println!("would run the required program {}", path.display());
+            continue;
+        }
+        if !is_quiet {
+            eprintln!("cargo-single: running {}", path.display());
+        }
+        match Command::new(&exe)
+            .args(["single", "run"])
+            .arg(&path)
+            .env(requires::SKIP_ENV, "1")
+            .status()
+        {
+            Err(e) => fatal_exit(&format!(
+                "cargo-single: error running {}: {}",
+                path.display(),
+                e
+            )),
+            Ok(status) if !status.success() => fatal_exit(&format!(
+                "cargo-single: the required program {} failed",
+                path.display()
+            )),
+            _ => (),
+        }
+        if let Err(e) = requires::mark(&path) {
+            fatal_exit(&format!(
+                "cargo-single: error recording the run of {}: {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+}
+
+fn edit_main(file_src: &Path, project_dir: &Path, check_args: Option<Vec<String>>) {
+    let check = |check_args: &[String]| {
+        if let Err(e) = relink_source(file_src, project_dir)
+            .and_then(|_| refresh_project(file_src, project_dir))
+        {
+            eprintln!("cargo-single: error refreshing dependencies: {}", e);
+            return;
+        }
+        if let Err(e) = Command::new("cargo").args(check_args).status() {
+            eprintln!("cargo-single: error executing \"cargo check\": {}", e);
+        }
+    };
+    let mut on_save = check_args.map(|check_args| move || check(&check_args));
+    match edit::edit(
+        file_src,
+        on_save.as_mut().map(|on_save| on_save as &mut dyn FnMut()),
+    ) {
+        Err(e) => fatal_exit(&format!("cargo-single: error running the editor: {}", e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The arguments are an optional "--target <function>", followed by arguments
+// for libFuzzer, which may be preceded by "--".
+fn fuzz_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    mut rest: Vec<String>,
+) {
+    let mut target = None;
+    if rest.first().map(String::as_str) == Some("--target") {
+        if rest.len() < 2 {
+            Failure::Usage("cargo-single: --target needs an argument".to_owned()).exit();
+        }
+        target = Some(rest.remove(1));
+        rest.remove(0);
+    }
+    if rest.first().map(String::as_str) == Some("--") {
+        rest.remove(0);
+    }
+    let source = match fs::read_to_string(file_src) {
+        Ok(source) => source,
+        Err(e) => Failure::SourceNotFound(format!(
+            "cargo-single: fatal: {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit(),
+    };
+    let targets = fuzz::find_targets(&source);
+    let target = match target {
+        Some(target) if targets.contains(&target) => target,
+        Some(target) => fatal_exit(&format!(
+            "cargo-single: no public #[cfg(fuzzing)] function named {}",
+            target
+        )),
+        None if targets.len() == 1 => targets[0].clone(),
+        None if targets.is_empty() => {
+            fatal_exit("cargo-single: no public #[cfg(fuzzing)] functions found")
+        }
+        None => Failure::Usage(format!(
+            "cargo-single: several fuzz targets found, choose one with --target: {}",
+            targets.join(", ")
+        ))
+        .exit(),
+    };
+    if let Err(e) = fuzz::prepare(project_dir, &targets) {
+        Failure::ProjectSetup(format!(
+            "cargo-single: error generating the fuzzing crate: {}",
+            e
+        ))
+        .exit();
+    }
+    // cargo-fuzz needs nightly, and finds the fuzzing crate from the current directory.
+    let toolchain = toolchain.unwrap_or_else(|| "+nightly".to_owned());
+    let mut fuzz = Command::new("cargo");
+    fuzz.arg(&toolchain)
+        .args(["fuzz", "run"])
+        .arg(&target)
+        .current_dir(project_dir);
+    if !rest.is_empty() {
+        fuzz.arg("--").args(&rest);
+    }
+    match fuzz.status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"cargo fuzz\": {}",
+            e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The arguments, which may be preceded by "--", are passed to the program in
+// the training run.
+fn pgo_main(
+    project_dir: &Path,
+    toolchain: Option<String>,
+    target_dir: Option<PathBuf>,
+    mut rest: Vec<String>,
+) {
+    if rest.first().map(String::as_str) == Some("--") {
+        rest.remove(0);
+    }
+    let host = match sanitize::host_target(toolchain.as_deref()) {
+        Some(host) => host,
+        None => fatal_exit("cargo-single: can't determine the host target"),
+    };
+    if let Err(e) = pgo::prepare(project_dir, &host) {
+        Failure::ProjectSetup(format!(
+            "cargo-single: error preparing the profile-guided build: {}",
+            e
+        ))
+        .exit();
+    }
+    let build_dir = BuildDir::new(project_dir, target_dir, Some(host.clone()));
+    let build = |config: &str| {
+        let mut cargo = Command::new("cargo");
+        cargo
+            .args(&toolchain)
+            .args(["build", "--release"])
+            .args(build_dir.cargo_args())
+            .arg("--manifest-path")
+            .arg(project_dir.join("Cargo.toml"))
+            .arg("--config")
+            .arg(project_dir.join(config));
+        match cargo.status() {
+            Err(e) => Failure::Cargo(format!(
+                "cargo-single: error executing \"cargo build\": {}",
+                e
+            ))
+            .exit(),
+            Ok(status) if !status.success() => {
+                Failure::ChildExit(status.code().unwrap_or(1)).exit()
+            }
+            _ => (),
+        }
+    };
+    eprintln!("cargo-single: building the instrumented program");
+    build(pgo::GENERATE_CONFIG);
+    let binary = build_dir.binary(
+        &project_dir
+            .file_name()
+            .expect("project name")
+            .to_string_lossy(),
+        true,
+    );
+    eprintln!("cargo-single: running the program to collect a profile");
+    match Command::new(&binary).args(&rest).status() {
+        Err(e) => fatal_exit(&format!(
+            "cargo-single: error running {}: {}",
+            binary.display(),
+            e
+        )),
+        Ok(status) if !status.success() => fatal_exit(&format!(
+            "cargo-single: the training run failed with {}",
+            status
+        )),
+        _ => (),
+    }
+    if let Err(e) = pgo::merge(project_dir, toolchain.as_deref(), &host) {
+        fatal_exit(&format!("cargo-single: error merging the profiles: {}", e));
+    }
+    eprintln!("cargo-single: building the optimized program");
+    build(pgo::USE_CONFIG);
+    println!("{}", binary.display());
+}
+
+// The arguments are an optional "--chip <chip>", which takes precedence over
+// the header, and "--release".
+fn embed_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    target: Option<String>,
+    rest: Vec<String>,
+) {
+    let mut chip = None;
+    let mut release = false;
+    let mut args = rest.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--chip" => chip = args.next(),
+            "--release" => release = true,
+            _ => match arg.strip_prefix("--chip=") {
+                Some(value) => chip = Some(value.to_owned()),
+                None => Failure::Usage(
+                    "cargo-single: embed accepts only \"--chip <chip>\" and \"--release\""
+                        .to_owned(),
+                )
+                .exit(),
+            },
+        }
+    }
+    let setting = |key| {
+        header_setting(file_src, key)
+            .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)))
+    };
+    let chip = match chip.or_else(|| setting("embed.chip")) {
+        Some(chip) => chip,
+        None => Failure::Usage(
+            "cargo-single: embed needs the chip, given with --chip or embed.chip in the header"
+                .to_owned(),
+        )
+        .exit(),
+    };
+    if let Err(e) = embed::check_chip(&chip) {
+        Failure::Usage(format!("cargo-single: {}", e)).exit();
+    }
+    let runner = embed::Runner::parse(setting("embed.runner").as_deref())
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let target = match target {
+        Some(target) => target,
+        None => Failure::Usage(
+            "cargo-single: embed needs the target of the chip, like \
+             `// target = \"thumbv7m-none-eabi\"` in the header"
+                .to_owned(),
+        )
+        .exit(),
+    };
+    if !target_installed(toolchain.as_deref(), &target) {
+        fatal_exit(&format!(
+            "cargo-single: target {0} is not installed; install it with \"rustup target add {0}\"",
+            target
+        ));
+    }
+    // Both come with probe-rs-tools.
+    let (tool, available) = match runner {
+        embed::Runner::ProbeRs => ("probe-rs", tool_available("probe-rs")),
+        embed::Runner::CargoEmbed => (
+            "cargo-embed",
+            cargo_subcommand_available(toolchain.as_deref(), "embed"),
+        ),
+    };
+    if !available {
+        Failure::Cargo(format!(
+            "cargo-single: {} is not installed; install it with \"cargo install probe-rs-tools\"",
+            tool
+        ))
+        .exit();
+    }
+    let rustflags = fs::read_to_string(file_src)
+        .map_err(|e| e.to_string())
+        .and_then(|source| {
+            let path = fs::canonicalize(file_src).map_err(|e| e.to_string())?;
+            embed::rustflags(&source, sidefiles::source_dir(&path))
+        })
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}: {}", file_src.display(), e)));
+    let mut cargo = Command::new("cargo");
+    cargo.args(&toolchain);
+    let written = match runner {
+        embed::Runner::ProbeRs => {
+            cargo
+                .args(["run", "--target", &target, "--manifest-path"])
+                .arg(project_dir.join("Cargo.toml"))
+                .arg("--config")
+                .arg(project_dir.join(embed::CONFIG));
+            embed::write_config(project_dir, &target, &chip, &rustflags)
+        }
+        // cargo-embed runs Cargo itself, which can't be given the configuration,
+        // so the flags are passed in the environment; they apply only to the
+        // program, since the target is given.
+        embed::Runner::CargoEmbed => {
+            cargo
+                .args(["embed", "--target", &target])
+                .current_dir(project_dir)
+                .env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"));
+            embed::write_embed_config(project_dir, &chip)
+        }
+    };
+    if let Err(e) = written {
+        Failure::ProjectSetup(format!(
+            "cargo-single: error writing the embed configuration: {}",
+            e
+        ))
+        .exit();
+    }
+    if release {
+        cargo.arg("--release");
+    }
+    log!(Info, "running {:?}", cargo);
+    match proctree::status(&mut cargo) {
+        Err(e) => Failure::Cargo(format!("cargo-single: error executing \"cargo\": {}", e)).exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The arguments are the checks to run; by default, licenses, bans and advisories.
+fn deny_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    mut rest: Vec<String>,
+) {
+    if !cargo_subcommand_available(toolchain.as_deref(), "deny") {
+        Failure::Cargo(
+            "cargo-single: cargo-deny is not installed; install it with \"cargo install cargo-deny\"".to_owned()).exit();
+    }
+    // A configuration named in the source file has precedence over the per-user one.
+    let deny_config = match header_setting(file_src, "deny") {
+        Ok(Some(path)) => Some(file_src.parent().unwrap_or(Path::new("")).join(path)),
+        Ok(None) => config::config_dir()
+            .map(|dir| dir.join("deny.toml"))
+            .filter(|path| path.is_file()),
+        Err(e) => Failure::SourceNotFound(format!(
+            "cargo-single: fatal: {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit(),
+    };
+    if rest.is_empty() {
+        rest = vec![
+            "licenses".to_owned(),
+            "bans".to_owned(),
+            "advisories".to_owned(),
+        ];
+    }
+    let mut deny = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        deny.arg(toolchain);
+    }
+    deny.args(["deny", "--manifest-path"])
+        .arg(project_dir.join("Cargo.toml"))
+        .arg("check");
+    if let Some(deny_config) = deny_config {
+        deny.arg("--config").arg(deny_config);
+    }
+    match deny.args(&rest).status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"cargo deny\": {}",
+            e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The arguments, which may be preceded by "--", are passed to the program.
+fn flamegraph_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    mut rest: Vec<String>,
+) {
+    if rest.first().map(String::as_str) == Some("--") {
+        rest.remove(0);
+    }
+    if !cargo_subcommand_available(toolchain.as_deref(), "flamegraph") {
+        Failure::Cargo(
+            "cargo-single: cargo-flamegraph is not installed; install it with \"cargo install flamegraph\"".to_owned()).exit();
+    }
+    // The profiler's data is left in the project directory, which cargo-flamegraph
+    // runs in, so the SVG is given with an absolute path.
+    let svg = match fs::canonicalize(file_src) {
+        Ok(path) => path.with_extension("svg"),
+        Err(e) => Failure::SourceNotFound(format!(
+            "cargo-single: fatal: {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit(),
+    };
+    let mut flamegraph = Command::new("cargo");
+    flamegraph
+        .args(&toolchain)
+        .arg("flamegraph")
+        .arg("--output")
+        .arg(&svg)
+        .current_dir(project_dir)
+        // Without debug symbols, the stacks have no function names.
+        .env("CARGO_PROFILE_RELEASE_DEBUG", "true");
+    if !rest.is_empty() {
+        flamegraph.arg("--").args(&rest);
+    }
+    log!(Info, "running {:?}", flamegraph);
+    match flamegraph.status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"cargo flamegraph\": {}",
+            e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => eprintln!("cargo-single: wrote {}", svg.display()),
+    }
+}
+
+// Builds the program in the build directory, with the settings passed to Cargo
+// with --config, and returns the path of the binary.
+fn build_program(
+    project_dir: &Path,
+    toolchain: Option<&str>,
+    build_dir: &BuildDir,
+    release: bool,
+    configs: &[&str],
+) -> PathBuf {
+    let mut cargo = Command::new("cargo");
+    cargo.args(toolchain).arg("build");
+    if release {
+        cargo.arg("--release");
+    }
+    cargo.args(build_dir.cargo_args());
+    cargo
+        .arg("--manifest-path")
+        .arg(project_dir.join("Cargo.toml"));
+    for config in configs {
+        cargo.args(["--config", config]);
+    }
+    match cargo.status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"cargo build\": {}",
+            e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+    build_dir.binary(
+        &project_dir
+            .file_name()
+            .expect("project name")
+            .to_string_lossy(),
+        release,
+    )
+}
+
+// The only argument is an optional "--name <name>".
+fn shim_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    build_dir: &BuildDir,
+    rest: Vec<String>,
+) {
+    let project_name = project_dir
+        .file_name()
+        .expect("project name")
+        .to_string_lossy();
+    let name = match rest.as_slice() {
+        [] => project_name.as_ref(),
+        [opt, name] if opt == "--name" && !name.is_empty() && !name.contains(['/', '\\']) => {
+            name.as_str()
+        }
+        _ => Failure::Usage("cargo-single: shim accepts only \"--name <name>\"".to_owned()).exit(),
+    };
+    let canonical = |path: &Path| {
+        fs::canonicalize(path)
+            .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}: {}", path.display(), e)))
+    };
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    let file_src = canonical(file_src);
+    // The launcher builds with the same target and target directory, so
+    // that it finds the binary where it's built.
+    let build_dir = BuildDir::new(
+        &canonical(project_dir),
+        build_dir.target_dir().map(Path::to_owned),
+        build_dir.target().map(str::to_owned),
+    );
+    let binary = build_dir.binary(&project_name, true);
+    let build_args = build_dir.cargo_args();
+    let shim = shim::Shim {
+        name,
+        exe: &exe,
+        file_src: &file_src,
+        binary: &binary,
+        toolchain: toolchain.as_deref(),
+        build_args: &build_args,
+    };
+    match shim::install(&shim) {
+        Ok(path) => eprintln!("cargo-single: installed {}", path.display()),
+        Err(e) => fatal_exit(&format!("cargo-single: error installing {}: {}", name, e)),
+    }
+}
+
+// The arguments are an optional "--vscode", and the arguments of the program,
+// which may be preceded by "--".
+fn debug_main(
+    project_dir: &Path,
+    toolchain: Option<String>,
+    build_dir: &BuildDir,
+    mut rest: Vec<String>,
+) {
+    let vscode = rest.first().map(String::as_str) == Some("--vscode");
+    if vscode {
+        rest.remove(0);
+    }
+    if rest.first().map(String::as_str) == Some("--") {
+        rest.remove(0);
+    }
+    if vscode {
+        let binary = build_program(project_dir, toolchain.as_deref(), build_dir, false, &[]);
+        let name = project_dir.file_name().expect("project name");
+        match debugger::launch_json(&name.to_string_lossy(), &binary, &rest) {
+            Ok(launch) => println!("{}", launch),
+            Err(e) => fatal_exit(&format!("cargo-single: {}", e)),
+        }
+        return;
+    }
+    // The debugger is found before the build, which may take a while.
+    let debugger =
+        debugger::debugger().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    let binary = build_program(project_dir, toolchain.as_deref(), build_dir, false, &[]);
+    let mut command = debugger::command(&debugger, &binary, &rest);
+    log!(Info, "running {:?}", command);
+    match command.status() {
+        Err(e) => fatal_exit(&format!("cargo-single: error running {}: {}", debugger, e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The arguments, which may be preceded by "--", are passed to the program.
+fn profile_main(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<String>,
+    build_dir: &BuildDir,
+    mut rest: Vec<String>,
+) {
+    if rest.first().map(String::as_str) == Some("--") {
+        rest.remove(0);
+    }
+    let output = record::output_path(file_src)
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    let binary = build_program(
+        project_dir,
+        toolchain.as_deref(),
+        build_dir,
+        true,
+        record::BUILD_CONFIGS,
+    );
+    let mut profiler = record::command(&binary, &output, &rest)
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    log!(Info, "running {:?}", profiler);
+    match profiler.status() {
+        Err(e) => fatal_exit(&format!("cargo-single: error running the profiler: {}", e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => eprintln!("cargo-single: the profile is in {}", output.display()),
+    }
+}
+
+// The arguments are the options of bench::parse_args(), followed by the
+// arguments of the program.
+fn bench_main(
+    project_dir: &Path,
+    toolchain: Option<String>,
+    build_dir: &BuildDir,
+    rest: Vec<String>,
+) {
+    let options = bench::parse_args(rest)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: bench-cmd: {}", e)).exit());
+    if !tool_available("hyperfine") {
+        fatal_exit("cargo-single: bench-cmd needs hyperfine");
+    }
+    let binary = build_program(project_dir, toolchain.as_deref(), build_dir, true, &[]);
+    let mut hyperfine = bench::command(&binary, &options);
+    log!(Info, "running {:?}", hyperfine);
+    match hyperfine.status() {
+        Err(e) => fatal_exit(&format!("cargo-single: error running hyperfine: {}", e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+// The only argument is an optional "--format <format>".
+fn sbom_main(project_dir: &Path, toolchain: Option<String>, rest: Vec<String>) {
+    let format = match rest.as_slice() {
+        [] => "cyclonedx",
+        [opt, format] if opt == "--format" && sbom::FORMATS.contains(&format.as_str()) => format,
+        _ => Failure::Usage(format!(
+            "cargo-single: sbom accepts only \"--format {{{}}}\"",
+            sbom::FORMATS.join("|")
+        ))
+        .exit(),
+    };
+    let metadata = match metadata::load(toolchain.as_deref(), &project_dir.join("Cargo.toml")) {
+        Ok(metadata) => metadata,
+        Err(e) => fatal_exit(&format!(
+            "cargo-single: error reading the dependency graph: {}",
+            e
+        )),
+    };
+    let sbom = match format {
+        "spdx" => sbom::spdx(&metadata),
+        _ => sbom::cyclonedx(&metadata),
+    };
+    println!("{}", sbom);
+}
+
+// The only argument is an optional "--deny <license>[,<license>...]", which adds
+// to the denylist from the configuration.
+fn licenses_main(project_dir: &Path, toolchain: Option<String>, rest: Vec<String>) {
+    let config = config::load().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    let mut denylist = config
+        .get_list("licenses.deny")
+        .unwrap_or_default()
+        .to_vec();
+    match rest.as_slice() {
+        [] => (),
+        [opt, licenses] if opt == "--deny" => {
+            denylist.extend(licenses.split(',').map(|l| l.trim().to_owned()));
+        }
+        _ => Failure::Usage(
+            "cargo-single: licenses accepts only \"--deny <license>[,<license>...]\"".to_owned(),
+        )
+        .exit(),
+    }
+    let metadata = match metadata::load(toolchain.as_deref(), &project_dir.join("Cargo.toml")) {
+        Ok(metadata) => metadata,
+        Err(e) => fatal_exit(&format!(
+            "cargo-single: error reading the dependency graph: {}",
+            e
+        )),
+    };
+    let mut denied = vec![];
+    for (license, packages) in licenses::report(&metadata) {
+        println!("{} ({}): {}", license, packages.len(), packages.join(", "));
+        if license != licenses::UNKNOWN && licenses::is_denied(&license, &denylist) {
+            denied.push(license);
+        }
+    }
+    if !denied.is_empty() {
+        fatal_exit(&format!(
+            "cargo-single: denied licenses found: {}",
+            denied.join("; ")
+        ));
+    }
+}
+
+// Reports suspicious version requirements, failing if they are denied.
+fn lint_main(file_src: &Path, deny: bool) {
+    let source = match fs::read_to_string(file_src) {
+        Ok(source) => source,
+        Err(e) => Failure::SourceNotFound(format!(
+            "cargo-single: fatal: {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit(),
+    };
+    let mut lints = lints::lint(&source);
+    lints.extend(lints::optional_deps(&source));
+    let level = if deny { "error" } else { "warning" };
+    for lint in &lints {
+        eprintln!("cargo-single: {}: {}", level, lint);
+    }
+    if deny && !lints.is_empty() {
+        Failure::Other(format!(
+            "cargo-single: {} denied lints in the dependency comments",
+            lints.len()
+        ))
+        .exit();
+    }
+}
+
+// Warns about dependencies which aren't on crates.io, suggesting crates with
+// similar names, and returns whether they all are. Dependencies whose presence
+// can't be checked are taken to be there.
+fn crate_names_main(file_src: &Path) -> bool {
+    if net::offline() {
+        return true;
+    }
+    let source = match fs::read_to_string(file_src) {
+        Ok(source) => source,
+        Err(_) => return true,
+    };
+    let mut checked = HashSet::new();
+    let mut all_found = true;
+    for req in upgrade::find_requirements(&source) {
+        if !checked.insert(req.package.clone()) || index::exists(&req.package).unwrap_or(true) {
+            continue;
+        }
+        all_found = false;
+        let similar = suggest::similar_crates(&req.package);
+        let hint = match &similar[..] {
+            [] => String::new(),
+            [name] => format!("; did you mean {}?", name),
+            names => format!("; did you mean one of: {}?", names.join(", ")),
+        };
+        eprintln!(
+            "cargo-single: warning: line {}: there's no crate named {} on crates.io{}",
+            req.line + 1,
+            req.package,
+            hint
+        );
+    }
+    all_found
+}
+
+// Prints the direct dependencies, resolving them if needed.
+fn explain_main(project_dir: &Path, toolchain: Option<&str>) {
+    match metadata::load(toolchain, &project_dir.join("Cargo.toml")) {
+        Ok(metadata) => {
+            for line in audit::explain(&metadata) {
+                println!("{}", line);
+            }
+        }
+        Err(e) => fatal_exit(&format!("cargo-single: can't list the dependencies: {}", e)),
+    }
+}
+
+// Warns about yanked or vulnerable dependencies after a refresh. Failing to
+// check isn't an error, since the refresh itself has succeeded.
+fn audit_main(project_dir: &Path, toolchain: Option<String>, is_quiet: bool) {
+    if net::offline() {
+        return;
+    }
+    let warnings = metadata::load(toolchain.as_deref(), &project_dir.join("Cargo.toml"))
+        .and_then(|metadata| audit::check(&metadata));
+    match warnings {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("cargo-single: warning: {}", warning);
+            }
+        }
+        Err(e) if !is_quiet => eprintln!(
+            "cargo-single: can't check for yanked versions and advisories: {}",
+            e
+        ),
+        Err(_) => (),
+    }
+}
+
+// The only argument is an optional "--compatible" or "--latest".
+fn upgrade_main(file_src: &Path, project_dir: &Path, rest: Vec<String>) {
+    let latest = match rest.as_slice() {
+        [] => false,
+        [opt] if opt == "--compatible" => false,
+        [opt] if opt == "--latest" => true,
+        _ => Failure::Usage(
+            "cargo-single: upgrade accepts only \"--compatible\" or \"--latest\"".to_owned(),
+        )
+        .exit(),
+    };
+    let source = match fs::read_to_string(file_src) {
+        Ok(source) => source,
+        Err(e) => Failure::SourceNotFound(format!(
+            "cargo-single: fatal: {}: {}",
+            file_src.to_string_lossy(),
+            e
+        ))
+        .exit(),
+    };
+    let (new_source, changes) = match upgrade::upgrade(&source, latest) {
+        Ok(upgraded) => upgraded,
+        Err(e) => fatal_exit(&format!("cargo-single: error upgrading: {}", e)),
+    };
+    if changes.is_empty() {
+        eprintln!("cargo-single: the dependencies are up to date");
+        return;
+    }
+    // Writing in place keeps the hard link to main.rs.
+    if let Err(e) = fs::write(file_src, new_source)
+        .map_err(Into::into)
+        .and_then(|_| refresh_project(file_src, project_dir))
+    {
+        fatal_exit(&format!("cargo-single: error updating the source: {}", e));
+    }
+    for change in changes {
+        println!("{}", change);
+    }
+}
+
+// Parses the "--dep <deps>" options, which take the same form as with "new",
+// returning the dependencies and the other arguments.
+fn dep_options(mut args: env::Args) -> (Vec<DepSpec>, Vec<String>) {
+    let mut deps = vec![];
+    let mut rest = vec![];
+    while let Some(arg) = args.next() {
+        if arg != "--dep" {
+            rest.push(arg);
+            continue;
+        }
+        match args.next().map(|spec| scaffold::parse_deps(&spec)) {
+            Some(Ok(spec_deps)) => deps.extend(spec_deps),
+            Some(Err(e)) => fatal_exit(&format!("cargo-single: {}", e)),
+            None => Failure::Usage("cargo-single: --dep needs an argument".to_owned()).exit(),
+        }
+    }
+    deps.sort();
+    deps.dedup();
+    (deps, rest)
+}
+
+// The expression may be followed or preceded by "--dep" options.
+fn eval_main(args: env::Args) {
+    let (deps, rest) = dep_options(args);
+    let expression = match <[String; 1]>::try_from(rest) {
+        Ok([expression]) => expression,
+        Err(_) => Failure::Usage(cli::usage()).exit(),
+    };
+    let file_src = match eval::prepare(&expression, &deps) {
+        Ok(file_src) => file_src,
+        Err(e) => fatal_exit(&format!("cargo-single: error writing the program: {}", e)),
+    };
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    match Command::new(exe)
+        .args(["single", "run"])
+        .arg(&file_src)
+        .status()
+    {
+        Err(e) => fatal_exit(&format!("cargo-single: error running the program: {}", e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+fn repl_main(args: env::Args) {
+    let (deps, rest) = dep_options(args);
+    if !rest.is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    if let Err(e) = repl::repl(&deps) {
+        fatal_exit(&format!("cargo-single: {}", e));
+    }
+}
+
+// The position of the source file or project directory in the arguments of a
+// subcommand taking one, which start with the subcommand.
+fn source_position(args: &[String]) -> Option<usize> {
+    let mut pos = 1;
+    while let Some(arg) = args.get(pos) {
+        match cli::find_option(cli::SOURCE_OPTIONS, arg) {
+            Some(spec) if spec.value.is_some() && spec.opt != Opt::Toolchain => pos += 2,
+            Some(_) => pos += 1,
+            None => return Some(pos),
+        }
+    }
+    None
+}
+
+// Runs an external subcommand with the rest of the arguments, exiting with its
+// status.
+fn plugin_main(name: &str, args: env::Args) {
+    let args: Vec<String> = args.collect();
+    let plugin = plugin::find(name).expect("plugin");
+    let project = plugin::project(&args);
+    log!(Info, "running the plugin {}", plugin.display());
+    let mut command = match plugin::command(&plugin, project.as_ref()) {
+        Ok(command) => command,
+        Err(e) => {
+            Failure::ProjectSetup(format!("cargo-single: error preparing the project: {}", e))
+                .exit()
+        }
+    };
+    match command.args(&args).status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing {}: {}",
+            plugin.display(),
+            e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        Ok(_) => (),
+    }
+}
+
+// The arguments are a command line for one of the watched subcommands. Its
+// source file is watched, and the dependencies are refreshed whenever their
+// comments change.
+fn watch_main(args: env::Args) {
+    let args: Vec<String> = args.collect();
+    match args.first() {
+        Some(cmd) if watch::COMMANDS.contains(&cmd.as_str()) => (),
+        Some(cmd) if cli::find_subcommand(cmd).is_some() => Failure::Usage(format!(
+            "cargo-single: {} can't be watched, expected one of: {}",
+            cmd,
+            watch::COMMANDS.join(", ")
+        ))
+        .exit(),
+        _ => Failure::Usage(cli::usage()).exit(),
+    }
+    let orig_src =
+        &args[source_position(&args).unwrap_or_else(|| Failure::Usage(cli::usage()).exit())];
+    if orig_src == "-" {
+        Failure::Usage("cargo-single: the standard input can't be watched".to_owned()).exit();
+    }
+    let mut file_src = PathBuf::from(orig_src);
+    if file_src.is_dir() || file_src.extension().unwrap_or_default() != "rs" {
+        file_src.set_extension("rs");
+    }
+    let project_dir = args
+        .windows(2)
+        .take(source_position(&args).unwrap_or_default())
+        .rev()
+        .find(|pair| pair[0] == "--project-dir")
+        .map(|pair| PathBuf::from(&pair[1]))
+        .unwrap_or_else(|| file_src.with_extension(""));
+    let header = |file_src: &Path| {
+        fs::read_to_string(file_src)
+            .map(|source| {
+                deps::header(&source)
+                    .map(|(_, dep)| dep.to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .ok()
+    };
+    let mut deps = header(&file_src);
+    let mut on_change = || {
+        let current = header(&file_src);
+        if !project_dir.is_dir() {
+            return;
+        }
+        // Editors which replace the file break the hard link in the project.
+        let mut result = relink_source(&file_src, &project_dir);
+        if current != deps {
+            log!(Info, "the header changed, refreshing the manifest");
+            result = result.and_then(|_| refresh_project(&file_src, &project_dir));
+            deps = current;
+        }
+        if let Err(e) = result {
+            eprintln!("cargo-single: error refreshing dependencies: {}", e);
+        }
+    };
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    let mut command = Command::new(exe);
+    command.arg("single").args(&args);
+    // The side files may change with the dependency comments.
+    let paths = || {
+        let dir = file_src.parent().unwrap_or(Path::new(""));
+        let mut paths = vec![file_src.clone()];
+        if let Ok(side_files) = side_files(&file_src) {
+            paths.extend(side_files.iter().map(|path| dir.join(path)));
+        }
+        if let Ok(Some(build)) = header_setting(&file_src, "build") {
+            paths.push(sidefiles::build_script(&file_src, &build));
+        }
+        paths
+    };
+    if let Err(e) = watch::watch(&paths, &mut command, &mut on_change) {
+        fatal_exit(&format!("cargo-single: error watching {}: {}", orig_src, e));
+    }
+}
+
+// The arguments are a command line for one of the subcommands which can build
+// for WebAssembly, which is run again with the WASI target.
+fn wasm_main(args: env::Args) {
+    let mut args: Vec<String> = args.collect();
+    match args.first() {
+        Some(cmd) if wasm::COMMANDS.contains(&cmd.as_str()) => (),
+        Some(cmd) if cli::find_subcommand(cmd).is_some() => Failure::Usage(format!(
+            "cargo-single: {} can't be used with wasm, expected one of: {}",
+            cmd,
+            wasm::COMMANDS.join(", ")
+        ))
+        .exit(),
+        _ => Failure::Usage(cli::usage()).exit(),
+    }
+    let pos = source_position(&args).unwrap_or_else(|| Failure::Usage(cli::usage()).exit());
+    for option in ["--target", "--use-cross", "--container"] {
+        if args[1..pos].iter().any(|arg| arg == option) {
+            Failure::Usage(format!("cargo-single: {} can't be used with wasm", option)).exit();
+        }
+    }
+    let toolchain = args[1..pos].iter().find(|arg| arg.starts_with('+'));
+    if !target_installed(toolchain.map(String::as_str), wasm::TARGET) {
+        fatal_exit(&format!(
+            "cargo-single: target {0} is not installed; install it with \"rustup target add {0}\"",
+            wasm::TARGET
+        ));
+    }
+    args.splice(1..1, ["--target".to_owned(), wasm::TARGET.to_owned()]);
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    let mut command = Command::new(exe);
+    command.arg("single").args(&args);
+    if ["run", "test"].contains(&args[0].as_str()) && env::var_os(wasm::RUNNER_ENV).is_none() {
+        match wasm::runner() {
+            Ok(runner) => command.env(wasm::RUNNER_ENV, runner),
+            Err(e) => fatal_exit(&format!("cargo-single: {}", e)),
+        };
+    }
+    match command.status() {
+        Err(e) => fatal_exit(&format!("cargo-single: fatal: {}", e)),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        _ => (),
+    }
+}
+
+fn search_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    let format = cli::take_format(&mut args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let query = args.join(" ");
+    if query.trim().is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    match search::search(&query) {
+        Ok(found) if format == Format::Json => {
+            let found = found.iter().map(search::Found::to_json).collect();
+            let output = json::object([("crates", json::Value::Array(found))]);
+            println!("{}", cli::versioned(output));
+        }
+        Ok(found) if found.is_empty() => fatal_exit("cargo-single: no crates found"),
+        Ok(found) => {
+            for krate in found {
+                println!("{}", krate.line());
+            }
+        }
+        Err(e) => fatal_exit(&format!("cargo-single: error searching crates.io: {}", e)),
+    }
+}
+
+// Runs a Cargo subcommand for the project of the source file among its
+// arguments, which is replaced with the path of the manifest.
+fn cargo_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    let toolchain = match args.first() {
+        Some(arg) if arg.starts_with('+') => Some(args.remove(0)),
+        _ => None,
+    };
+    if args.is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    let subcommand = args.remove(0);
+    let dashes = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let (pos, project) = match args[..dashes]
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| arg.ends_with(".rs"))
+        .find_map(|(pos, arg)| Some((pos, SingleProject::locate(arg).ok()?)))
+    {
+        Some(found) => found,
+        None => Failure::SourceNotFound(
+            "cargo-single: no existing source file among the arguments".to_owned(),
+        )
+        .exit(),
+    };
+    if let Err(e) = project.prepare() {
+        Failure::ProjectSetup(format!("cargo-single: error preparing the project: {}", e)).exit();
+    }
+    args.splice(
+        pos..=pos,
+        [
+            "--manifest-path".to_owned(),
+            project.dir().join("Cargo.toml").display().to_string(),
+        ],
+    );
+    let mut cargo = Command::new("cargo");
+    cargo.args(toolchain).arg(&subcommand).args(&args);
+    log!(Info, "running {:?}", cargo);
+    match cargo.status() {
+        Err(e) => Failure::Cargo(format!(
+            "cargo-single: error executing \"cargo {}\": {}",
+            subcommand, e
+        ))
+        .exit(),
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        Ok(_) => (),
+    }
+}
+
+// Runs a command for each source file with dependency comments in a
+// directory. The arguments are the options, followed by the directory.
+fn run_all_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    let format = cli::take_format(&mut args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let runall::Args {
+        dir,
+        jobs,
+        command,
+        include,
+        exclude,
+        reports,
+    } = runall::parse_args(args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let scripts = runall::scripts(Path::new(&dir), &include, &exclude)
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}: {}", dir, e)));
+    if scripts.is_empty() {
+        fatal_exit(&format!(
+            "cargo-single: no source files with dependency comments in {}",
+            dir
+        ));
+    }
+    let exe =
+        env::current_exe().unwrap_or_else(|e| fatal_exit(&format!("cargo-single: fatal: {}", e)));
+    let outcomes = runall::run_all(&exe, &command, &scripts, jobs, format == Format::Json);
+    for (report, path) in &reports {
+        if let Err(e) = runall::write_report(report, path, Path::new(&dir), &outcomes) {
+            fatal_exit(&format!(
+                "cargo-single: error writing {}: {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+    if format == Format::Json {
+        println!("{}", cli::versioned(runall::to_json(&outcomes)));
+    } else {
+        eprintln!();
+        for outcome in &outcomes {
+            eprintln!(
+                "{:40} {:>8.1}s {}",
+                outcome.source.display(),
+                outcome.duration.as_secs_f64(),
+                runall::describe(outcome)
+            );
+        }
+    }
+    let failed = outcomes.iter().filter(|outcome| !outcome.success).count();
+    if failed > 0 {
+        fatal_exit(&format!(
+            "cargo-single: {} of {} source files failed",
+            failed,
+            outcomes.len()
+        ));
+    }
+}
+
+fn ps_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    let format = cli::take_format(&mut args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    if !args.is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    let detached = detach::list()
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: error listing programs: {}", e)));
+    if format == Format::Json {
+        println!("{}", cli::versioned(detach::to_json(&detached)));
+        return;
+    }
+    for detached in &detached {
+        println!(
+            "{:20} {:>7} {:8} {}",
+            detached.name,
+            detached.pid,
+            if detached.running {
+                "running"
+            } else {
+                "exited"
+            },
+            detached.source
+        );
+    }
+}
+
+fn stop_main(args: env::Args) {
+    let args: Vec<_> = args.collect();
+    let name = match args.as_slice() {
+        [program] => detach::program_name(program),
+        _ => Failure::Usage(cli::usage()).exit(),
+    };
+    match detach::stop(&name) {
+        Ok(detached) => eprintln!("cargo-single: stopped {} (PID {})", name, detached.pid),
+        Err(e) => fatal_exit(&format!("cargo-single: error stopping {}: {}", name, e)),
+    }
+}
+
+// Runs the command line again with cargo-single in the WSL distribution, and
+// exits with its status.
+fn wsl_main(distro: &str) {
+    let args: Vec<_> = env::args().skip(2).collect();
+    let mut wsl = wsl::command(distro, &args);
+    log!(Info, "running {:?}", wsl);
+    match proctree::status(&mut wsl) {
+        Ok(status) if !status.success() => Failure::ChildExit(status.code().unwrap_or(1)).exit(),
+        Ok(_) => (),
+        Err(e) => fatal_exit(&format!(
+            "cargo-single: the source file is in the WSL distribution {}, where it must be \
+             built, but wsl.exe can't be run: {}",
+            distro, e
+        )),
+    }
+}
+
+// Formats the dependency comments of each source file in place, or only
+// lists the files which aren't formatted with --check.
+fn fmt_header_main(args: env::Args) {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if arg.starts_with('-') => Failure::Usage(cli::usage()).exit(),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        Failure::Usage(cli::usage()).exit();
+    }
+    let count = files.len();
+    let mut failed = 0;
+    let mut unformatted = 0;
+    for mut file_src in files {
+        if file_src.is_dir() || file_src.extension().unwrap_or_default() != "rs" {
+            file_src.set_extension("rs");
+        }
+        let result = fs::read_to_string(&file_src)
+            .map_err(|e| e.to_string())
+            .and_then(|source| Ok((headerfmt::format(&source)?, source)));
+        let (formatted, source) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("cargo-single: {}: {}", file_src.display(), e);
+                failed += 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file_src.display());
+            unformatted += 1;
+            continue;
+        }
+        // Written in place, so that the hard link in the project is kept.
+        if let Err(e) = fs::write(&file_src, formatted) {
+            eprintln!("cargo-single: error writing {}: {}", file_src.display(), e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        Failure::Other(format!(
+            "cargo-single: {} of {} source files couldn't be formatted",
+            failed, count
+        ))
+        .exit();
+    }
+    if unformatted > 0 {
+        Failure::Other(format!(
+            "cargo-single: {} of {} source files aren't formatted",
+            unformatted, count
+        ))
+        .exit();
+    }
+}
+
+// Shows the settings taken from the environment, the header of the source
+// file if given, and the configuration file.
+fn config_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    if args.first().map(String::as_str) != Some("show") {
+        Failure::Usage(cli::usage()).exit();
+    }
+    args.remove(0);
+    let format = cli::take_format(&mut args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let file_src = match args.as_slice() {
+        [] => None,
+        [source] => {
+            let mut file_src = PathBuf::from(source);
+            if file_src.is_dir() || file_src.extension().unwrap_or_default() != "rs" {
+                file_src.set_extension("rs");
+            }
+            Some(file_src)
+        }
+        _ => Failure::Usage(cli::usage()).exit(),
+    };
+    let layers = settings::Layers::load(file_src.as_deref())
+        .unwrap_or_else(|e| fatal_exit(&format!("cargo-single: {}", e)));
+    let shown = settings::show(&layers);
+    if format == Format::Json {
+        println!("{}", cli::versioned(settings::to_json(&shown)));
+        return;
+    }
+    for ((name, value, source), setting) in shown.iter().zip(settings::SETTINGS) {
+        let value = match value {
+            Some(value) => format!("\"{}\"", value),
+            None => "(not set)".to_owned(),
+        };
+        let mut line = format!("{:9} = {:30} # {}", name, value, source);
+        if let Some(options) = setting.options {
+            line.push_str(&format!(", overridden by {}", options));
+        }
+        println!("{}", line);
+    }
+}
+
+// Updates cargo-single to the latest release, showing the release notes of
+// the versions in between. With --check, only shows them.
+fn self_main(args: env::Args) {
+    let args: Vec<_> = args.collect();
+    let check = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["update"] => false,
+        ["update", "--check"] => true,
+        _ => Failure::Usage(cli::usage()).exit(),
+    };
+    let current = selfupdate::current();
+    let latest = match selfupdate::newer_release() {
+        Ok(Some(latest)) => latest,
+        Ok(None) => {
+            println!("cargo-single {} is up to date", current);
+            return;
+        }
+        Err(e) => fatal_exit(&format!(
+            "cargo-single: can't find the latest release: {}",
+            e
+        )),
+    };
+    println!(
+        "cargo-single {} is available, this is {}\n",
+        latest, current
+    );
+    print!("{}", selfupdate::changelog(&latest));
+    if check {
+        return;
+    }
+    if let Err(e) = selfupdate::update(&latest) {
+        fatal_exit(&format!("cargo-single: error updating: {}", e));
+    }
+    println!("cargo-single updated to {}", latest);
+}
+
+// Checks the environment, and the header of the source file if given,
+// printing a fix for each problem. Exits with 1 if any check failed.
+fn doctor_main(args: env::Args) {
+    let mut args: Vec<_> = args.collect();
+    let format = cli::take_format(&mut args)
+        .unwrap_or_else(|e| Failure::Usage(format!("cargo-single: {}", e)).exit());
+    let mut toolchain = None;
+    let mut source = None;
+    for arg in args {
+        match arg.strip_prefix('+') {
+            Some(name) if toolchain.is_none() && source.is_none() => {
+                toolchain = Some(name.to_owned())
+            }
+            _ if source.is_none() => source = Some(arg),
+            _ => Failure::Usage(cli::usage()).exit(),
+        }
+    }
+    let mut checks = vec![];
+    let toolchain_arg = toolchain.as_ref().map(|name| format!("+{}", name));
+    checks.push(("cargo".to_owned(), doctor::cargo(toolchain_arg.as_deref())));
+    checks.push(("rustup".to_owned(), doctor::rustup()));
+    if let Some(name) = toolchain.as_ref() {
+        checks.push((format!("toolchain {}", name), doctor::toolchain(name)));
+    }
+    checks.push(("configuration".to_owned(), doctor::config_file()));
+    checks.push(("cache directory".to_owned(), doctor::cache_dir()));
+    match source.map(PathBuf::from) {
+        Some(mut file_src) => {
+            if file_src.is_dir() || !file_src.exists() {
+                file_src.set_extension("rs");
+            }
+            let dir = sidefiles::source_dir(&file_src).to_owned();
+            checks.push(("hard links".to_owned(), doctor::hard_links(&dir)));
+            checks.extend(doctor_header(&file_src));
+        }
+        None => checks.push(("hard links".to_owned(), doctor::hard_links(Path::new(".")))),
+    }
+    if format == Format::Json {
+        println!("{}", cli::versioned(doctor::to_json(&checks)));
+    } else {
+        for (name, outcome) in &checks {
+            doctor::print(name, outcome);
+        }
+    }
+    let failed = checks
+        .iter()
+        .filter(|(_, outcome)| matches!(outcome, Outcome::Failure(_)))
+        .count();
+    if failed > 0 {
+        Failure::Other(format!(
+            "cargo-single: {} of {} checks failed",
+            failed,
+            checks.len()
+        ))
+        .exit();
+    }
+}
+
+// Checks of the header for "doctor": that the lines are dependencies or
+// tables, and that the settings refer to existing files.
+fn doctor_header(file_src: &Path) -> Vec<(String, Outcome)> {
+    let name = |check: &str| format!("{} {}", file_src.display(), check);
+    let src = match fs::read_to_string(file_src) {
+        Ok(src) => src,
+        Err(e) => {
+            return vec![(
+                name("source file"),
+                Outcome::Failure(format!("{}; give the path of an existing source file", e)),
+            )]
+        }
+    };
+    let mut checks = vec![];
+    let src_dir = std::path::absolute(sidefiles::source_dir(file_src)).unwrap_or_default();
+    let mut problems = vec![];
+    // Settings are only taken from the lines before the first table header.
+    let mut top_level = true;
+    for (line, dep) in deps::header(&src) {
+        let entry = dep.split_once('=');
+        let is_table = dep.starts_with('[') && dep.trim_end().ends_with(']');
+        top_level &= !is_table;
+        if !is_table
+            && !entry.is_some_and(|(key, value)| !key.trim().is_empty() && !value.trim().is_empty())
+        {
+            problems.push(format!(
+                "line {}: \"{}\" isn't a \"name = value\" line or a [table]; \
+                 end the header with a line which isn't a \"// \" comment",
+                line + 1,
+                dep
+            ));
+        } else if !top_level || setting_key(dep).is_none() {
+            let dep = platform_dep(dep).map_or(dep, |(_, dep)| dep);
+            if let Err(e) = deps::expand(dep, &src_dir) {
+                problems.push(format!("line {}: {}", line + 1, e));
+            }
+        }
+    }
+    checks.push((
+        name("header"),
+        if problems.is_empty() {
+            Outcome::Ok(format!("{} lines", deps::header(&src).count()))
+        } else {
+            Outcome::Failure(problems.join("; "))
+        },
+    ));
+    let settings = doctor_settings(file_src);
+    checks.push((
+        name("settings"),
+        match settings {
+            Ok(()) => Outcome::Ok("valid".to_owned()),
+            Err(e) => Outcome::Failure(format!("{}; fix the setting in the header", e)),
+        },
+    ));
+    for lint in lints::lint(&src) {
+        checks.push((name("requirements"), Outcome::Warning(lint)));
+    }
+    for lint in lints::optional_deps(&src) {
+        checks.push((name("features"), Outcome::Warning(lint)));
+    }
+    checks
+}
+
+// The side files and the build script must exist, and the required programs
+// must not require each other.
+fn doctor_settings(file_src: &Path) -> Result<(), Box<dyn Error>> {
+    let dir = sidefiles::source_dir(file_src);
+    if let Some(path) = side_files(file_src)?
+        .iter()
+        .find(|path| !dir.join(path).is_file())
+    {
+        return Err(format!("mod: {} doesn't exist", path.display()).into());
+    }
+    if let Some(build) = header_setting(file_src, "build")? {
+        if !sidefiles::build_script(file_src, &build).is_file() {
+            return Err(format!("build: {} doesn't exist", build).into());
+        }
+    }
+    resources::build_script(file_src, &header_settings(file_src)?)?;
+    let setting = |path: &Path| header_setting(path, "requires");
+    requires::order(file_src, &setting)?;
+    Ok(())
+}
+
+fn completions_main(mut args: env::Args) {
+    let script = match (args.next(), args.next()) {
+        (Some(shell), None) => completions::script(&shell),
+        _ => None,
+    };
+    match script {
+        Some(script) => print!("{}", script),
+        None => Failure::Usage(cli::usage()).exit(),
+    }
+}
+
+// For --dry-run, prints the dependencies which would be written to the
+// manifest, and the side files which would be linked into the project.
+fn print_planned_links(
+    file_src: &Path,
+    project_dir: &Path,
+    refresh: bool,
+) -> Result<(), Box<dyn Error>> {
+    if refresh {
+        let src = fs::read_to_string(file_src)?;
+        println!(
+            "would write {} with the dependencies:",
+            project_dir.join("Cargo.toml").display()
+        );
+        let header = parse_header(&src);
+        for entry in header
+            .manifest_lines()
+            .chain(header.platform_deps())
+            .chain(header.lints())
+        {
+            println!("    {}", entry.text);
+        }
+    }
+    for path in side_files(file_src)? {
+        println!(
+            "would link {} to {}",
+            sidefiles::source_dir(file_src).join(&path).display(),
+            project_dir.join("src").join(&path).display()
+        );
+    }
+    let build_rs = project_dir.join("build.rs");
+    if let Some(build) = header_setting(file_src, "build")? {
+        println!(
+            "would link {} to {}",
+            sidefiles::build_script(file_src, &build).display(),
+            build_rs.display()
+        );
+    } else if resources::build_script(file_src, &header_settings(file_src)?)?.is_some() {
+        println!(
+            "would write the Windows resources build script {}",
+            build_rs.display()
+        );
+    }
+    Ok(())
+}
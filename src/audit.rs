@@ -0,0 +1,191 @@
+// Checks of the resolved dependencies against the crates.io index, for yanked
+// versions, and against the OSV database, which includes the RustSec advisories.
+// Also the description of the direct dependencies for "refresh --explain".
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use crate::index::{index_versions, latest_release};
+use crate::json::{self, Value};
+use crate::metadata::{Metadata, Package};
+use crate::net;
+use crate::version::Version;
+
+const CRATES_IO_SOURCES: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+const OSV_URL: &str = "https://api.osv.dev/v1";
+
+fn from_crates_io(package: &Package) -> bool {
+    package
+        .source
+        .as_deref()
+        .is_some_and(|source| CRATES_IO_SOURCES.contains(&source))
+}
+
+fn check_yanked(package: &Package) -> Result<Option<String>, Box<dyn Error>> {
+    let current = match Version::parse(&package.version) {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+    let versions = index_versions(&package.name)?;
+    if !versions.iter().any(|(v, yanked)| *v == current && *yanked) {
+        return Ok(None);
+    }
+    let replacement = versions
+        .iter()
+        .filter(|(v, yanked)| {
+            !yanked && current.is_compatible(v) && (current.is_prerelease() || !v.is_prerelease())
+        })
+        .map(|(v, _)| v)
+        .max();
+    Ok(Some(match replacement {
+        Some(v) => format!(
+            "{} {} is yanked; the latest compatible version is {}",
+            package.name, package.version, v
+        ),
+        None => format!(
+            "{} {} is yanked, and there is no compatible replacement",
+            package.name, package.version
+        ),
+    }))
+}
+
+fn osv_query(package: &Package) -> Value {
+    json::object([
+        (
+            "package",
+            json::object([
+                ("name", package.name.as_str().into()),
+                ("ecosystem", "crates.io".into()),
+            ]),
+        ),
+        ("version", package.version.as_str().into()),
+    ])
+}
+
+// The lowest version above the current one in which an advisory is fixed.
+fn fixed_version(advisory: &Value, package: &Package) -> Option<Version> {
+    let current = Version::parse(&package.version)?;
+    advisory
+        .get("affected")
+        .map(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter(|affected| {
+            affected.get("package").and_then(|p| p.str_at("name")) == Some(package.name.as_str())
+        })
+        .flat_map(|affected| {
+            affected
+                .get("ranges")
+                .map(Value::as_array)
+                .unwrap_or_default()
+        })
+        .flat_map(|range| range.get("events").map(Value::as_array).unwrap_or_default())
+        .filter_map(|event| event.str_at("fixed").and_then(Version::parse))
+        .filter(|fixed| *fixed > current)
+        .min()
+}
+
+fn check_advisories(packages: &[&Package]) -> Result<Vec<String>, Box<dyn Error>> {
+    let queries = Value::Array(packages.iter().map(|p| osv_query(p)).collect());
+    let response = json::parse(&net::post_json(
+        &format!("{}/querybatch", OSV_URL),
+        &json::object([("queries", queries)]).to_string(),
+    )?)?;
+    let mut warnings = vec![];
+    let results = response
+        .get("results")
+        .map(Value::as_array)
+        .unwrap_or_default();
+    for (package, result) in packages.iter().zip(results) {
+        // The same advisory may be listed under several ids.
+        let mut seen = HashSet::new();
+        for id in result
+            .get("vulns")
+            .map(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|vuln| vuln.str_at("id"))
+        {
+            if seen.contains(id) {
+                continue;
+            }
+            let advisory = json::parse(&net::get(&format!("{}/vulns/{}", OSV_URL, id))?)?;
+            seen.insert(id.to_owned());
+            for alias in advisory
+                .get("aliases")
+                .map(Value::as_array)
+                .unwrap_or_default()
+            {
+                seen.extend(alias.as_str().map(str::to_owned));
+            }
+            let summary = advisory.str_at("summary").unwrap_or("no summary");
+            let fix = match fixed_version(&advisory, package) {
+                Some(fixed) => format!("fixed in {}", fixed),
+                None => "no fixed version is available".to_owned(),
+            };
+            warnings.push(format!(
+                "{} {} is affected by {} ({}); {}",
+                package.name, package.version, id, summary, fix
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+// Returns a warning for each yanked direct dependency, and for each advisory
+// affecting any dependency from crates.io.
+pub fn check(metadata: &Metadata) -> Result<Vec<String>, Box<dyn Error>> {
+    let root = metadata.root_package();
+    let packages: Vec<_> = metadata
+        .packages
+        .iter()
+        .filter(|package| from_crates_io(package))
+        .collect();
+    let mut warnings = vec![];
+    for package in packages.iter().filter(|p| root.deps.contains(&p.id)) {
+        warnings.extend(check_yanked(package)?);
+    }
+    if !packages.is_empty() {
+        warnings.extend(check_advisories(&packages)?);
+    }
+    Ok(warnings)
+}
+
+// A line for each direct dependency, with its resolved version, the latest
+// release of those from crates.io, unless network access is disabled, and its
+// description, with the whitespace collapsed.
+pub fn explain(metadata: &Metadata) -> Vec<String> {
+    let root = metadata.root_package();
+    let mut packages: Vec<_> = metadata
+        .packages
+        .iter()
+        .filter(|package| root.deps.contains(&package.id))
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+        .into_iter()
+        .map(|package| {
+            let latest = if !from_crates_io(package) {
+                " (not from crates.io)".to_owned()
+            } else if net::offline() {
+                String::new()
+            } else {
+                match latest_release(&package.name) {
+                    Ok(latest) => format!(" (latest {})", latest),
+                    Err(_) => String::new(),
+                }
+            };
+            let description = match &package.description {
+                Some(description) => description.split_whitespace().collect::<Vec<_>>().join(" "),
+                None => "no description".to_owned(),
+            };
+            format!(
+                "{} {}{}: {}",
+                package.name, package.version, latest, description
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,66 @@
+// End-to-end timing of a program with hyperfine, for "bench-cmd". The program
+// is built in release mode, and hyperfine runs the binary directly, without a
+// shell in between, so that only the program is timed.
+
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Default)]
+pub struct Options {
+    pub warmup: Option<u32>,
+    pub runs: Option<u32>,
+    // The arguments of the program.
+    pub args: Vec<String>,
+}
+
+// Parses "[--warmup <n>] [--runs <n>] [--] [<arguments>]", where the options
+// end at the first argument which isn't one of them.
+pub fn parse_args(rest: Vec<String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut rest = rest.into_iter().peekable();
+    while let Some(opt) = rest.next_if(|arg| arg == "--warmup" || arg == "--runs") {
+        let count = rest
+            .next()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{} needs a number", opt))?;
+        if opt == "--warmup" {
+            options.warmup = Some(count);
+        } else if count == 0 {
+            return Err("--runs needs at least one run".to_owned());
+        } else {
+            options.runs = Some(count);
+        }
+    }
+    rest.next_if(|arg| arg == "--");
+    options.args = rest.collect();
+    Ok(options)
+}
+
+// Quotes the argument for the command line given to hyperfine, which splits it
+// like a POSIX shell.
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+pub fn command(binary: &Path, options: &Options) -> Command {
+    let mut line = quote(&binary.to_string_lossy());
+    for arg in &options.args {
+        line.push(' ');
+        line.push_str(&quote(arg));
+    }
+    let mut hyperfine = Command::new("hyperfine");
+    hyperfine.arg("--shell=none");
+    if let Some(warmup) = options.warmup {
+        hyperfine.args(["--warmup", &warmup.to_string()]);
+    }
+    if let Some(runs) = options.runs {
+        hyperfine.args(["--runs", &runs.to_string()]);
+    }
+    hyperfine.arg(line);
+    hyperfine
+}
@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Passed to Cargo with --config only with --build-std, like the sanitizer
+// settings.
+pub const CONFIG: &str = ".cargo/build-std.toml";
+
+// The crates of the standard library to build, from the value of
+// "--build-std=core,alloc"; by default std, as with Cargo's own option.
+pub fn crates(value: &str) -> Result<Vec<String>, String> {
+    if value.is_empty() {
+        return Ok(vec!["std".to_owned()]);
+    }
+    let crates: Vec<_> = value
+        .split(',')
+        .map(|name| name.trim().to_owned())
+        .collect();
+    if crates.iter().any(String::is_empty) {
+        return Err(format!("invalid list of crates \"{}\"", value));
+    }
+    Ok(crates)
+}
+
+pub fn write_config(project_dir: &Path, crates: &[String]) -> Result<(), Box<dyn Error>> {
+    let quoted: Vec<_> = crates.iter().map(|name| format!("\"{}\"", name)).collect();
+    let config = format!("[unstable]\nbuild-std = [{}]\n", quoted.join(", "));
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+// Source files kept in the per-user cache directory, named after a hash of
+// their contents, so that each distinct program is only built once.
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config;
+
+// FNV-1a, which is stable across Rust versions, unlike the standard hasher.
+pub fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Writes the source into the `kind` subdirectory of the cache directory, if
+// it isn't there already, and returns the path of the source file.
+pub fn store(kind: &str, source: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = config::cache_dir().ok_or("no cache directory")?.join(kind);
+    let path = dir.join(format!("{}_{:016x}.rs", kind, hash(source)));
+    if !path.is_file() {
+        fs::create_dir_all(&dir)?;
+        fs::write(&path, source)?;
+    }
+    Ok(path)
+}
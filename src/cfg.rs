@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Passed to Cargo with --config only with --cfg, like the sanitizer settings.
+pub const CONFIG: &str = ".cargo/cfg.toml";
+
+// The argument of rustc's --cfg for "name" or "name=value", where the value
+// may be given quoted or not.
+pub fn parse(spec: &str) -> Result<String, String> {
+    let invalid = || {
+        format!(
+            "invalid --cfg \"{}\", expected name or name=\"value\"",
+            spec
+        )
+    };
+    let (name, value) = match spec.split_once('=') {
+        Some((name, value)) => {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            (name.trim(), Some(value))
+        }
+        None => (spec.trim(), None),
+    };
+    let is_ident = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_ident || value.is_some_and(|value| value.contains(['"', '\\'])) {
+        return Err(invalid());
+    }
+    Ok(match value {
+        Some(value) => format!("{}=\"{}\"", name, value),
+        None => name.to_owned(),
+    })
+}
+
+// The flags are given for all targets with cfg(all()), like those of
+// --native. Each name is also declared for the unexpected_cfgs lint, with any
+// value, since the program may check for others than the one given.
+pub fn write_config(project_dir: &Path, cfgs: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut flags = vec![];
+    for cfg in cfgs {
+        let check = match cfg.split_once('=') {
+            Some((name, _)) => format!("cfg({}, values(any()))", name),
+            None => format!("cfg({})", cfg),
+        };
+        flags.push("\"--cfg\"".to_owned());
+        flags.push(format!("'{}'", cfg));
+        flags.push("\"--check-cfg\"".to_owned());
+        flags.push(format!("'{}'", check));
+    }
+    let config = format!(
+        "[target.'cfg(all())']\nrustflags = [{}]\n",
+        flags.join(", ")
+    );
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
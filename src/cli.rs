@@ -0,0 +1,1122 @@
+// Declarative description of the command line, used for parsing, the usage
+// message and shell completions.
+
+use std::env;
+
+use crate::json::Value;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    // A source file or project directory, followed by program arguments.
+    Source,
+    CrateDir,
+    Shell,
+    // Free-form words, like a search query.
+    Words,
+}
+
+pub struct Subcommand {
+    pub name: &'static str,
+    // Synopsis for subcommands which don't take a source file.
+    pub synopsis: Option<&'static str>,
+    pub help: &'static str,
+    pub options: &'static [OptSpec],
+    pub operand: Operand,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opt {
+    Toolchain,
+    Release,
+    Target,
+    NoQuiet,
+    Quiet,
+    UseCross,
+    Container,
+    ProjectDir,
+    TargetDir,
+    Watch,
+    NoRun,
+    TestThreads,
+    Doc,
+    Sanitize,
+    MinimalVersions,
+    NoNetwork,
+    GitStamp,
+    Codesign,
+    CrateType,
+    Static,
+    Lto,
+    Strip,
+    Optimization,
+    Panic,
+    CodegenUnits,
+    Debuginfo,
+    SplitDebuginfo,
+    Native,
+    BuildStd,
+    Cfg,
+    DocumentPrivateItems,
+    NoDeps,
+    RustdocFlags,
+    KeepGoing,
+    CargoConfig,
+    Sandbox,
+    Stdin,
+    Stdout,
+    Stderr,
+    Tty,
+    NoTty,
+    Detach,
+    Restart,
+    Yes,
+    Checksum,
+    FmtCheck,
+    Edition,
+    ConfigPath,
+    DenyLints,
+    Explain,
+    Template,
+    Preset,
+    LogLevel,
+    DryRun,
+    TimingsSelf,
+    Format,
+    Output,
+}
+
+pub struct OptSpec {
+    pub opt: Opt,
+    // "+" stands for the toolchain prefix, which is followed by its name.
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    pub value: Option<&'static str>,
+    // Subcommands with which the option is valid; if empty, all of them.
+    pub only: &'static [&'static str],
+    pub help: &'static str,
+}
+
+const TOOLCHAIN: OptSpec = OptSpec {
+    opt: Opt::Toolchain,
+    long: "+",
+    short: None,
+    value: Some("toolchain"),
+    only: &[],
+    help: "Name of a toolchain installed with Rustup.",
+};
+
+const LOG_LEVEL: OptSpec = OptSpec {
+    opt: Opt::LogLevel,
+    long: "--log-level",
+    short: None,
+    value: Some("level"),
+    only: &[],
+    help: "Log cargo-single's own operations at the level\n\
+           error, warn, info, debug or trace. The default is\n\
+           taken from CARGO_SINGLE_LOG.",
+};
+
+// Output format of the subcommands which report something, for other programs
+// to read. It's given with --format after the subcommand, or for all of them
+// with --format before it or with CARGO_SINGLE_FORMAT; with json, failures are
+// reported as JSON too.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Json,
+}
+
+pub const FORMAT_ENV: &str = "CARGO_SINGLE_FORMAT";
+
+// Version of the JSON output, raised when a field is removed or its meaning
+// changes. Fields may be added without raising it.
+pub const SCHEMA_VERSION: i64 = 1;
+
+fn parse_format(name: &str) -> Result<Format, String> {
+    match name {
+        "plain" => Ok(Format::Plain),
+        "json" => Ok(Format::Json),
+        format => Err(format!(
+            "unknown format \"{}\", expected plain or json",
+            format
+        )),
+    }
+}
+
+// Sets the format for all subcommands, including those run again by
+// cargo-single, like "watch" does.
+pub fn set_format(name: &str) -> Result<(), String> {
+    parse_format(name)?;
+    env::set_var(FORMAT_ENV, name);
+    Ok(())
+}
+
+// The format for all subcommands; plain if CARGO_SINGLE_FORMAT is invalid,
+// which is reported by the subcommands taking --format.
+pub fn format() -> Format {
+    env::var(FORMAT_ENV)
+        .ok()
+        .and_then(|name| parse_format(&name).ok())
+        .unwrap_or(Format::Plain)
+}
+
+// Adds the schema version to a JSON object printed by a subcommand.
+pub fn versioned(value: Value) -> Value {
+    match value {
+        Value::Object(mut members) => {
+            members.insert(0, ("schema_version".to_owned(), SCHEMA_VERSION.into()));
+            Value::Object(members)
+        }
+        value => value,
+    }
+}
+
+const FORMAT: OptSpec = OptSpec {
+    opt: Opt::Format,
+    long: "--format",
+    short: None,
+    value: Some("format"),
+    only: &[],
+    help: "Output format, plain or json.",
+};
+
+// Options for subcommands which run a Cargo plugin with its own options.
+pub const TOOLCHAIN_OPTIONS: &[OptSpec] = &[TOOLCHAIN, LOG_LEVEL];
+
+const TEMPLATE: OptSpec = OptSpec {
+    opt: Opt::Template,
+    long: "--template",
+    short: None,
+    value: Some("name"),
+    only: &["new"],
+    help: "With \"new\", create the source file from a template\n\
+           in the configuration directory.",
+};
+
+const PRESET: OptSpec = OptSpec {
+    opt: Opt::Preset,
+    long: "--preset",
+    short: None,
+    value: Some("name"),
+    only: &["add", "new"],
+    help: "With \"add\" and \"new\", add the dependencies of a\n\
+           preset from the configuration file.",
+};
+
+pub const NEW_OPTIONS: &[OptSpec] = &[TEMPLATE, PRESET, LOG_LEVEL];
+
+pub const ADD_OPTIONS: &[OptSpec] = &[PRESET, LOG_LEVEL];
+
+pub const SOURCE_OPTIONS: &[OptSpec] = &[
+    TOOLCHAIN,
+    OptSpec {
+        opt: Opt::Release,
+        long: "--release",
+        short: None,
+        value: None,
+        only: &[],
+        help: "Build/check in release mode.",
+    },
+    OptSpec {
+        opt: Opt::Target,
+        long: "--target",
+        short: None,
+        value: Some("target"),
+        only: &[],
+        help: "Use the specified target for building.",
+    },
+    OptSpec {
+        opt: Opt::NoQuiet,
+        long: "--no-quiet",
+        short: None,
+        value: None,
+        only: &[],
+        help: "Don't pass --quiet to Cargo.",
+    },
+    OptSpec {
+        opt: Opt::Quiet,
+        long: "--quiet",
+        short: Some("-q"),
+        value: None,
+        only: &[],
+        help: "Don't show the progress of creating the project,\n\
+               refreshing it or building with Cargo.",
+    },
+    OptSpec {
+        opt: Opt::UseCross,
+        long: "--use-cross",
+        short: None,
+        value: None,
+        only: &[],
+        help: "Use cross instead of Cargo for building. This is\n\
+               the default if the target isn't installed with\n\
+               Rustup and cross is available.",
+    },
+    OptSpec {
+        opt: Opt::Container,
+        long: "--container",
+        short: None,
+        value: Some("image"),
+        only: &[],
+        help: "Run Cargo in a Docker or Podman container made\n\
+               from the specified image.",
+    },
+    OptSpec {
+        opt: Opt::ProjectDir,
+        long: "--project-dir",
+        short: None,
+        value: Some("path"),
+        only: &[],
+        help: "Use the specified directory for the project, instead\n\
+               of the one next to the source file.",
+    },
+    OptSpec {
+        opt: Opt::TargetDir,
+        long: "--target-dir",
+        short: None,
+        value: Some("path"),
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Place the build artifacts in the directory, instead\n\
+               of the target directory of the project.",
+    },
+    OptSpec {
+        opt: Opt::Sanitize,
+        long: "--sanitize",
+        short: None,
+        value: Some("sanitizer"),
+        only: &["build", "check", "nextest", "run", "test"],
+        help: "Build with the address, thread or leak sanitizer.\n\
+               Uses the nightly toolchain by default.",
+    },
+    OptSpec {
+        opt: Opt::BuildStd,
+        long: "--build-std",
+        short: None,
+        value: None,
+        only: &["build", "check", "nextest", "run", "test"],
+        help: "Build the standard library too, or only the crates\n\
+               listed in --build-std=core,alloc. Uses the nightly\n\
+               toolchain by default.",
+    },
+    OptSpec {
+        opt: Opt::Cfg,
+        long: "--cfg",
+        short: None,
+        value: Some("name[=\"value\"]"),
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Set a configuration option for conditional\n\
+               compilation. May be given more than once.",
+    },
+    OptSpec {
+        opt: Opt::DocumentPrivateItems,
+        long: "--document-private-items",
+        short: None,
+        value: None,
+        only: &["doc"],
+        help: "Document the private items too.",
+    },
+    OptSpec {
+        opt: Opt::NoDeps,
+        long: "--no-deps",
+        short: None,
+        value: None,
+        only: &["doc"],
+        help: "Don't document the dependencies.",
+    },
+    OptSpec {
+        opt: Opt::RustdocFlags,
+        long: "--rustdoc-flags",
+        short: None,
+        value: Some("flags"),
+        only: &["doc"],
+        help: "Pass the flags to rustdoc, in addition to those in\n\
+               RUSTDOCFLAGS.",
+    },
+    OptSpec {
+        opt: Opt::KeepGoing,
+        long: "--keep-going",
+        short: None,
+        value: None,
+        only: &["build", "check", "doc", "run"],
+        help: "Build as many crates as possible, rather than\n\
+               stopping at the first one which fails.",
+    },
+    OptSpec {
+        opt: Opt::FmtCheck,
+        long: "--check",
+        short: None,
+        value: None,
+        only: &["fmt"],
+        help: "Check the formatting without changing the source\n\
+               file.",
+    },
+    OptSpec {
+        opt: Opt::Edition,
+        long: "--edition",
+        short: None,
+        value: Some("edition"),
+        only: &["fmt"],
+        help: "Format for this edition instead of the program's.",
+    },
+    OptSpec {
+        opt: Opt::ConfigPath,
+        long: "--config-path",
+        short: None,
+        value: Some("path"),
+        only: &["fmt"],
+        help: "Use this rustfmt configuration instead of the one\n\
+               found next to the source file.",
+    },
+    OptSpec {
+        opt: Opt::CargoConfig,
+        long: "--config",
+        short: None,
+        value: Some("key=value"),
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Override a Cargo configuration value, or pass a\n\
+               configuration file. May be given more than once.",
+    },
+    OptSpec {
+        opt: Opt::MinimalVersions,
+        long: "--minimal-versions",
+        short: None,
+        value: None,
+        only: &["build", "check", "test"],
+        help: "Resolve the dependencies to the lowest versions\n\
+               allowed by the source file. Uses the nightly\n\
+               toolchain by default.",
+    },
+    OptSpec {
+        opt: Opt::NoNetwork,
+        long: "--no-network",
+        short: None,
+        value: None,
+        only: &["build", "check", "doc", "nextest", "refresh", "run", "test"],
+        help: "Run Cargo offline and, if possible, without network\n\
+               access, to build untrusted programs.",
+    },
+    OptSpec {
+        opt: Opt::CrateType,
+        long: "--crate-type",
+        short: None,
+        value: Some("type"),
+        only: &["build", "check"],
+        help: "Build the source file as a cdylib, dylib, rlib or\n\
+               staticlib library.",
+    },
+    OptSpec {
+        opt: Opt::Static,
+        long: "--static",
+        short: None,
+        value: None,
+        only: &["build", "check", "run", "test"],
+        help: "Build a static binary in release mode, for the musl\n\
+               target of the host architecture.",
+    },
+    OptSpec {
+        opt: Opt::Lto,
+        long: "--lto",
+        short: None,
+        value: Some("lto"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Use fat, thin or no (off) link-time optimization.",
+    },
+    OptSpec {
+        opt: Opt::Strip,
+        long: "--strip",
+        short: None,
+        value: Some("strip"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Strip none, debuginfo or symbols from the binary.",
+    },
+    OptSpec {
+        opt: Opt::Optimization,
+        long: "--opt-level",
+        short: None,
+        value: Some("level"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Optimize at the level 0, 1, 2, 3, s or z.",
+    },
+    OptSpec {
+        opt: Opt::Panic,
+        long: "--panic",
+        short: None,
+        value: Some("strategy"),
+        only: &["build", "run"],
+        help: "Unwind or abort on panic.",
+    },
+    OptSpec {
+        opt: Opt::CodegenUnits,
+        long: "--codegen-units",
+        short: None,
+        value: Some("n"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Split each crate into n code generation units.",
+    },
+    OptSpec {
+        opt: Opt::Debuginfo,
+        long: "--debuginfo",
+        short: None,
+        value: Some("level"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Include no (none), line-tables or full debug\n\
+               information.",
+    },
+    OptSpec {
+        opt: Opt::SplitDebuginfo,
+        long: "--split-debuginfo",
+        short: None,
+        value: Some("mode"),
+        only: &["build", "nextest", "run", "test"],
+        help: "Keep the debug information in the binary (off), or\n\
+               in separate files (packed or unpacked).",
+    },
+    OptSpec {
+        opt: Opt::Native,
+        long: "--native",
+        short: None,
+        value: None,
+        only: &["build", "nextest", "run", "test"],
+        help: "Optimize for the CPU of this machine, with\n\
+               -C target-cpu=native.",
+    },
+    OptSpec {
+        opt: Opt::Sandbox,
+        long: "--sandbox",
+        short: None,
+        value: None,
+        only: &["run"],
+        help: "Run the program with access only to the paths in\n\
+               sandbox.fs, and to the network with sandbox.net.",
+    },
+    OptSpec {
+        opt: Opt::Stdin,
+        long: "--stdin",
+        short: None,
+        value: Some("file"),
+        only: &["run"],
+        help: "Read the standard input of the program from\n\
+               the file.",
+    },
+    OptSpec {
+        opt: Opt::Stdout,
+        long: "--stdout",
+        short: None,
+        value: Some("file"),
+        only: &["run"],
+        help: "Append the standard output to the file.",
+    },
+    OptSpec {
+        opt: Opt::Stderr,
+        long: "--stderr",
+        short: None,
+        value: Some("file"),
+        only: &["run"],
+        help: "Append the standard error, including Cargo's\n\
+               messages, to the file.",
+    },
+    OptSpec {
+        opt: Opt::Tty,
+        long: "--tty",
+        short: None,
+        value: None,
+        only: &["run"],
+        help: "Connect the standard streams which aren't redirected\n\
+               to the terminal, and use colors.",
+    },
+    OptSpec {
+        opt: Opt::NoTty,
+        long: "--no-tty",
+        short: None,
+        value: None,
+        only: &["run"],
+        help: "Don't use colors or show progress, and don't let the\n\
+               program read the terminal.",
+    },
+    OptSpec {
+        opt: Opt::Detach,
+        long: "--detach",
+        short: None,
+        value: None,
+        only: &["run"],
+        help: "Build the program and start it in the background,\n\
+               with its output going to a log.",
+    },
+    OptSpec {
+        opt: Opt::Restart,
+        long: "--restart",
+        short: None,
+        value: Some("policy"),
+        only: &["run"],
+        help: "Run the program again when it fails, with on-failure,\n\
+               at most n times with on-failure:max=n.",
+    },
+    OptSpec {
+        opt: Opt::Yes,
+        long: "--yes",
+        short: None,
+        value: None,
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Build a source file from a URL without asking for a\n\
+               confirmation.",
+    },
+    OptSpec {
+        opt: Opt::Checksum,
+        long: "--checksum",
+        short: None,
+        value: Some("sha256:hex"),
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Verify the checksum of a source file from a URL.",
+    },
+    OptSpec {
+        opt: Opt::GitStamp,
+        long: "--git-stamp",
+        short: None,
+        value: None,
+        only: &["build", "check", "nextest", "run", "test"],
+        help: "Set CARGO_SINGLE_GIT_DESCRIBE and\n\
+               CARGO_SINGLE_GIT_COMMIT for the build, from the\n\
+               Git repository of the source file.",
+    },
+    OptSpec {
+        opt: Opt::Codesign,
+        long: "--codesign",
+        short: None,
+        value: None,
+        only: &["build"],
+        help: "On macOS, sign the built program, ad hoc unless\n\
+               an identity is configured.",
+    },
+    OptSpec {
+        opt: Opt::DryRun,
+        long: "--dry-run",
+        short: None,
+        value: None,
+        only: &[
+            "build", "check", "doc", "fmt", "nextest", "refresh", "run", "test",
+        ],
+        help: "Print what would be done, like creating the project\n\
+               and the Cargo command, without doing it.",
+    },
+    OptSpec {
+        opt: Opt::TimingsSelf,
+        long: "--timings-self",
+        short: None,
+        value: None,
+        only: &["build", "check", "doc", "nextest", "run", "test"],
+        help: "Report the time spent by cargo-single in each phase,\n\
+               and by the Cargo command.",
+    },
+    OptSpec {
+        opt: Opt::DenyLints,
+        long: "--deny-lints",
+        short: None,
+        value: None,
+        only: &["refresh"],
+        help: "With \"refresh\", fail if a version requirement\n\
+               is suspicious.",
+    },
+    OptSpec {
+        opt: Opt::Explain,
+        long: "--explain",
+        short: None,
+        value: None,
+        only: &["refresh"],
+        help: "With \"refresh\", print each dependency with its\n\
+               version, latest version and description.",
+    },
+    LOG_LEVEL,
+    TEMPLATE,
+    PRESET,
+    OptSpec {
+        opt: Opt::Watch,
+        long: "--watch",
+        short: None,
+        value: None,
+        only: &["edit"],
+        help: "With \"edit\", check the program whenever the\n\
+               source file is saved.",
+    },
+    OptSpec {
+        opt: Opt::NoRun,
+        long: "--no-run",
+        short: None,
+        value: None,
+        only: &["test"],
+        help: "With \"test\", compile the tests but don't run them.",
+    },
+    OptSpec {
+        opt: Opt::TestThreads,
+        long: "--test-threads",
+        short: None,
+        value: Some("n"),
+        only: &["test", "nextest"],
+        help: "With \"test\" and \"nextest\", number of threads\n\
+               used for running the tests.",
+    },
+    OptSpec {
+        opt: Opt::Doc,
+        long: "--doc",
+        short: None,
+        value: None,
+        only: &["test"],
+        help: "With \"test\", run the documentation tests. Items\n\
+               used in the tests must be public.",
+    },
+];
+
+pub const IMPORT_OPTIONS: &[OptSpec] = &[OptSpec {
+    opt: Opt::Output,
+    long: "--output",
+    short: Some("-o"),
+    value: Some("source-file"),
+    only: &[],
+    help: "Name of the source file to write.",
+}];
+
+pub const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "build",
+        synopsis: None,
+        help: "Build the program. The arguments, like --features\n\
+               <features>, are passed to \"cargo build\".",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "check",
+        synopsis: None,
+        help: "Check the program for errors. The arguments are\n\
+               passed to \"cargo check\".",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "doc",
+        synopsis: None,
+        help: "Build the documentation of the program. The\n\
+               arguments, like --open, are passed to \"cargo doc\".",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "fmt",
+        synopsis: None,
+        help: "Format the source file. The arguments are passed\n\
+               to rustfmt.",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "fmt-header",
+        synopsis: Some("fmt-header [--check] <source-file> ..."),
+        help: "Format the dependency comments, sorting the\n\
+               dependencies. With --check, list the files which\n\
+               aren't formatted instead, and fail if there are any.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "edit",
+        synopsis: None,
+        help: "Create the project if needed, refresh the\n\
+               dependencies, and open the source file in\n\
+               an editor.",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "new",
+        synopsis: None,
+        help: "Create a source file and its project. The arguments\n\
+               are an optional \"--deps <crate>[+<feature>...],...\"\n\
+               and \"--edition <edition>\".",
+        options: NEW_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "add",
+        synopsis: None,
+        help: "Add dependencies to the source file, and refresh\n\
+               them. The argument is an optional\n\
+               \"--deps <crate>[+<feature>...],...\".",
+        options: ADD_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "refresh",
+        synopsis: None,
+        help: "Re-read the source file and update the dependencies\n\
+               in Cargo.toml.",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "run",
+        synopsis: None,
+        help: "Build and run the program.",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "test",
+        synopsis: None,
+        help: "Build and run the tests in the source file.\n\
+               The arguments are passed to the test harness.",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "nextest",
+        synopsis: None,
+        help: "Run the tests in the source file with\n\
+               cargo-nextest. The arguments are passed to\n\
+               \"cargo nextest run\".",
+        options: SOURCE_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "fuzz",
+        synopsis: None,
+        help: "Fuzz a public #[cfg(fuzzing)] function taking &[u8]\n\
+               with cargo-fuzz. The arguments are an optional\n\
+               \"--target <function>\", and libFuzzer arguments.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "pgo",
+        synopsis: None,
+        help: "Build the program in release mode with profile-guided\n\
+               optimization. The arguments are passed to the program\n\
+               in the run which collects the profile.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "shim",
+        synopsis: None,
+        help: "Install a launcher for the program into Cargo's bin\n\
+               directory, which rebuilds it when the source file\n\
+               changes. The only argument is an optional\n\
+               \"--name <name>\" for the launcher.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "debug",
+        synopsis: None,
+        help: "Build the program and run it under a debugger. The\n\
+               arguments are an optional \"--vscode\", which prints a\n\
+               VS Code launch.json instead, and the arguments of the\n\
+               program.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "flamegraph",
+        synopsis: None,
+        help: "Profile the program built in release mode with\n\
+               cargo-flamegraph, writing an SVG next to the source\n\
+               file. The arguments are passed to the program.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "profile",
+        synopsis: None,
+        help: "Record a profile of the program built in release mode\n\
+               with perf on Linux, or xctrace on macOS. The\n\
+               arguments are passed to the program.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "bench-cmd",
+        synopsis: None,
+        help: "Time the program built in release mode with hyperfine.\n\
+               The arguments are an optional \"--warmup <n>\" and\n\
+               \"--runs <n>\", and the arguments of the program.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "embed",
+        synopsis: None,
+        help: "Build the program for a microcontroller, flash it\n\
+               with probe-rs and show its RTT output. The arguments\n\
+               are an optional \"--chip <chip>\" and \"--release\".",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "deny",
+        synopsis: None,
+        help: "Check the dependencies with cargo-deny. The\n\
+               arguments are the checks to run.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "sbom",
+        synopsis: None,
+        help: "Print a software bill of materials for the program.\n\
+               The only argument is an optional\n\
+               \"--format {cyclonedx|spdx}\".",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "licenses",
+        synopsis: None,
+        help: "List the licenses of all dependencies. The only\n\
+               argument is an optional\n\
+               \"--deny <license>[,<license>...]\", which makes the\n\
+               command fail if a dependency has only denied\n\
+               licenses.",
+        options: TOOLCHAIN_OPTIONS,
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "upgrade",
+        synopsis: None,
+        help: "Upgrade the dependency versions in the source file\n\
+               to the newest ones from crates.io. The only\n\
+               argument is an optional \"--compatible\", the\n\
+               default, which keeps the upgrades compatible, or\n\
+               \"--latest\".",
+        options: &[],
+        operand: Operand::Source,
+    },
+    Subcommand {
+        name: "import",
+        synopsis: Some("import <crate-dir> -o <source-file>"),
+        help: "Turn a crate with a single binary and no other\n\
+               sources into a source file, listing its\n\
+               dependencies at the top.",
+        options: IMPORT_OPTIONS,
+        operand: Operand::CrateDir,
+    },
+    Subcommand {
+        name: "eval",
+        synopsis: Some("eval <expression> [--dep <crate>[+<feature>...]] ..."),
+        help: "Build and run a program printing the value of an\n\
+               expression, or running statements ending with \";\".",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "repl",
+        synopsis: Some("repl [--dep <crate>[+<feature>...]] ..."),
+        help: "Read items, statements and expressions, and run\n\
+               them in a program kept between inputs.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "run-all",
+        synopsis: Some(
+            "run-all <source-dir> [--format <format>] [--jobs <n>] [--command {run|check|test}] [--include <glob>] [--exclude <glob>] [--report {junit|json}:<file>] ...",
+        ),
+        help: "Build all the source files with dependency comments\n\
+               in the directory, or matching the --include patterns,\n\
+               run those which built, or check or test them with\n\
+               --command, and summarize their exit statuses.",
+        options: &[FORMAT],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "ps",
+        synopsis: Some("ps [--format <format>]"),
+        help: "List the programs started with run --detach.",
+        options: &[FORMAT],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "stop",
+        synopsis: Some("stop <program>"),
+        help: "Stop a program started with run --detach.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "doctor",
+        synopsis: Some("doctor [--format <format>] [+toolchain] [<source-file>]"),
+        help: "Check the environment, and the header of the\n\
+               source file if given, printing how to fix the\n\
+               problems found.",
+        options: &[FORMAT],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "self",
+        synopsis: Some("self update [--check]"),
+        help: "Update cargo-single to the latest release, showing\n\
+               the release notes. With --check, only show them.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "config",
+        synopsis: Some("config show [--format <format>] [<source-file>]"),
+        help: "Show the toolchain, target, quiet, cache-dir and\n\
+               editor settings, and where each comes from.",
+        options: &[FORMAT],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "cargo",
+        synopsis: Some(
+            "cargo [+toolchain] <subcommand> [<argument> ...] <source-file> [<argument> ...]",
+        ),
+        help: "Run any Cargo subcommand for the project of the\n\
+               source file, refreshing it first if needed.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "watch",
+        synopsis: Some("watch <command> [<option> ...] {<source-file>|<source-dir>} [<arguments>]"),
+        help: "Run build, check, nextest, run or test again\n\
+               whenever the source file changes.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "wasm",
+        synopsis: Some("wasm <command> [<option> ...] {<source-file>|<source-dir>} [<arguments>]"),
+        help: "Build, check, run or test the program for the\n\
+               wasm32-wasip1 target, running it with wasmtime\n\
+               or wasmer.",
+        options: &[],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "search",
+        synopsis: Some("search [--format <format>] <query> ..."),
+        help: "Search crates.io and print the crates found as\n\
+               dependency comments.",
+        options: &[FORMAT],
+        operand: Operand::Words,
+    },
+    Subcommand {
+        name: "completions",
+        synopsis: Some("completions {bash|zsh|fish|powershell}"),
+        help: "Print a completion script for the specified shell.",
+        options: &[],
+        operand: Operand::Shell,
+    },
+];
+
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+const HELP_COLUMN: usize = 32;
+
+pub fn find_subcommand(name: &str) -> Option<&'static Subcommand> {
+    SUBCOMMANDS.iter().find(|sub| sub.name == name)
+}
+
+pub fn find_option(options: &'static [OptSpec], arg: &str) -> Option<&'static OptSpec> {
+    options.iter().find(|spec| {
+        if spec.long == "+" {
+            arg.len() > 1 && arg.starts_with('+')
+        } else {
+            spec.long == arg
+                || spec.short == Some(arg)
+                // The crates to build are given with "=", since the option
+                // may also be given without them.
+                || (spec.opt == Opt::BuildStd
+                    && arg
+                        .strip_prefix(spec.long)
+                        .is_some_and(|crates| crates.starts_with('=')))
+        }
+    })
+}
+
+// Takes a leading "--format <format>" from the arguments of a subcommand which
+// reports something.
+pub fn take_format(args: &mut Vec<String>) -> Result<Format, String> {
+    if args.first().map(String::as_str) != Some(FORMAT.long) {
+        return match env::var(FORMAT_ENV) {
+            Ok(name) => parse_format(&name).map_err(|e| format!("{}: {}", FORMAT_ENV, e)),
+            Err(_) => Ok(Format::Plain),
+        };
+    }
+    args.remove(0);
+    if args.is_empty() {
+        return Err(format!("{} needs a value", FORMAT.long));
+    }
+    parse_format(&args.remove(0))
+}
+
+impl OptSpec {
+    pub fn display(&self) -> String {
+        if self.long == "+" {
+            return "toolchain".to_owned();
+        }
+        self.long.to_owned()
+    }
+
+    fn usage(&self) -> String {
+        let mut usage = match (self.long, self.short) {
+            ("+", _) => "+".to_owned(),
+            (long, Some(short)) => format!("{}, {}", short, long),
+            (long, None) => long.to_owned(),
+        };
+        if let Some(value) = self.value {
+            if self.long != "+" {
+                usage.push(' ');
+            }
+            usage.push_str(&format!("<{}>", value));
+        }
+        usage
+    }
+}
+
+fn push_entry(text: &mut String, name: &str, help: &str) {
+    let mut lines = help.lines();
+    text.push_str(&format!(
+        "    {:<width$}{}\n",
+        name,
+        lines.next().unwrap_or_default(),
+        width = HELP_COLUMN - 4
+    ));
+    for line in lines {
+        text.push_str(&format!("{:<width$}{}\n", "", line, width = HELP_COLUMN));
+    }
+}
+
+pub fn usage() -> String {
+    let mut text = "Usage:\n    cargo-single <command> [<option> ...] \
+                    {<source-file>|<source-dir>} [<arguments>]\n"
+        .to_owned();
+    for sub in SUBCOMMANDS {
+        if let Some(synopsis) = sub.synopsis {
+            text.push_str(&format!("    cargo-single {}\n", synopsis));
+        }
+    }
+    text.push_str("\n<command> is one of:\n");
+    for sub in SUBCOMMANDS
+        .iter()
+        .filter(|sub| sub.operand == Operand::Source)
+    {
+        push_entry(&mut text, sub.name, sub.help);
+    }
+    text.push_str("\nOther commands:\n");
+    for sub in SUBCOMMANDS
+        .iter()
+        .filter(|sub| sub.operand != Operand::Source)
+    {
+        push_entry(&mut text, sub.name, sub.help);
+    }
+    text.push_str("\n<option> is one or more of:\n");
+    for spec in SOURCE_OPTIONS {
+        push_entry(&mut text, &spec.usage(), spec.help);
+    }
+    text.push_str(
+        "\nAny other command runs cargo-single-<command> from the PATH, if there is one.\n\
+         \"--format json\" before any command makes the commands which report something\n\
+         print JSON, and all of them report failures as JSON.",
+    );
+    text
+}
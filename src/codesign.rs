@@ -0,0 +1,62 @@
+// Signing of the built program on macOS, for "build --codesign", so that it
+// can be copied to other Macs, where Gatekeeper blocks unsigned programs. The
+// identity is taken from CARGO_SINGLE_CODESIGN_IDENTITY, or the "identity" key
+// in the [codesign] table of the configuration file; without one, the program
+// is signed ad hoc, which is enough on Apple silicon for programs which don't
+// come from a download. The quarantine attribute, which a download leaves on
+// files and a copy keeps, is removed too.
+
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config;
+
+// The identity for ad-hoc signing.
+const AD_HOC: &str = "-";
+
+pub fn identity() -> Result<String, String> {
+    if let Some(identity) = env::var("CARGO_SINGLE_CODESIGN_IDENTITY")
+        .ok()
+        .filter(|identity| !identity.trim().is_empty())
+    {
+        return Ok(identity);
+    }
+    Ok(config::load()?
+        .get_str("codesign.identity")
+        .unwrap_or(AD_HOC)
+        .to_owned())
+}
+
+// A real identity also gets the hardened runtime and a secure timestamp, which
+// notarization requires.
+pub fn command(binary: &Path, identity: &str) -> Command {
+    let mut codesign = Command::new("codesign");
+    codesign.args(["--force", "--sign", identity]);
+    if identity != AD_HOC {
+        codesign.args(["--options", "runtime", "--timestamp"]);
+    }
+    codesign.arg(binary);
+    codesign
+}
+
+pub fn sign(binary: &Path, identity: &str) -> Result<(), Box<dyn Error>> {
+    let output = command(binary, identity)
+        .output()
+        .map_err(|e| format!("codesign: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "codesign failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    // The attribute is usually missing, which xattr reports as an error.
+    let _ = Command::new("xattr")
+        .args(["-d", "com.apple.quarantine"])
+        .arg(binary)
+        .stderr(Stdio::null())
+        .status();
+    Ok(())
+}
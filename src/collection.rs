@@ -0,0 +1,97 @@
+// Curated collections of programs: cargo-single.toml, in the current directory
+// or one of its ancestors, names programs which can then be given as
+// "@<name>" instead of a source file, each with its own default settings,
+// features and arguments. A name is either a path:
+//
+//     [scripts]
+//     report = "reports/daily.rs"
+//
+// or a table with the path and the defaults:
+//
+//     [scripts.nightly-report]
+//     path = "reports/daily.rs"
+//     toolchain = "nightly"
+//     features = ["fast"]
+//     args = ["--since", "yesterday"]
+//
+// Paths are relative to the directory of cargo-single.toml.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ConfigValue};
+
+pub const FILE: &str = "cargo-single.toml";
+
+// Keys of a program's table which are settings, resolved like the ones in the
+// header.
+const SETTINGS: &[&str] = &["toolchain", "target", "quiet"];
+
+pub struct Script {
+    pub path: PathBuf,
+    pub settings: Vec<(String, String)>,
+    pub features: Vec<String>,
+    // Used when no arguments are given on the command line.
+    pub args: Vec<String>,
+}
+
+fn find() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn resolve(name: &str) -> Result<Script, String> {
+    let manifest = find().ok_or_else(|| format!("@{}: no {} found", name, FILE))?;
+    let text =
+        fs::read_to_string(&manifest).map_err(|e| format!("{}: {}", manifest.display(), e))?;
+    let config = Config::parse(&text).map_err(|e| format!("{}: {}", manifest.display(), e))?;
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let key = format!("scripts.{}", name);
+    if let Some(path) = config.get_str(&key) {
+        return Ok(Script {
+            path: dir.join(path),
+            settings: vec![],
+            features: vec![],
+            args: vec![],
+        });
+    }
+    let path = config.get_str(&format!("{}.path", key)).ok_or_else(|| {
+        format!(
+            "{}: no program named {} in [scripts]",
+            manifest.display(),
+            name
+        )
+    })?;
+    let mut settings = vec![];
+    for setting in SETTINGS {
+        match config.get(&format!("{}.{}", key, setting)) {
+            Some(ConfigValue::String(value)) => settings.push((setting.to_string(), value.clone())),
+            Some(ConfigValue::Bool(value)) => {
+                settings.push((setting.to_string(), value.to_string()))
+            }
+            Some(_) => {
+                return Err(format!(
+                    "{}: invalid {} for {}",
+                    manifest.display(),
+                    setting,
+                    name
+                ))
+            }
+            None => (),
+        }
+    }
+    let list = |item: &str| {
+        config
+            .get_list(&format!("{}.{}", key, item))
+            .unwrap_or_default()
+    };
+    Ok(Script {
+        path: dir.join(path),
+        settings,
+        features: list("features").to_vec(),
+        args: list("args").to_vec(),
+    })
+}
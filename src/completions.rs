@@ -0,0 +1,322 @@
+// Completion scripts for the supported shells, generated from the command line
+// description. The first argument is always "single", as when the program is
+// invoked by Cargo.
+
+use crate::cli::{Operand, OptSpec, Subcommand, SHELLS, SUBCOMMANDS};
+
+pub fn script(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        "powershell" => Some(powershell()),
+        _ => None,
+    }
+}
+
+fn flags(options: &[OptSpec]) -> Vec<&'static str> {
+    options
+        .iter()
+        .filter(|spec| spec.long != "+")
+        .flat_map(|spec| spec.short.into_iter().chain(Some(spec.long)))
+        .collect()
+}
+
+fn value_flags(options: &[OptSpec]) -> Vec<&'static str> {
+    flags(options)
+        .into_iter()
+        .filter(|flag| {
+            options.iter().any(|spec| {
+                (spec.long == *flag || spec.short == Some(*flag)) && spec.value.is_some()
+            })
+        })
+        .collect()
+}
+
+fn one_line(help: &str) -> String {
+    help.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+}
+
+fn bash() -> String {
+    let mut text = String::from(
+        "_cargo_single() {
+    local cur prev
+    cur=\"${COMP_WORDS[COMP_CWORD]}\"
+    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W \"single\" -- \"$cur\"))
+        return
+    fi
+    if [[ $COMP_CWORD -eq 2 ]]; then
+",
+    );
+    let names: Vec<_> = SUBCOMMANDS.iter().map(|sub| sub.name).collect();
+    text.push_str(&format!(
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n        return\n    fi\n",
+        names.join(" ")
+    ));
+    text.push_str("    case \"${COMP_WORDS[2]}\" in\n");
+    for sub in SUBCOMMANDS {
+        text.push_str(&format!("        {})\n", sub.name));
+        let value_flags = value_flags(sub.options);
+        if !value_flags.is_empty() {
+            text.push_str(&format!(
+                "            case \"$prev\" in\n                {})\n",
+                value_flags.join("|")
+            ));
+            if sub.operand == Operand::CrateDir {
+                text.push_str("                    COMPREPLY=($(compgen -f -- \"$cur\"))\n");
+            }
+            text.push_str("                    return ;;\n            esac\n");
+        }
+        let flags = flags(sub.options);
+        if !flags.is_empty() {
+            text.push_str(&format!(
+                "            if [[ \"$cur\" == -* ]]; then\n                \
+                 COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n                \
+                 return\n            fi\n",
+                flags.join(" ")
+            ));
+        }
+        text.push_str(match sub.operand {
+            Operand::Source => {
+                "            if [[ \"$cur\" == +* ]]; then\n                \
+                 COMPREPLY=($(compgen -W \"$(rustup toolchain list 2>/dev/null | \
+                 sed 's/ .*//; s/^/+/')\" -- \"$cur\"))\n                \
+                 return\n            fi\n            \
+                 COMPREPLY=($(compgen -f -X '!*.rs' -- \"$cur\") $(compgen -d -- \"$cur\"))\n"
+            }
+            Operand::CrateDir => "            COMPREPLY=($(compgen -d -- \"$cur\"))\n",
+            Operand::Shell | Operand::Words => "",
+        });
+        if sub.operand == Operand::Shell {
+            text.push_str(&format!(
+                "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+                SHELLS.join(" ")
+            ));
+        }
+        text.push_str("            ;;\n");
+    }
+    text.push_str("    esac\n}\ncomplete -o filenames -F _cargo_single cargo-single\n");
+    text
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_arguments(sub: &Subcommand) -> String {
+    let mut specs = vec![];
+    for spec in sub.options.iter().filter(|spec| spec.long != "+") {
+        let help = zsh_escape(&one_line(spec.help));
+        let value = match spec.value {
+            Some(value) if sub.operand == Operand::CrateDir => format!(":{}:_files", value),
+            Some(value) => format!(":{}:", value),
+            None => String::new(),
+        };
+        let names = match spec.short {
+            Some(short) => format!("{{{},{}}}", short, spec.long),
+            None => spec.long.to_owned(),
+        };
+        specs.push(format!("'{}[{}]{}'", names, help, value));
+    }
+    specs.push(match sub.operand {
+        Operand::Source => "'*:source file:_files -g \"*.rs\"'".to_owned(),
+        Operand::CrateDir => "':crate directory:_directories'".to_owned(),
+        Operand::Shell => format!("':shell:({})'", SHELLS.join(" ")),
+        Operand::Words => "'*:query:'".to_owned(),
+    });
+    specs.join(" \\\n                ")
+}
+
+fn zsh() -> String {
+    let mut text = String::from(
+        "#compdef cargo-single
+
+_cargo_single() {
+    local -a commands
+    commands=(
+",
+    );
+    for sub in SUBCOMMANDS {
+        text.push_str(&format!(
+            "        '{}:{}'\n",
+            sub.name,
+            zsh_escape(&one_line(sub.help))
+        ));
+    }
+    text.push_str(
+        "    )
+    if (( CURRENT == 2 )); then
+        compadd single
+        return
+    fi
+    if (( CURRENT == 3 )); then
+        _describe 'command' commands
+        return
+    fi
+    local cmd=${words[3]}
+    shift 2 words
+    (( CURRENT -= 2 ))
+    case $cmd in
+",
+    );
+    for sub in SUBCOMMANDS {
+        text.push_str(&format!("        {})\n", sub.name));
+        if sub.operand == Operand::Source {
+            text.push_str(
+                "            if [[ ${words[CURRENT]} == +* ]]; then\n                \
+                 compadd -- ${(f)\"$(rustup toolchain list 2>/dev/null | \
+                 sed 's/ .*//; s/^/+/')\"}\n                \
+                 return\n            fi\n",
+            );
+        }
+        text.push_str(&format!(
+            "            _arguments -s {}\n            ;;\n",
+            zsh_arguments(sub)
+        ));
+    }
+    text.push_str("    esac\n}\n\n_cargo_single \"$@\"\n");
+    text
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish() -> String {
+    let mut text = String::from(
+        "complete -c cargo-single -f
+complete -c cargo-single -n 'test (count (commandline -opc)) -eq 1' -a single
+",
+    );
+    for sub in SUBCOMMANDS {
+        text.push_str(&format!(
+            "complete -c cargo-single -n 'test (count (commandline -opc)) -eq 2' -a {} -d '{}'\n",
+            sub.name,
+            fish_escape(&one_line(sub.help))
+        ));
+    }
+    for sub in SUBCOMMANDS {
+        let cond = format!(
+            "-n 'test (count (commandline -opc)) -gt 2; and __fish_seen_subcommand_from {}'",
+            sub.name
+        );
+        for spec in sub.options {
+            if spec.long == "+" {
+                text.push_str(&format!(
+                    "complete -c cargo-single {} -a '(rustup toolchain list 2>/dev/null | \
+                     string replace -r \" .*\" \"\" | string replace -r \"^\" \"+\")'\n",
+                    cond
+                ));
+                continue;
+            }
+            let mut line = format!(
+                "complete -c cargo-single {} -l {}",
+                cond,
+                spec.long.trim_start_matches("--")
+            );
+            if let Some(short) = spec.short {
+                line.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+            }
+            if spec.value.is_some() {
+                line.push_str(" -r");
+                if sub.operand == Operand::CrateDir {
+                    line.push_str(" -F");
+                }
+            }
+            line.push_str(&format!(" -d '{}'\n", fish_escape(&one_line(spec.help))));
+            text.push_str(&line);
+        }
+        let candidates = match sub.operand {
+            Operand::Source => "-a '(__fish_complete_suffix .rs)'".to_owned(),
+            Operand::CrateDir => "-a '(__fish_complete_directories)'".to_owned(),
+            Operand::Shell => format!("-a '{}'", SHELLS.join(" ")),
+            Operand::Words => continue,
+        };
+        text.push_str(&format!(
+            "complete -c cargo-single {} {}\n",
+            cond, candidates
+        ));
+    }
+    text
+}
+
+fn powershell_list(items: &[&str]) -> String {
+    items
+        .iter()
+        .map(|item| format!("'{}'", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn powershell() -> String {
+    let names: Vec<_> = SUBCOMMANDS.iter().map(|sub| sub.name).collect();
+    let mut text = format!(
+        "Register-ArgumentCompleter -Native -CommandName 'cargo-single' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})
+    $index = $words.Count
+    if ($wordToComplete -ne '') {{ $index -= 1 }}
+    $candidates = @()
+    $files = $null
+    if ($index -eq 1) {{
+        $candidates = @('single')
+    }} elseif ($index -eq 2) {{
+        $candidates = @({})
+    }} else {{
+        switch ($words[2]) {{
+",
+        powershell_list(&names)
+    );
+    for sub in SUBCOMMANDS {
+        let flags = flags(sub.options);
+        text.push_str(&format!("            '{}' {{\n", sub.name));
+        if !flags.is_empty() {
+            text.push_str(&format!(
+                "                if ($wordToComplete -like '-*') {{\n                    \
+                 $candidates = @({})\n                }} else {{\n",
+                powershell_list(&flags)
+            ));
+        }
+        text.push_str(&match sub.operand {
+            Operand::Source => {
+                "                    $files = Get-ChildItem -Path \"$wordToComplete*\" | \
+                 Where-Object { $_.PSIsContainer -or $_.Extension -eq '.rs' }\n"
+                    .to_owned()
+            }
+            Operand::CrateDir => {
+                "                    $files = Get-ChildItem -Path \"$wordToComplete*\" \
+                 -Directory\n"
+                    .to_owned()
+            }
+            Operand::Shell => format!(
+                "                    $candidates = @({})\n",
+                powershell_list(SHELLS)
+            ),
+            Operand::Words => String::new(),
+        });
+        if !flags.is_empty() {
+            text.push_str("                }\n");
+        }
+        text.push_str("            }\n");
+    }
+    text.push_str(
+        "        }
+    }
+    if ($files) {
+        $candidates = @($files | ForEach-Object { Resolve-Path -Relative $_.FullName })
+    } else {
+        $candidates = @($candidates | Where-Object { $_ -like \"$wordToComplete*\" })
+    }
+    $candidates | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+",
+    );
+    text
+}
@@ -0,0 +1,272 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::settings;
+
+// Per-user configuration directory: $XDG_CONFIG_HOME/cargo-single or
+// ~/.config/cargo-single on Unix-like systems, %APPDATA%\cargo-single on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("cargo-single"))
+}
+
+// The cache directory, which may be set with CARGO_SINGLE_CACHE_DIR or the
+// cache-dir setting in the [defaults] table.
+pub fn cache_dir() -> Option<PathBuf> {
+    let layers = settings::Layers::load(None).ok();
+    match layers.and_then(|layers| layers.resolve("cache-dir")) {
+        Some((dir, _)) => Some(PathBuf::from(dir)),
+        None => default_cache_dir(),
+    }
+}
+
+// Per-user cache directory: $XDG_CACHE_HOME/cargo-single or ~/.cache/cargo-single
+// on Unix-like systems, %LOCALAPPDATA%\cargo-single on Windows.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+    base.map(|base| base.join("cargo-single"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigValue {
+    String(String),
+    Bool(bool),
+    Integer(i64),
+    List(Vec<String>),
+}
+
+// Settings from the per-user config.toml. Only a subset of TOML is supported:
+// tables, and keys with string, boolean, integer or string array values, each
+// on a single line. Keys in tables are stored as "table.key".
+#[derive(Default)]
+pub struct Config {
+    values: Vec<(String, ConfigValue)>,
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        Some(value[1..value.len() - 1].to_owned())
+    } else {
+        None
+    }
+}
+
+// Removes a trailing comment, taking care not to cut a string value.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = None;
+    for (i, c) in line.char_indices() {
+        match (c, in_string) {
+            ('"' | '\'', None) => in_string = Some(c),
+            (c, Some(quote)) if c == quote => in_string = None,
+            ('#', None) => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+// Splits the items of an array at the commas which aren't in a string.
+fn split_items(items: &str) -> Vec<&str> {
+    let mut split = vec![];
+    let mut start = 0;
+    let mut in_string = None;
+    for (i, c) in items.char_indices() {
+        match (c, in_string) {
+            ('"' | '\'', None) => in_string = Some(c),
+            (c, Some(quote)) if c == quote => in_string = None,
+            (',', None) => {
+                split.push(&items[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    split.push(&items[start..]);
+    split
+}
+
+fn parse_value(value: &str) -> Option<ConfigValue> {
+    let value = value.trim();
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return split_items(items)
+            .into_iter()
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(unquote)
+            .collect::<Option<Vec<_>>>()
+            .map(ConfigValue::List);
+    }
+    match value {
+        "true" => Some(ConfigValue::Bool(true)),
+        "false" => Some(ConfigValue::Bool(false)),
+        _ => unquote(value)
+            .map(ConfigValue::String)
+            .or_else(|| value.parse().ok().map(ConfigValue::Integer)),
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut values = vec![];
+        let mut table = String::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.trim().to_owned();
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => {
+                    (unquote(key).unwrap_or_else(|| key.trim().to_owned()), value)
+                }
+                None => return Err(format!("line {}: expected a key and a value", n + 1)),
+            };
+            let value = match parse_value(value) {
+                Some(value) => value,
+                None => return Err(format!("line {}: unsupported value", n + 1)),
+            };
+            let key = if table.is_empty() {
+                key
+            } else {
+                format!("{}.{}", table, key)
+            };
+            values.push((key, value));
+        }
+        Ok(Config { values })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(ConfigValue::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key) {
+            Some(ConfigValue::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // The keys in a table, without the table name.
+    pub fn table_keys(&self, table: &str) -> Vec<&str> {
+        let prefix = format!("{}.", table);
+        let mut keys: Vec<_> = self
+            .values
+            .iter()
+            .filter_map(|(key, _)| key.strip_prefix(&prefix))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    pub fn get_list(&self, key: &str) -> Option<&[String]> {
+        match self.get(key) {
+            Some(ConfigValue::List(items)) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+// Loads config.toml from the configuration directory; a missing file is an
+// empty configuration.
+pub fn load() -> Result<Config, String> {
+    let path = match config_dir() {
+        Some(dir) => dir.join("config.toml"),
+        None => return Ok(Config::default()),
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigValue};
+
+    #[test]
+    fn parses_values() {
+        let config = Config::parse(
+            "# settings\n\
+             toolchain = \"nightly\"  # a comment\n\
+             offline = true\n\
+             jobs = 4\n\
+             features = [\"a,b\", 'c#d', \"e\",]\n\
+             empty = []\n\
+             \n\
+             [defaults]\n\
+             \"quoted key\" = 'x'\n\
+             release = false\n",
+        )
+        .unwrap();
+        assert_eq!(config.get_str("toolchain"), Some("nightly"));
+        assert_eq!(config.get_bool("offline"), Some(true));
+        assert_eq!(config.get("jobs"), Some(&ConfigValue::Integer(4)));
+        assert_eq!(
+            config.get_list("features"),
+            Some(&["a,b".to_owned(), "c#d".to_owned(), "e".to_owned()][..])
+        );
+        assert_eq!(config.get_list("empty"), Some(&[][..]));
+        assert_eq!(config.get_str("defaults.quoted key"), Some("x"));
+        assert_eq!(config.get_bool("defaults.release"), Some(false));
+        assert_eq!(config.table_keys("defaults"), ["quoted key", "release"]);
+        assert_eq!(config.get("release"), None);
+    }
+
+    #[test]
+    fn later_keys_win() {
+        let config = Config::parse("a = \"1\"\na = \"2\"\n").unwrap();
+        assert_eq!(config.get_str("a"), Some("2"));
+    }
+
+    #[test]
+    fn rejects_unsupported_lines() {
+        assert_eq!(
+            Config::parse("a = 1\nb\n").err().as_deref(),
+            Some("line 2: expected a key and a value")
+        );
+        assert_eq!(
+            Config::parse("a = bare\n").err().as_deref(),
+            Some("line 1: unsupported value")
+        );
+        assert_eq!(
+            Config::parse("a = [1, 2]\n").err().as_deref(),
+            Some("line 1: unsupported value")
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::deps::{inline_value, quoted};
+use crate::tool_available;
+
+// Named volume shared by all container builds, so that crates are downloaded only once.
+const REGISTRY_VOLUME: &str = "cargo-single-registry";
+pub const PROJECT_MOUNT: &str = "/project";
+
+// The engine can be chosen with CARGO_SINGLE_CONTAINER_ENGINE, otherwise the
+// first one of Docker and Podman which can be executed is used.
+pub fn engine() -> Option<String> {
+    if let Ok(engine) = env::var("CARGO_SINGLE_CONTAINER_ENGINE") {
+        return Some(engine);
+    }
+    ["docker", "podman"]
+        .into_iter()
+        .find(|engine| tool_available(engine))
+        .map(str::to_owned)
+}
+
+// The directories of the path dependencies in the manifest, which are absolute
+// since the manifest is generated with relocated paths. They are mounted into
+// the container at the same paths, so that the manifest works there as is.
+pub fn path_dependencies(manifest: &str) -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![];
+    let deps = manifest
+        .split_once("[dependencies]\n")
+        .map_or("", |(_, deps)| deps);
+    for line in deps.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let range = match (key.trim(), value.trim_start().chars().next()) {
+            ("path", Some('"')) => quoted(value),
+            (_, Some('{')) => inline_value(value, "path"),
+            _ => None,
+        };
+        let (start, end) = match range {
+            Some(range) => range,
+            None => continue,
+        };
+        if cfg!(windows) {
+            return Err(format!(
+                "the path dependency {} can't be mounted into a Linux container from Windows; \
+                 use a git or registry dependency, or build without --container",
+                key.trim()
+            ));
+        }
+        let dir = PathBuf::from(&value[start..end]);
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    Ok(dirs)
+}
+
+// Returns a command which will run Cargo inside the container; Cargo's own
+// arguments are to be appended by the caller. Build artifacts are kept in a
+// separate subdirectory of the project target dir, since they are built for
+// the container environment. An isolated container has no network access.
+// The variables named in `pass_env` are passed from the environment of the
+// command into the container, and the directories in `mounts` are mounted at
+// the same paths.
+pub fn cargo_command(
+    engine: &str,
+    image: &str,
+    project_dir: &Path,
+    mounts: &[PathBuf],
+    isolated: bool,
+    pass_env: &[String],
+) -> Command {
+    let mut runner = Command::new(engine);
+    runner.args(["run", "--rm", "-i"]);
+    if io::stdin().is_terminal() {
+        runner.arg("-t");
+    }
+    if isolated {
+        runner.args(["--network", "none"]);
+    }
+    for name in pass_env {
+        runner.arg("-e").arg(name);
+    }
+    for dir in mounts {
+        runner
+            .arg("-v")
+            .arg(format!("{}:{}", dir.display(), dir.display()));
+    }
+    runner
+        .arg("-v")
+        .arg(format!("{}:{}", project_dir.display(), PROJECT_MOUNT))
+        .arg("-v")
+        .arg(format!("{}:/usr/local/cargo/registry", REGISTRY_VOLUME))
+        .args(["-w", PROJECT_MOUNT])
+        .arg("-e")
+        .arg(format!(
+            "CARGO_TARGET_DIR={}/target/container",
+            PROJECT_MOUNT
+        ))
+        .arg(image)
+        .arg("cargo");
+    runner
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::path_dependencies;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn finds_path_dependencies() {
+        let manifest = "[package]\nname = \"prog\"\n\n[dependencies]\nrand = \"0.8\"\n\
+                        helper = { path = \"/src/helper\", version = \"0.1\" }\n\
+                        [dependencies.other]\npath = \"/src/other\"\n\
+                        again = { path = \"/src/helper\" }\n";
+        assert_eq!(
+            path_dependencies(manifest).unwrap(),
+            [PathBuf::from("/src/helper"), PathBuf::from("/src/other")]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Running the program under a debugger, for "debug", and the VS Code launch
+// configuration for debugging it there instead.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config;
+use crate::json::{self, Value};
+use crate::tool_available;
+
+// The wrappers which come with Rustup load the pretty-printers for the
+// standard library types; LLDB comes first on macOS, where it's the system
+// debugger.
+const DEBUGGERS: &[&str] = if cfg!(target_os = "macos") {
+    &["rust-lldb", "lldb", "rust-gdb", "gdb"]
+} else {
+    &["rust-gdb", "gdb", "rust-lldb", "lldb"]
+};
+
+// The debugger is taken from CARGO_SINGLE_DEBUGGER, the "debugger" key in the
+// [debug] table of the configuration file, or is the first available one. It
+// may include arguments separated by whitespace.
+pub fn debugger() -> Result<String, String> {
+    if let Some(debugger) = env::var("CARGO_SINGLE_DEBUGGER")
+        .ok()
+        .filter(|debugger| !debugger.trim().is_empty())
+    {
+        return Ok(debugger);
+    }
+    if let Some(debugger) = config::load()?.get_str("debug.debugger") {
+        return Ok(debugger.to_owned());
+    }
+    DEBUGGERS
+        .iter()
+        .find(|debugger| tool_available(debugger))
+        .map(|debugger| (*debugger).to_owned())
+        .ok_or_else(|| {
+            "no debugger found; install gdb or lldb, or set CARGO_SINGLE_DEBUGGER".to_owned()
+        })
+}
+
+// LLDB takes the program and its arguments after "--", and GDB after "--args".
+pub fn command(debugger: &str, binary: &Path, args: &[String]) -> Command {
+    let mut words = debugger.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let mut command = Command::new(program);
+    command.args(words);
+    let name = Path::new(program).file_name().unwrap_or_default();
+    if name.to_string_lossy().contains("lldb") {
+        command.arg("--");
+    } else {
+        command.arg("--args");
+    }
+    command.arg(binary).args(args);
+    command
+}
+
+// A launch.json with a configuration for CodeLLDB, which starts the program in
+// the current directory, without building it.
+pub fn launch_json(name: &str, binary: &Path, args: &[String]) -> Result<Value, String> {
+    let binary = fs::canonicalize(binary).map_err(|e| format!("{}: {}", binary.display(), e))?;
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    let configuration = json::object([
+        ("type", "lldb".into()),
+        ("request", "launch".into()),
+        ("name", format!("Debug {}", name).into()),
+        ("program", binary.to_string_lossy().as_ref().into()),
+        (
+            "args",
+            Value::Array(args.iter().map(|arg| arg.as_str().into()).collect()),
+        ),
+        ("cwd", cwd.to_string_lossy().as_ref().into()),
+        ("sourceLanguages", Value::Array(vec!["rust".into()])),
+    ]);
+    Ok(json::object([
+        ("version", "0.2.0".into()),
+        ("configurations", Value::Array(vec![configuration])),
+    ]))
+}
@@ -0,0 +1,134 @@
+// Expansion of the shorthand forms which may be used in the dependency comments
+// into the regular dependency specifications written to Cargo.toml.
+
+use std::path::Path;
+
+// The dependency comments start on the first line of the source file, or the
+// second one if the first is a shebang line, and end with the first line which
+// isn't one. Returns their line numbers and contents without the comment marker.
+pub fn header(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source
+        .lines()
+        .enumerate()
+        .skip_while(|(n, line)| *n == 0 && line.starts_with("#!") && !line.starts_with("#!["))
+        .map_while(|(n, line)| line.strip_prefix("// ").map(|dep| (n, dep)))
+}
+
+const GIT_PREFIX: &str = "git:";
+const GIT_REFERENCES: &[&str] = &["branch", "tag", "rev"];
+
+// Byte range of the contents of the first quoted string in `text`.
+pub fn quoted(text: &str) -> Option<(usize, usize)> {
+    let start = text.find('"')? + 1;
+    let len = text[start..].find('"')?;
+    Some((start, start + len))
+}
+
+// Byte range of the value of `key` in an inline table, if it's a string.
+pub fn inline_value(table: &str, key: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    while let Some(pos) = table[from..].find(key).map(|pos| pos + from) {
+        from = pos + key.len();
+        let before = table[..pos].trim_end();
+        let after = table[from..].trim_start();
+        if (before.ends_with('{') || before.ends_with(',')) && after.starts_with('=') {
+            let value_start = table.len() - after.len() + 1;
+            let value = table[value_start..].trim_start();
+            if !value.starts_with('"') {
+                return None;
+            }
+            let (start, end) = quoted(value)?;
+            let offset = table.len() - value.len();
+            return Some((offset + start, offset + end));
+        }
+    }
+    None
+}
+
+// Expands "git:<url>[#{branch|tag|rev}=<name>]", where the URL defaults to
+// https, into an inline table.
+fn expand_git(spec: &str) -> Result<String, String> {
+    let (url, reference) = match spec.split_once('#') {
+        Some((url, reference)) => (url.trim(), Some(reference.trim())),
+        None => (spec.trim(), None),
+    };
+    if url.is_empty() || url.contains(['"', ' ']) {
+        return Err(format!("invalid git URL \"{}\"", url));
+    }
+    let url = if url.contains("://") {
+        url.to_owned()
+    } else {
+        format!("https://{}", url)
+    };
+    let mut table = format!("{{ git = \"{}\"", url);
+    if let Some(reference) = reference {
+        match reference.split_once('=') {
+            Some((kind, name))
+                if GIT_REFERENCES.contains(&kind.trim())
+                    && !name.trim().is_empty()
+                    && !name.contains(['"', ' ']) =>
+            {
+                table.push_str(&format!(", {} = \"{}\"", kind.trim(), name.trim()));
+            }
+            _ => {
+                return Err(format!(
+                    "invalid git reference \"{}\", expected one of {}=<name>",
+                    reference,
+                    GIT_REFERENCES.join("|")
+                ))
+            }
+        }
+    }
+    table.push_str(" }");
+    Ok(table)
+}
+
+// The inline table a "git:" shorthand value stands for, if the value is one.
+pub fn git_shorthand(value: &str) -> Option<Result<String, String>> {
+    value.trim_start().strip_prefix(GIT_PREFIX).map(expand_git)
+}
+
+// Relative paths in the dependency comments are meant to be relative to the
+// source file, while Cargo.toml is in the project directory, and may be copied
+// to its subdirectories. Such paths are made absolute.
+fn relocate_path(dep: &str, src_dir: &Path) -> String {
+    let (key, value) = match dep.split_once('=') {
+        Some(entry) => entry,
+        None => return dep.to_owned(),
+    };
+    let value_offset = dep.len() - value.len();
+    let range = match (key.trim(), value.trim_start().chars().next()) {
+        ("path", Some('"')) => quoted(value),
+        (_, Some('{')) => inline_value(value, "path"),
+        _ => None,
+    };
+    let (start, end) = match range {
+        Some((start, end)) => (value_offset + start, value_offset + end),
+        None => return dep.to_owned(),
+    };
+    let path = Path::new(&dep[start..end]);
+    if path.is_absolute() {
+        return dep.to_owned();
+    }
+    let path = src_dir
+        .join(path)
+        .display()
+        .to_string()
+        .replace('\\', "\\\\");
+    format!("{}{}{}", &dep[..start], path, &dep[end..])
+}
+
+// Returns the dependency line with any shorthand expanded, and a relative path
+// interpreted as relative to `src_dir`, which must be absolute.
+pub fn expand(dep: &str, src_dir: &Path) -> Result<String, String> {
+    let (key, value) = match dep.split_once('=') {
+        Some(entry) => entry,
+        None => return Ok(dep.to_owned()),
+    };
+    match value.trim_start().strip_prefix(GIT_PREFIX) {
+        Some(spec) => expand_git(spec)
+            .map(|table| format!("{} = {}", key.trim_end(), table))
+            .map_err(|e| format!("{}: {}", key.trim(), e)),
+        None => Ok(relocate_path(dep, src_dir)),
+    }
+}
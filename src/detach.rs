@@ -0,0 +1,214 @@
+// Programs started in the background with "run --detach", in a session of
+// their own, with their output going to a log. Each has a PID file and the log
+// in the "detached" subdirectory of the cache directory, named after the
+// program, which "ps" lists and "stop" uses to stop it.
+
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::json::{self, Value};
+
+// How long "stop" waits for a program to exit before killing it.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Detached {
+    pub name: String,
+    pub pid: u32,
+    pub source: String,
+    pub running: bool,
+    pub log: PathBuf,
+}
+
+fn dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(config::cache_dir()
+        .ok_or("no cache directory")?
+        .join("detached"))
+}
+
+// The name of a program given to "stop", which may also be its source file.
+pub fn program_name(arg: &str) -> String {
+    let path = Path::new(arg);
+    match path.extension() {
+        Some(ext) if ext == "rs" => path.file_stem().unwrap_or_default(),
+        _ => path.file_name().unwrap_or_default(),
+    }
+    .to_string_lossy()
+    .into_owned()
+}
+
+fn is_running(pid: u32) -> bool {
+    if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+            .unwrap_or(false)
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+// The program leads its own process group, which is signalled as a whole, so
+// that the processes it started are stopped too.
+fn terminate(pid: u32, force: bool) {
+    if cfg!(windows) {
+        let mut taskkill = Command::new("taskkill");
+        if force {
+            taskkill.arg("/F");
+        }
+        let _ = taskkill.args(["/T", "/PID", &pid.to_string()]).output();
+    } else {
+        let signal = if force { "-KILL" } else { "-TERM" };
+        let _ = Command::new("kill")
+            .args([signal, "--", &format!("-{}", pid)])
+            .output();
+    }
+}
+
+fn read(pid_file: &Path) -> Option<Detached> {
+    let text = fs::read_to_string(pid_file).ok()?;
+    let mut lines = text.lines();
+    let pid = lines.next()?.parse().ok()?;
+    let source = lines.next().unwrap_or_default().to_owned();
+    Some(Detached {
+        name: pid_file.file_stem()?.to_string_lossy().into_owned(),
+        pid,
+        source,
+        running: is_running(pid),
+        log: pid_file.with_extension("log"),
+    })
+}
+
+#[cfg(unix)]
+fn new_session(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    extern "C" {
+        fn setsid() -> i32;
+    }
+    // SAFETY: setsid() is async-signal-safe, so it may be called between fork
+    // and exec.
+    unsafe {
+        command.pre_exec(|| {
+            setsid();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn new_session(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn new_session(_command: &mut Command) {}
+
+// Starts the built program, unless it's already running, and returns the
+// detached program.
+pub fn start(
+    name: &str,
+    file_src: &Path,
+    binary: &Path,
+    args: &[String],
+) -> Result<Detached, Box<dyn Error>> {
+    let dir = dir()?;
+    let pid_file = dir.join(format!("{}.pid", name));
+    if let Some(detached) = read(&pid_file).filter(|detached| detached.running) {
+        return Err(format!("{} is already running, with PID {}", name, detached.pid).into());
+    }
+    fs::create_dir_all(&dir)?;
+    let log_path = pid_file.with_extension("log");
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)?;
+    let mut command = Command::new(binary);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    new_session(&mut command);
+    let child = command.spawn()?;
+    let source = fs::canonicalize(file_src)?;
+    fs::write(
+        &pid_file,
+        format!("{}\n{}\n", child.id(), source.to_string_lossy()),
+    )?;
+    Ok(Detached {
+        name: name.to_owned(),
+        pid: child.id(),
+        source: source.to_string_lossy().into_owned(),
+        running: true,
+        log: log_path,
+    })
+}
+
+pub fn list() -> Result<Vec<Detached>, Box<dyn Error>> {
+    let dir = dir()?;
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut detached: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pid"))
+        .filter_map(|path| read(&path))
+        .collect();
+    detached.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(detached)
+}
+
+// Stops the program, killing it if it doesn't exit in time, and forgets it.
+// The log is kept.
+pub fn stop(name: &str) -> Result<Detached, Box<dyn Error>> {
+    let pid_file = dir()?.join(format!("{}.pid", name));
+    let mut detached = match read(&pid_file) {
+        Some(detached) => detached,
+        None => return Err(format!("{} was not started with --detach", name).into()),
+    };
+    if detached.running {
+        terminate(detached.pid, false);
+        let start = Instant::now();
+        while is_running(detached.pid) && start.elapsed() < STOP_TIMEOUT {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if is_running(detached.pid) {
+            terminate(detached.pid, true);
+        }
+        detached.running = false;
+    }
+    fs::remove_file(pid_file)?;
+    Ok(detached)
+}
+
+pub fn to_json(detached: &[Detached]) -> Value {
+    let programs = detached
+        .iter()
+        .map(|detached| {
+            json::object([
+                ("name", detached.name.as_str().into()),
+                ("pid", (detached.pid as i64).into()),
+                ("running", detached.running.into()),
+                ("source", detached.source.as_str().into()),
+                ("log", detached.log.to_string_lossy().as_ref().into()),
+            ])
+        })
+        .collect();
+    json::object([("programs", Value::Array(programs))])
+}
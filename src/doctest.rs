@@ -0,0 +1,20 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::manifest;
+
+// Doctests are only run for library targets, so the doctest manifest lives in a
+// subdirectory of the project and builds the source file as a library. It's kept
+// separate from the main manifest to avoid having the same file in two targets.
+pub const MANIFEST: &str = "doctest/Cargo.toml";
+
+pub fn prepare(project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    // Use the same dependency versions as the program.
+    manifest::write_nested(
+        project_dir,
+        MANIFEST,
+        "[lib]\npath = \"../src/main.rs\"\n",
+        true,
+    )?;
+    Ok(())
+}
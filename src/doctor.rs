@@ -0,0 +1,159 @@
+// Checks of the environment for "doctor", each printed with a fix for what's
+// wrong, since most failures otherwise only show up as a failed build.
+
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+use crate::config;
+use crate::json::{self, Value};
+use crate::wsl;
+
+pub enum Outcome {
+    Ok(String),
+    // Something that only matters for some uses, like Rustup.
+    Warning(String),
+    Failure(String),
+}
+
+pub fn print(name: &str, outcome: &Outcome) {
+    let (label, text) = match outcome {
+        Outcome::Ok(text) => ("ok", text),
+        Outcome::Warning(text) => ("warning", text),
+        Outcome::Failure(text) => ("error", text),
+    };
+    println!("{:7}  {}: {}", label, name, text);
+}
+
+pub fn to_json(checks: &[(String, Outcome)]) -> Value {
+    let checks = checks
+        .iter()
+        .map(|(name, outcome)| {
+            let (status, text) = match outcome {
+                Outcome::Ok(text) => ("ok", text),
+                Outcome::Warning(text) => ("warning", text),
+                Outcome::Failure(text) => ("error", text),
+            };
+            json::object([
+                ("check", name.as_str().into()),
+                ("status", status.into()),
+                ("message", text.as_str().into()),
+            ])
+        })
+        .collect();
+    json::object([("checks", Value::Array(checks))])
+}
+
+fn version(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .arg("--version")
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+pub fn cargo(toolchain: Option<&str>) -> Outcome {
+    match version("cargo", &toolchain.into_iter().collect::<Vec<_>>()) {
+        Ok(version) => Outcome::Ok(version),
+        Err(e) => Outcome::Failure(format!(
+            "{}; install Rust from https://rustup.rs, or add Cargo to the PATH",
+            e
+        )),
+    }
+}
+
+pub fn rustup() -> Outcome {
+    match version("rustup", &[]) {
+        Ok(version) => Outcome::Ok(version),
+        Err(_) => Outcome::Warning(
+            "not found; toolchains given with +toolchain and installed targets can't be \
+             checked, and --sanitize and --minimal-versions need a nightly toolchain"
+                .to_owned(),
+        ),
+    }
+}
+
+// The toolchain name is given without the "+".
+pub fn toolchain(name: &str) -> Outcome {
+    let output = match Command::new("rustup").args(["toolchain", "list"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return Outcome::Failure("can't list the toolchains with Rustup".to_owned()),
+    };
+    let installed = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|toolchain| {
+            toolchain == name
+                || toolchain
+                    .strip_prefix(name)
+                    .is_some_and(|host| host.starts_with('-'))
+        });
+    if installed {
+        Outcome::Ok("installed".to_owned())
+    } else {
+        Outcome::Failure(format!(
+            "not installed; install it with \"rustup toolchain install {}\"",
+            name
+        ))
+    }
+}
+
+// The source file is hard linked into its project, which is created next to
+// it, so the directory must allow hard links.
+pub fn hard_links(dir: &Path) -> Outcome {
+    let file = dir.join(format!(".cargo-single-doctor-{}", process::id()));
+    let link = file.with_extension("link");
+    let result = fs::write(&file, "").and_then(|_| fs::hard_link(&file, &link));
+    let _ = fs::remove_file(&link);
+    let _ = fs::remove_file(&file);
+    match result {
+        Ok(()) => Outcome::Ok(format!("supported in {}", dir.display())),
+        Err(e) => Outcome::Failure(match wsl::link_hint(&file) {
+            Some(hint) => format!("{}: {}; {}", dir.display(), e, hint),
+            None => format!(
+                "{}: {}; the project can't be created next to the source file, move it to \
+                 a writable directory on a file system which supports hard links",
+                dir.display(),
+                e
+            ),
+        }),
+    }
+}
+
+pub fn cache_dir() -> Outcome {
+    let dir = match config::cache_dir() {
+        Some(dir) => dir,
+        None => {
+            return Outcome::Warning(
+                "unknown; programs from the standard input and \"eval\" need it, set \
+                 XDG_CACHE_HOME"
+                    .to_owned(),
+            )
+        }
+    };
+    let probe = dir.join(format!(".doctor-{}", process::id()));
+    let result = fs::create_dir_all(&dir).and_then(|_| fs::write(&probe, ""));
+    let _ = fs::remove_file(&probe);
+    match result {
+        Ok(()) => Outcome::Ok(format!("{} is writable", dir.display())),
+        Err(e) => Outcome::Failure(format!(
+            "{}: {}; fix its permissions, or set XDG_CACHE_HOME to a writable directory",
+            dir.display(),
+            e
+        )),
+    }
+}
+
+pub fn config_file() -> Outcome {
+    match (config::load(), config::config_dir()) {
+        (Err(e), _) => Outcome::Failure(format!("{}; fix or remove the file", e)),
+        (Ok(_), Some(dir)) if dir.join("config.toml").is_file() => {
+            Outcome::Ok(format!("{} is valid", dir.join("config.toml").display()))
+        }
+        (Ok(_), _) => Outcome::Ok("none".to_owned()),
+    }
+}
@@ -0,0 +1,59 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+use crate::settings;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// The editor is taken from the editor setting, which is CARGO_SINGLE_EDITOR or
+// the [defaults] table of the configuration file, or from VISUAL or EDITOR, in
+// that order, and may include arguments separated by whitespace.
+fn editor() -> Command {
+    let layers = settings::Layers::load(None).ok();
+    let editor = layers
+        .and_then(|layers| layers.resolve("editor"))
+        .map(|(editor, _)| editor)
+        .filter(|editor| !editor.trim().is_empty())
+        .or_else(|| {
+            ["VISUAL", "EDITOR"]
+                .iter()
+                .find_map(|var| env::var(var).ok().filter(|value| !value.trim().is_empty()))
+        })
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_owned());
+    let mut words = editor.split_whitespace();
+    let mut command = Command::new(words.next().expect("editor"));
+    command.args(words);
+    command
+}
+
+// Runs the editor on the source file. If `on_save` is given, the file is polled
+// while the editor is running, and the callback invoked whenever it changes.
+pub fn edit(
+    file_src: &Path,
+    on_save: Option<&mut dyn FnMut()>,
+) -> Result<ExitStatus, Box<dyn Error>> {
+    let mut child = editor().arg(file_src).spawn()?;
+    let on_save = match on_save {
+        Some(on_save) => on_save,
+        None => return Ok(child.wait()?),
+    };
+    let mut modified = fs::metadata(file_src)?.modified()?;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        thread::sleep(POLL_INTERVAL);
+        // The file may briefly disappear while the editor replaces it.
+        if let Ok(current) = fs::metadata(file_src).and_then(|md| md.modified()) {
+            if current != modified {
+                modified = current;
+                on_save();
+            }
+        }
+    }
+}
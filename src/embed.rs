@@ -0,0 +1,127 @@
+// Flashing programs to microcontrollers with a debug probe, for "embed". The
+// program is built for the target of the chip, given with `// target =
+// "thumbv7m-none-eabi"` as for the other commands, and flashed with probe-rs,
+// which Cargo uses as the runner; probe-rs then prints the RTT output of the
+// program until it's stopped with Ctrl-C. The chip is given with --chip, or
+// `// embed.chip = "STM32F103C8"`. With `// embed.runner = "cargo-embed"`,
+// the program is flashed with cargo-embed instead, which is configured with
+// an Embed.toml in the project.
+//
+// The linker scripts of the runtime crates are passed to the linker when they
+// are dependencies, and memory.x, which cortex-m-rt and riscv-rt include, is
+// looked for in the directory of the source file.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::header::parse_header;
+
+// Passed to Cargo with --config, like the settings of the other options.
+pub const CONFIG: &str = ".cargo/embed.toml";
+
+// cargo-embed reads its configuration from the directory it runs in.
+const EMBED_CONFIG: &str = "Embed.toml";
+
+// Dependencies which come with a linker script, and the script.
+const LINKER_SCRIPTS: &[(&str, &str)] = &[
+    ("cortex-m-rt", "link.x"),
+    ("riscv-rt", "link.x"),
+    ("defmt", "defmt.x"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    ProbeRs,
+    CargoEmbed,
+}
+
+impl Runner {
+    // From the value of "embed.runner", which is probe-rs by default.
+    pub fn parse(setting: Option<&str>) -> Result<Runner, String> {
+        match setting {
+            None | Some("probe-rs") => Ok(Runner::ProbeRs),
+            Some("cargo-embed") => Ok(Runner::CargoEmbed),
+            Some(value) => Err(format!(
+                "embed.runner must be probe-rs or cargo-embed, not {}",
+                value
+            )),
+        }
+    }
+}
+
+// Chip names are like "STM32F103C8" or "nRF52840_xxAA"; anything else is
+// refused, since the chip is written into the runner command, which Cargo
+// splits at whitespace.
+pub fn check_chip(chip: &str) -> Result<(), String> {
+    let valid = !chip.is_empty()
+        && chip
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid chip name \"{}\"", chip))
+    }
+}
+
+// The flags for rustc: the linker scripts of the dependencies, and the
+// directory of the source file as a library search path, for memory.x.
+pub fn rustflags(source: &str, source_dir: &Path) -> Result<Vec<String>, String> {
+    let header = parse_header(source);
+    let mut scripts = vec![];
+    for (dep, script) in LINKER_SCRIPTS {
+        if header.deps().any(|entry| entry.key == *dep) && !scripts.contains(script) {
+            scripts.push(*script);
+        }
+    }
+    let mut flags: Vec<_> = scripts
+        .iter()
+        .map(|script| format!("-Clink-arg=-T{}", script))
+        .collect();
+    let dir = source_dir
+        .to_str()
+        .ok_or_else(|| format!("{} is not valid UTF-8", source_dir.to_string_lossy()))?;
+    flags.push(format!("-L{}", dir));
+    Ok(flags)
+}
+
+pub fn write_config(
+    project_dir: &Path,
+    target: &str,
+    chip: &str,
+    rustflags: &[String],
+) -> Result<(), Box<dyn Error>> {
+    // The flags are written as TOML literal strings, which need no escaping of
+    // Windows path separators.
+    let mut quoted = vec![];
+    for flag in rustflags {
+        if flag.contains(['\'', '\n']) {
+            return Err(format!("can't pass {} to rustc", flag).into());
+        }
+        quoted.push(format!("'{}'", flag));
+    }
+    let config = format!(
+        "[target.'{}']\nrunner = 'probe-rs run --chip {}'\nrustflags = [{}]\n",
+        target,
+        chip,
+        quoted.join(", ")
+    );
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
+
+// cargo-embed flashes the program, and shows its RTT output once it's
+// enabled.
+pub fn write_embed_config(project_dir: &Path, chip: &str) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        project_dir.join(EMBED_CONFIG),
+        format!(
+            "[default.general]\nchip = \"{}\"\n\n[default.rtt]\nenabled = true\n",
+            chip
+        ),
+    )?;
+    Ok(())
+}
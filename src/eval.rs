@@ -0,0 +1,44 @@
+// Evaluation of an expression given on the command line, which is wrapped into
+// a program kept in the cache directory.
+
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::cache;
+use crate::scaffold::{dep_line, DepSpec};
+
+const PRINT_MACROS: &[&str] = &["print!", "println!", "eprint!", "eprintln!", "dbg!"];
+
+// Dependency comments for the dependencies, which get the latest versions.
+pub fn header(deps: &[DepSpec]) -> String {
+    deps.iter().map(|dep| dep_line(dep, "*")).collect()
+}
+
+// An expression ending with a semicolon, or starting with a printing macro, is
+// run as statements; the value of any other one is printed.
+pub fn is_statement(expression: &str) -> bool {
+    let expression = expression.trim();
+    expression.ends_with(';') || PRINT_MACROS.iter().any(|mac| expression.starts_with(mac))
+}
+
+// Code printing the value of an expression.
+pub fn print_value(expression: &str) -> String {
+    format!(
+        "println!(\"{{:?}}\", {{\n        {}\n    }});",
+        expression.trim()
+    )
+}
+
+fn script(expression: &str, deps: &[DepSpec]) -> String {
+    let code = if is_statement(expression) {
+        expression.trim().to_owned()
+    } else {
+        print_value(expression)
+    };
+    format!("{}\nfn main() {{\n    {}\n}}\n", header(deps), code)
+}
+
+// Returns the path of the program for the expression in the cache.
+pub fn prepare(expression: &str, deps: &[DepSpec]) -> Result<PathBuf, Box<dyn Error>> {
+    cache::store("eval", &script(expression, deps))
+}
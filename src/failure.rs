@@ -0,0 +1,84 @@
+// Classes of failures, with distinct exit statuses, so that scripts running
+// cargo-single can react to them. The statuses follow sysexits.h, which keeps
+// them apart from the status of a failed Cargo command, 101, and from the
+// statuses used by most programs.
+
+use std::process;
+
+use crate::cli::{self, Format};
+use crate::json;
+
+pub enum Failure {
+    // Invalid subcommand, options or arguments.
+    Usage(String),
+    // The source file or the project directory doesn't exist or isn't usable.
+    SourceNotFound(String),
+    // Creating the project, or writing files into it, failed.
+    ProjectSetup(String),
+    // Updating the manifest from the header failed.
+    ManifestRefresh(String),
+    // Cargo, or another program needed for the command, couldn't be run.
+    Cargo(String),
+    // Cargo or the program ran and failed; its status is passed on.
+    ChildExit(i32),
+    Other(String),
+}
+
+impl Failure {
+    pub fn code(&self) -> i32 {
+        match self {
+            Failure::Usage(_) => 64,
+            Failure::ManifestRefresh(_) => 65,
+            Failure::SourceNotFound(_) => 66,
+            Failure::Cargo(_) => 69,
+            Failure::ProjectSetup(_) => 73,
+            Failure::ChildExit(code) => *code,
+            Failure::Other(_) => 1,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Failure::Usage(_) => "usage",
+            Failure::ManifestRefresh(_) => "manifest-refresh",
+            Failure::SourceNotFound(_) => "source-not-found",
+            Failure::Cargo(_) => "cargo",
+            Failure::ProjectSetup(_) => "project-setup",
+            Failure::ChildExit(_) => "child-exit",
+            Failure::Other(_) => "other",
+        }
+    }
+
+    // With the JSON format, the failure is printed as an object with its kind,
+    // status and message, on the standard error like the plain message.
+    pub fn exit(self) -> ! {
+        let message = match &self {
+            Failure::Usage(message)
+            | Failure::SourceNotFound(message)
+            | Failure::ProjectSetup(message)
+            | Failure::ManifestRefresh(message)
+            | Failure::Cargo(message)
+            | Failure::Other(message) => Some(message),
+            Failure::ChildExit(_) => None,
+        };
+        match (cli::format(), message) {
+            (Format::Json, message) => {
+                let message = message.map(|message| {
+                    message
+                        .strip_prefix("cargo-single: ")
+                        .unwrap_or(message)
+                        .to_owned()
+                });
+                let error = json::object([
+                    ("kind", self.kind().into()),
+                    ("status", i64::from(self.code()).into()),
+                    ("message", message.into()),
+                ]);
+                eprintln!("{}", cli::versioned(json::object([("error", error)])));
+            }
+            (Format::Plain, Some(message)) => eprintln!("{}", message),
+            (Format::Plain, None) => (),
+        }
+        process::exit(self.code())
+    }
+}
@@ -0,0 +1,93 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// The fuzzing crate is generated in the "fuzz" subdirectory of the project, which
+// is where cargo-fuzz expects it. Since the program is a binary, the fuzz targets
+// include the source file as a module instead of depending on the project.
+const FUZZ_DIR: &str = "fuzz";
+
+// Finds public functions marked with #[cfg(fuzzing)], which can be used as fuzz targets.
+pub fn find_targets(source: &str) -> Vec<String> {
+    let mut targets = vec![];
+    let mut cfg_fuzzing = false;
+    for line in source.lines() {
+        let line = line.trim();
+        if line == "#[cfg(fuzzing)]" {
+            cfg_fuzzing = true;
+            continue;
+        }
+        if cfg_fuzzing {
+            if let Some(name) = line
+                .strip_prefix("pub fn ")
+                .and_then(|rest| rest.split(['(', '<']).next())
+            {
+                targets.push(name.trim().to_owned());
+            }
+            cfg_fuzzing = line.starts_with("#[");
+        }
+    }
+    targets
+}
+
+fn fuzz_manifest(project_manifest: &str, targets: &[String]) -> String {
+    let mut manifest = String::new();
+    let mut in_package = false;
+    for line in project_manifest.lines() {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        }
+        if in_package && line.starts_with("name = ") {
+            let name = line["name = ".len()..].trim_matches('"');
+            manifest.push_str(&format!("name = \"{}-fuzz\"\n", name));
+            continue;
+        }
+        manifest.push_str(line);
+        manifest.push('\n');
+        if line == "[dependencies]" {
+            manifest.push_str("libfuzzer-sys = \"0.4\"\n");
+        }
+    }
+    manifest.push_str("\n[package.metadata]\ncargo-fuzz = true\n");
+    for target in targets {
+        manifest.push_str(&format!(
+            "\n[[bin]]\nname = \"{}\"\npath = \"fuzz_targets/{}.rs\"\ntest = false\ndoc = false\nbench = false\n",
+            target, target
+        ));
+    }
+    manifest
+}
+
+// Generates the fuzzing crate with a fuzz target for each of `targets`.
+pub fn prepare(project_dir: &Path, targets: &[String]) -> Result<(), Box<dyn Error>> {
+    let project_manifest = fs::read_to_string(project_dir.join("Cargo.toml"))?;
+    let fuzz_dir = project_dir.join(FUZZ_DIR);
+    let targets_dir = fuzz_dir.join("fuzz_targets");
+    fs::create_dir_all(&targets_dir)?;
+    fs::write(
+        fuzz_dir.join("Cargo.toml"),
+        fuzz_manifest(&project_manifest, targets),
+    )?;
+    // Start with the dependency versions used by the program.
+    let lock_path = project_dir.join("Cargo.lock");
+    let fuzz_lock_path = fuzz_dir.join("Cargo.lock");
+    if lock_path.exists() && !fuzz_lock_path.exists() {
+        fs::copy(lock_path, fuzz_lock_path)?;
+    }
+    for target in targets {
+        fs::write(
+            targets_dir.join(format!("{}.rs", target)),
+            format!(
+                "#![no_main]\n\n\
+                 #[path = \"../../src/main.rs\"]\n\
+                 #[allow(dead_code)]\n\
+                 mod script;\n\n\
+                 libfuzzer_sys::fuzz_target!(|data: &[u8]| {{\n    \
+                 script::{}(data);\n\
+                 }});\n",
+                target
+            ),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// Version information from the Git repository containing the source file, set
+// as environment variables for the build with --git-stamp, so that the program
+// can report which commit it was built from with env!().
+
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("error executing git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+// Returns CARGO_SINGLE_GIT_DESCRIBE, the output of "git describe" for the
+// repository, and CARGO_SINGLE_GIT_COMMIT, the last commit which changed the
+// source file.
+pub fn variables(file_src: &Path) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let dir = match file_src.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = file_src.file_name().ok_or("no file name")?;
+    let describe = git(dir, &["describe", "--always", "--dirty", "--tags"])?;
+    let commit = git(
+        dir,
+        &["log", "-1", "--format=%H", "--", &name.to_string_lossy()],
+    )?;
+    if commit.is_empty() {
+        return Err(format!("{} isn't committed", file_src.display()).into());
+    }
+    Ok(vec![
+        ("CARGO_SINGLE_GIT_DESCRIBE".to_owned(), describe),
+        ("CARGO_SINGLE_GIT_COMMIT".to_owned(), commit),
+    ])
+}
@@ -0,0 +1,113 @@
+// Matching files against glob patterns, with "*" and "?" matching within a
+// path component, and a "**" component matching any number of directories.
+// Patterns use "/" as the separator, and hidden files are only matched by
+// components which start with ".".
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Whether a name matches a pattern component.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    fn rest(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.first(), name.first()) {
+            (None, None) => true,
+            (Some('*'), _) => {
+                rest(&pattern[1..], name) || (!name.is_empty() && rest(pattern, &name[1..]))
+            }
+            (Some('?'), Some(_)) => rest(&pattern[1..], &name[1..]),
+            (Some(p), Some(n)) if p == n => rest(&pattern[1..], &name[1..]),
+            _ => false,
+        }
+    }
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+    rest(&pattern, &name)
+}
+
+// Whether a relative path matches a pattern.
+pub fn path_matches(pattern: &str, path: &Path) -> bool {
+    fn rest(pattern: &[&str], path: &[String]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", after)) => {
+                rest(after, path)
+                    || (!path.is_empty() && !path[0].starts_with('.') && rest(pattern, &path[1..]))
+            }
+            Some((component, after)) => {
+                !path.is_empty() && matches(component, &path[0]) && rest(after, &path[1..])
+            }
+        }
+    }
+    let pattern: Vec<_> = pattern
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    let path: Vec<_> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    rest(&pattern, &path)
+}
+
+fn entries(dir: &Path) -> Vec<(String, bool)> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let is_dir = entry.path().is_dir();
+            (entry.file_name().to_string_lossy().into_owned(), is_dir)
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+fn walk(base: &Path, relative: &Path, components: &[&str], found: &mut Vec<PathBuf>) {
+    let dir = base.join(relative);
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            if dir.is_file() {
+                found.push(relative.to_owned());
+            }
+            return;
+        }
+    };
+    match *component {
+        "**" => {
+            walk(base, relative, rest, found);
+            for (name, is_dir) in entries(&dir) {
+                if is_dir && !name.starts_with('.') {
+                    walk(base, &relative.join(name), components, found);
+                }
+            }
+        }
+        literal if !literal.contains(['*', '?']) => {
+            walk(base, &relative.join(literal), rest, found)
+        }
+        pattern => {
+            for (name, _) in entries(&dir) {
+                if matches(pattern, &name) {
+                    walk(base, &relative.join(name), rest, found);
+                }
+            }
+        }
+    }
+}
+
+// Returns the files below `dir` matching the pattern, relative to `dir`.
+pub fn expand(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let components: Vec<_> = pattern
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    let mut found = vec![];
+    walk(dir, Path::new(""), &components, &mut found);
+    found.sort();
+    found.dedup();
+    found
+}
@@ -0,0 +1,365 @@
+//! Parsing of the dependency comments at the top of a source file, the
+//! header, without reading or writing any files.
+//!
+//! The header consists of the `// ` comment lines starting on the first line
+//! of the source file, or the second one if the first is a shebang line. Its
+//! lines are written to the `[dependencies]` table of the manifest, and may
+//! start other tables, like `[dev-dependencies]`, except for the package keys,
+//! cargo-single's own settings and the lint levels, like `lints.clippy =
+//! { pedantic = "warn" }`, which are written to the `[lints]` table.
+//! Dependencies for a platform, like `[windows] winreg = "0.52"`, are written
+//! to the `[target.'cfg(windows)'.dependencies]` table.
+
+use std::ops::Range;
+
+use crate::deps;
+use crate::hooks;
+use crate::resources;
+
+// Keys in the dependency comments which configure cargo-single instead of
+// listing dependencies. A key may also be a dotted one starting with these.
+// The Windows resource settings, like `windows.icon`, are matched by their
+// full keys, since `windows` is also the name of a crate.
+pub(crate) const SETTING_KEYS: &[&str] = &[
+    "build",
+    "checksum",
+    "deny",
+    "edition",
+    "embed",
+    "env",
+    "include",
+    "mod",
+    hooks::POST_RUN,
+    hooks::PRE_RUN,
+    "quiet",
+    "requires",
+    "sandbox",
+    "target",
+    "toolchain",
+];
+
+// The platforms of the `[<platform>] <dependency>` shorthand, and the
+// conditions of the target tables their dependencies are written to.
+const PLATFORMS: &[(&str, &str)] = &[
+    ("unix", "cfg(unix)"),
+    ("windows", "cfg(windows)"),
+    ("linux", "cfg(target_os = \"linux\")"),
+    ("macos", "cfg(target_os = \"macos\")"),
+];
+
+// Keys which are written to the [package] table: "self", for the version, and
+// "edition".
+const PACKAGE_KEYS: &[&str] = &["self", "edition"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// An entry of the `[dependencies]` table, where the header starts.
+    Dependency,
+    /// An entry of the `[dev-dependencies]` table.
+    DevDependency,
+    /// `self`, the package version, or `edition`.
+    Package,
+    /// A setting of cargo-single, like `mod` or `env.NAME`, before any table
+    /// header.
+    Setting,
+    /// A dependency for a platform, like `[windows] winreg = "0.52"`, before
+    /// any table header.
+    PlatformDependency,
+    /// An entry of the `[lints]` table, given as `lints.<tool>` before any
+    /// table header.
+    Lints,
+    /// A table header, like `[dev-dependencies]`.
+    Table,
+    /// An entry of any other table, or a line which isn't a `key = value` one.
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry<'a> {
+    pub kind: Kind,
+    /// The line without the comment marker.
+    pub text: &'a str,
+    /// The key, trimmed, which is empty for tables and lines without one.
+    pub key: &'a str,
+    /// The value, trimmed, with any quotes.
+    pub value: &'a str,
+    /// The name of the table the entry is in, which is `dependencies` until
+    /// the first table header.
+    pub table: &'a str,
+    /// Zero-based line number.
+    pub line: usize,
+    /// Byte range of `text` in the source.
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Header<'a> {
+    pub entries: Vec<Entry<'a>>,
+    /// Byte range of the header lines in the source, including the comment
+    /// markers; empty if there are none.
+    pub span: Range<usize>,
+}
+
+impl<'a> Header<'a> {
+    fn of_kind(&self, kind: Kind) -> impl Iterator<Item = &Entry<'a>> {
+        self.entries.iter().filter(move |entry| entry.kind == kind)
+    }
+
+    pub fn deps(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::Dependency)
+    }
+
+    pub fn dev_deps(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::DevDependency)
+    }
+
+    pub fn package_keys(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::Package)
+    }
+
+    pub fn settings(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::Setting)
+    }
+
+    pub fn platform_deps(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::PlatformDependency)
+    }
+
+    pub fn lints(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.of_kind(Kind::Lints)
+    }
+
+    /// The lines written to the manifest after its `[dependencies]` table
+    /// header, which are all but the package keys, the settings, the
+    /// dependencies for a platform and the lint levels.
+    pub fn manifest_lines(&self) -> impl Iterator<Item = &Entry<'a>> {
+        self.entries.iter().filter(|entry| {
+            !matches!(
+                entry.kind,
+                Kind::Package | Kind::Setting | Kind::PlatformDependency | Kind::Lints
+            )
+        })
+    }
+}
+
+/// The key of a setting of cargo-single, if the header line is one.
+pub fn setting_key(text: &str) -> Option<&str> {
+    let key = text.split_once('=')?.0.trim();
+    let name = key.split('.').next().unwrap_or_default().trim();
+    (SETTING_KEYS.contains(&name) || resources::is_setting(key)).then_some(key)
+}
+
+/// The key of an entry of the `[lints]` table, like `clippy` or
+/// `rust.unsafe_code`, if the header line is a `lints.<key> = <value>` one.
+pub fn lints_key(text: &str) -> Option<&str> {
+    let key = text.split_once('=')?.0.trim();
+    key.strip_prefix("lints.").map(str::trim_start)
+}
+
+/// The condition of the target table and the dependency line, if the header
+/// line is a `[<platform>] <dependency>` one, like `[unix] nix = "0.29"`. The
+/// platforms are `unix`, `windows`, `linux` and `macos`.
+pub fn platform_dep(text: &str) -> Option<(&'static str, &str)> {
+    let (platform, dep) = text.trim_start().strip_prefix('[')?.split_once(']')?;
+    let (_, condition) = PLATFORMS
+        .iter()
+        .find(|(name, _)| *name == platform.trim())?;
+    let dep = dep.trim_start();
+    dep.contains('=').then_some((*condition, dep))
+}
+
+fn table_name(text: &str) -> Option<&str> {
+    let name = text.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+    Some(name.split('.').next().unwrap_or_default().trim())
+}
+
+pub fn parse_header(source: &str) -> Header<'_> {
+    let offset = |text: &str| text.as_ptr() as usize - source.as_ptr() as usize;
+    let mut header = Header::default();
+    let mut table = "dependencies";
+    // Settings are only taken from the lines before the first table header,
+    // so that keys like `target` or `build` remain dependency keys in other
+    // tables.
+    let mut top_level = true;
+    for (line, text) in deps::header(source) {
+        let start = offset(text);
+        if header.entries.is_empty() {
+            header.span = start - "// ".len()..start - "// ".len();
+        }
+        header.span.end = start + text.len();
+        let platform_dep = platform_dep(text).filter(|_| table == "dependencies");
+        let (key, value) = match platform_dep.unwrap_or(("", text)).1.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => ("", ""),
+        };
+        let kind = if platform_dep.is_some() {
+            Kind::PlatformDependency
+        } else if let Some(name) = table_name(text) {
+            table = name;
+            top_level = false;
+            Kind::Table
+        } else if key.is_empty() {
+            Kind::Other
+        } else if table == "dependencies" && PACKAGE_KEYS.contains(&key) {
+            Kind::Package
+        } else if top_level && setting_key(text).is_some() {
+            Kind::Setting
+        } else if table == "dependencies" && lints_key(text).is_some() {
+            Kind::Lints
+        } else {
+            match table {
+                "dependencies" => Kind::Dependency,
+                "dev-dependencies" => Kind::DevDependency,
+                _ => Kind::Other,
+            }
+        };
+        header.entries.push(Entry {
+            kind,
+            text,
+            key,
+            value,
+            table,
+            line,
+            span: start..start + text.len(),
+        });
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_header, Kind};
+
+    fn kinds(source: &str) -> Vec<(Kind, &str)> {
+        parse_header(source)
+            .entries
+            .into_iter()
+            .map(|entry| (entry.kind, entry.key))
+            .collect()
+    }
+
+    #[test]
+    fn line_kinds() {
+        let source = "// rand = \"0.8\"\n// self = \"1.2.0\"\n// edition = \"2024\"\n\
+                      // mod = \"util.rs\"\n// env.API = \"x\"\n// lints.clippy = { all = \"warn\" }\n\
+                      // [unix] nix = \"0.29\"\n// not a key\n// [dev-dependencies]\n\
+                      // tempfile = \"3\"\n// [profile.release]\n// lto = true\nfn main() {}\n";
+        assert_eq!(
+            kinds(source),
+            [
+                (Kind::Dependency, "rand"),
+                (Kind::Package, "self"),
+                (Kind::Package, "edition"),
+                (Kind::Setting, "mod"),
+                (Kind::Setting, "env.API"),
+                (Kind::Lints, "lints.clippy"),
+                (Kind::PlatformDependency, "nix"),
+                (Kind::Other, ""),
+                (Kind::Table, ""),
+                (Kind::DevDependency, "tempfile"),
+                (Kind::Table, ""),
+                (Kind::Other, "lto"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_and_lines() {
+        let source = "#!/usr/bin/env run-cargo-single\n// rand = \"0.8\"\n// [dev-dependencies]\n\
+                      fn main() {}\n";
+        let header = parse_header(source);
+        assert_eq!(
+            &source[header.span.clone()],
+            "// rand = \"0.8\"\n// [dev-dependencies]"
+        );
+        let entry = &header.entries[0];
+        assert_eq!(entry.line, 1);
+        assert_eq!(&source[entry.span.clone()], "rand = \"0.8\"");
+        assert_eq!(entry.value, "\"0.8\"");
+        assert_eq!(header.entries[1].line, 2);
+        assert_eq!(header.entries[1].text, "[dev-dependencies]");
+    }
+
+    #[test]
+    fn no_header() {
+        let header = parse_header("fn main() {}\n// rand = \"0.8\"\n");
+        assert!(header.entries.is_empty());
+        assert!(header.span.is_empty());
+    }
+
+    #[test]
+    fn tables() {
+        let header = parse_header(
+            "// rand = \"0.8\"\n// [dev-dependencies]\n// tempfile = \"3\"\n\
+             // [target.'cfg(unix)'.dependencies]\n// nix = \"0.29\"\nfn main() {}\n",
+        );
+        let tables: Vec<_> = header.entries.iter().map(|entry| entry.table).collect();
+        assert_eq!(
+            tables,
+            [
+                "dependencies",
+                "dev-dependencies",
+                "dev-dependencies",
+                "target",
+                "target"
+            ]
+        );
+        assert_eq!(header.dev_deps().count(), 1);
+        assert_eq!(header.manifest_lines().count(), 5);
+    }
+
+    #[test]
+    fn package_keys_and_settings_are_not_manifest_lines() {
+        let header = parse_header(
+            "// self = \"1.0.0\"\n// edition = \"2018\"\n// quiet = true\n\
+             // rand = \"0.8\"\nfn main() {}\n",
+        );
+        let package: Vec<_> = header
+            .package_keys()
+            .map(|entry| (entry.key, entry.value))
+            .collect();
+        assert_eq!(package, [("self", "\"1.0.0\""), ("edition", "\"2018\"")]);
+        assert_eq!(header.settings().count(), 1);
+        let lines: Vec<_> = header.manifest_lines().map(|entry| entry.text).collect();
+        assert_eq!(lines, ["rand = \"0.8\""]);
+    }
+
+    #[test]
+    fn windows_crate_is_not_a_setting() {
+        assert_eq!(
+            kinds("// windows = \"0.58\"\n// windows.features = []\n// windows.icon = \"a.ico\"\n"),
+            [
+                (Kind::Dependency, "windows"),
+                (Kind::Dependency, "windows.features"),
+                (Kind::Setting, "windows.icon"),
+            ]
+        );
+    }
+
+    #[test]
+    fn setting_keys_in_other_tables() {
+        let source = "// target = \"0.1\"\n// [dev-dependencies]\n// target = \"0.2\"\n\
+                      // [build-dependencies]\n// build = \"1\"\n\
+                      // [target.'cfg(windows)'.dependencies]\n// env = \"1\"\n\
+                      // windows = \"0.58\"\n";
+        assert_eq!(
+            kinds(source),
+            [
+                (Kind::Setting, "target"),
+                (Kind::Table, ""),
+                (Kind::DevDependency, "target"),
+                (Kind::Table, ""),
+                (Kind::Other, "build"),
+                (Kind::Table, ""),
+                (Kind::Other, "env"),
+                (Kind::Other, "windows"),
+            ]
+        );
+        let lines: Vec<_> = parse_header(source)
+            .manifest_lines()
+            .map(|entry| entry.text)
+            .collect();
+        assert!(lines.contains(&"build = \"1\""));
+        assert!(lines.contains(&"env = \"1\""));
+    }
+}
@@ -0,0 +1,222 @@
+// Canonical formatting of the dependency comments, for "fmt-header": one
+// space around "=", uniform spacing in inline tables and arrays, the "git:"
+// shorthand expanded, and the dependencies sorted by name. Before the first
+// table header, the package keys come first, then the settings, the
+// dependencies, the dependencies for a platform and the lint levels; the
+// entries of later dependency tables are sorted too. Keys aren't padded into
+// columns, since adding a longer name would then change every line.
+
+use std::str::Chars;
+
+use crate::deps;
+use crate::header::{parse_header, platform_dep, Entry, Kind};
+
+// Copies the rest of a string starting with `quote` from `chars`. Only basic
+// strings, in double quotes, have escapes.
+fn copy_string(quote: char, chars: &mut Chars, out: &mut String) {
+    out.push(quote);
+    let mut escaped = false;
+    for c in chars.by_ref() {
+        out.push(c);
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return;
+        }
+    }
+}
+
+// The value with uniform spacing, like `{ version = "1", features = ["a"] }`,
+// and the depth of its brackets and braces, which isn't zero for a value
+// spanning several lines. Strings are kept as they are, and so is a trailing
+// comment.
+fn normalize_value(value: &str) -> (String, i32) {
+    let mut normalized = String::new();
+    let mut depth = 0;
+    let mut chars = value.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => copy_string(c, &mut chars, &mut normalized),
+            '#' => {
+                normalized.truncate(normalized.trim_end().len());
+                normalized.push_str(" #");
+                normalized.extend(chars.by_ref());
+            }
+            c if c.is_whitespace() => (),
+            '{' | '[' => {
+                depth += 1;
+                normalized.push_str(if c == '{' { "{ " } else { "[" });
+            }
+            '}' | ']' => {
+                depth -= 1;
+                let len = normalized.trim_end().trim_end_matches(',').len();
+                normalized.truncate(len);
+                if c == '}' && !normalized.ends_with('{') {
+                    normalized.push(' ');
+                }
+                normalized.push(c);
+            }
+            ',' => normalized.push_str(", "),
+            '=' => normalized.push_str(" = "),
+            c => normalized.push(c),
+        }
+    }
+    (normalized, depth)
+}
+
+// The formatted text of an entry, without the comment marker.
+fn format_entry(entry: &Entry, in_deps: bool) -> Result<String, String> {
+    let error = |e| format!("line {}: {}", entry.line + 1, e);
+    if entry.kind == Kind::Table {
+        let name = entry
+            .text
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        return Ok(format!("[{}]", name.trim()));
+    }
+    if entry.key.is_empty() {
+        return Ok(entry.text.trim().to_owned());
+    }
+    let (prefix, key) = match platform_dep(entry.text) {
+        Some(_) if entry.kind == Kind::PlatformDependency => {
+            let platform = entry.text.trim_start()[1..]
+                .split(']')
+                .next()
+                .unwrap_or_default();
+            (format!("[{}] ", platform.trim()), entry.key)
+        }
+        _ => (String::new(), entry.key),
+    };
+    let value = match deps::git_shorthand(entry.value) {
+        Some(table) if in_deps => table.map_err(error)?,
+        _ => entry.value.to_owned(),
+    };
+    let (value, depth) = normalize_value(&value);
+    if depth != 0 {
+        return Err(error(
+            "a value spanning several lines can't be formatted".to_owned(),
+        ));
+    }
+    Ok(format!("{}{} = {}", prefix, key, value))
+}
+
+fn sort_key(entry: &Entry) -> String {
+    entry.key.trim_matches('"').to_lowercase()
+}
+
+// The formatted entries of a table, or of the dependencies before the first
+// table header. Tables with lines which aren't "key = value" ones keep their
+// order.
+fn format_section(
+    table: Option<&Entry>,
+    entries: &[&Entry],
+    first: bool,
+) -> Result<Vec<String>, String> {
+    let mut lines = vec![];
+    let in_deps = match table {
+        Some(table) => {
+            lines.push(format_entry(table, false)?);
+            let name = table
+                .text
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            name.trim().ends_with("dependencies")
+        }
+        None => true,
+    };
+    let mut entries = entries.to_vec();
+    if entries.iter().all(|entry| !entry.key.is_empty()) {
+        if first {
+            let rank = |entry: &Entry| match entry.kind {
+                Kind::Package => 0,
+                Kind::Setting => 1,
+                Kind::Dependency => 2,
+                Kind::PlatformDependency => 3,
+                Kind::Lints => 4,
+                _ => 5,
+            };
+            entries.sort_by_key(|entry| match entry.kind {
+                Kind::Dependency => (rank(entry), String::new(), sort_key(entry)),
+                Kind::PlatformDependency => {
+                    let (condition, _) = platform_dep(entry.text).unwrap_or_default();
+                    (rank(entry), condition.to_owned(), sort_key(entry))
+                }
+                _ => (rank(entry), String::new(), String::new()),
+            });
+        } else if in_deps {
+            entries.sort_by_key(|entry| sort_key(entry));
+        }
+    }
+    for entry in entries {
+        let in_deps = in_deps
+            && matches!(
+                entry.kind,
+                Kind::Dependency | Kind::DevDependency | Kind::PlatformDependency | Kind::Other
+            );
+        lines.push(format_entry(entry, in_deps)?);
+    }
+    Ok(lines)
+}
+
+// Returns the source with its dependency comments formatted.
+pub fn format(source: &str) -> Result<String, String> {
+    let header = parse_header(source);
+    if header.entries.is_empty() {
+        return Ok(source.to_owned());
+    }
+    let mut sections: Vec<(Option<&Entry>, Vec<&Entry>)> = vec![(None, vec![])];
+    for entry in &header.entries {
+        if entry.kind == Kind::Table {
+            sections.push((Some(entry), vec![]));
+        } else {
+            sections.last_mut().expect("section").1.push(entry);
+        }
+    }
+    let mut lines = vec![];
+    for (i, (table, entries)) in sections.iter().enumerate() {
+        lines.extend(format_section(*table, entries, i == 0)?);
+    }
+    // The lines keep the file's line endings, which the header's span ends
+    // before.
+    let newline = if source[header.span.end..].starts_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let formatted: Vec<_> = lines.iter().map(|line| format!("// {}", line)).collect();
+    Ok(format!(
+        "{}{}{}",
+        &source[..header.span.start],
+        formatted.join(newline),
+        &source[header.span.end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+
+    #[test]
+    fn formats_the_header() {
+        let source = "// rand={version=\"0.8\",features=[\"small_rng\"]}\n// anyhow = \"1\"\n\n\
+                      fn main() {}\n";
+        assert_eq!(
+            format(source).unwrap(),
+            "// anyhow = \"1\"\n// rand = { version = \"0.8\", features = [\"small_rng\"] }\n\n\
+             fn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let source = "// rand = \"0.8\"\r\n// anyhow=\"1\"\r\n\r\nfn main() {}\r\n";
+        assert_eq!(
+            format(source).unwrap(),
+            "// anyhow = \"1\"\r\n// rand = \"0.8\"\r\n\r\nfn main() {}\r\n"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+// Shell commands run around the program by "run", from the "pre-run" and
+// "post-run" settings in the dependency comments, or the same keys in the
+// [hooks] table of the configuration file. A setting in the dependency
+// comments overrides the configuration file.
+
+use std::error::Error;
+use std::process::Command;
+
+use crate::config;
+use crate::log::log;
+
+pub const PRE_RUN: &str = "pre-run";
+pub const POST_RUN: &str = "post-run";
+
+fn shell(line: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", line]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", line]);
+        command
+    }
+}
+
+// The command line of a hook, given its setting in the dependency comments.
+pub fn find(name: &str, setting: Option<String>) -> Result<Option<String>, Box<dyn Error>> {
+    if setting.is_some() {
+        return Ok(setting);
+    }
+    let config = config::load()?;
+    Ok(config
+        .get_str(&format!("hooks.{}", name))
+        .map(str::to_owned))
+}
+
+pub fn run(name: &str, line: &str) -> Result<(), Box<dyn Error>> {
+    log!(Info, "running the {} hook \"{}\"", name, line);
+    let status = shell(line)
+        .status()
+        .map_err(|e| format!("error running the {} hook: {}", name, e))?;
+    if !status.success() {
+        return Err(format!("the {} hook \"{}\" failed with {}", name, line, status).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+// Tables which make a crate something other than a single binary built from src/main.rs.
+const NON_SIMPLE_TABLES: &[&str] = &["lib", "bin", "example", "test", "bench", "workspace"];
+
+pub fn import_crate(crate_dir: &Path, out: &Path) -> Result<(), Box<dyn Error>> {
+    if let Ok(out_dir) = fs::canonicalize(out.with_extension("")) {
+        if out_dir == fs::canonicalize(crate_dir)? {
+            return Err(format!(
+                "{}: would use the imported crate as its project directory",
+                out.to_string_lossy()
+            )
+            .into());
+        }
+    }
+    let mut src_dir = crate_dir.to_path_buf();
+    src_dir.push("src");
+    for entry in fs::read_dir(&src_dir)? {
+        let entry = entry?;
+        if entry.file_name() != "main.rs" || !entry.file_type()?.is_file() {
+            return Err(format!(
+                "{}: not a single-file crate",
+                entry.path().to_string_lossy()
+            )
+            .into());
+        }
+    }
+    let mut build_rs = crate_dir.to_path_buf();
+    build_rs.push("build.rs");
+    if build_rs.exists() {
+        return Err(format!(
+            "{}: build scripts are not supported",
+            build_rs.to_string_lossy()
+        )
+        .into());
+    }
+    let mut cargo_path = crate_dir.to_path_buf();
+    cargo_path.push("Cargo.toml");
+    let manifest = fs::read_to_string(&cargo_path)?;
+    let mut main_src = src_dir;
+    main_src.push("main.rs");
+    let main = fs::read_to_string(&main_src)?;
+
+    let mut self_version = None;
+    // Cargo takes a manifest without an edition to be for the 2015 edition,
+    // while the projects of cargo-single get the current one by default.
+    let mut edition = "\"2015\"";
+    let mut deps = vec![];
+    let mut extra = vec![];
+    let mut table = String::new();
+    for line in manifest.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            table = trimmed
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim()
+                .to_owned();
+            let root = table.split('.').next().unwrap_or_default();
+            if NON_SIMPLE_TABLES.contains(&root) {
+                return Err(format!(
+                    "{}: [{}] is not supported in a single-file program",
+                    cargo_path.to_string_lossy(),
+                    table
+                )
+                .into());
+            }
+            if root != "package" && table != "dependencies" {
+                extra.push(line);
+            }
+            continue;
+        }
+        match table.as_str() {
+            "package" => {
+                if let Some((key, value)) = trimmed.split_once('=') {
+                    if !value.trim().starts_with('"') {
+                        continue;
+                    }
+                    match key.trim() {
+                        "version" => self_version = Some(value.trim()),
+                        "edition" => edition = value.trim(),
+                        _ => (),
+                    }
+                }
+            }
+            "dependencies" => deps.push(line),
+            pkg if pkg.starts_with("package.") => (),
+            _ => extra.push(line),
+        }
+    }
+
+    let mut script = String::new();
+    if let Some(version) = self_version {
+        script.push_str(&format!("// self = {}\n", version));
+    }
+    script.push_str(&format!("// edition = {}\n", edition));
+    for line in deps.iter().chain(extra.iter()) {
+        script.push_str("// ");
+        script.push_str(line);
+        script.push('\n');
+    }
+    script.push('\n');
+    script.push_str(&main);
+    let mut out_file = OpenOptions::new().write(true).create_new(true).open(out)?;
+    out_file.write_all(script.as_bytes())?;
+    Ok(())
+}
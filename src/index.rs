@@ -0,0 +1,53 @@
+// Access to the crates.io index, through its sparse HTTP protocol.
+
+use std::error::Error;
+
+use crate::json::{self, Value};
+use crate::net;
+use crate::version::Version;
+
+const INDEX_URL: &str = "https://index.crates.io";
+
+// Path of a crate's file in the index, as described in the Cargo book.
+fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+// Whether the crate is on crates.io, which the index answers with a 404 for
+// crates which aren't.
+pub fn exists(name: &str) -> Result<bool, Box<dyn Error>> {
+    match net::get(&format!("{}/{}", INDEX_URL, index_path(name))) {
+        Ok(_) => Ok(true),
+        Err(e) if e.to_string().contains("error: 404") => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+// Published versions of a crate, with their yanked flags.
+pub fn index_versions(name: &str) -> Result<Vec<(Version, bool)>, Box<dyn Error>> {
+    let index = net::get(&format!("{}/{}", INDEX_URL, index_path(name)))?;
+    let mut versions = vec![];
+    for line in index.lines().filter(|line| !line.trim().is_empty()) {
+        let entry = json::parse(line)?;
+        if let Some(version) = entry.str_at("vers").and_then(Version::parse) {
+            versions.push((version, entry.get("yanked") == Some(&Value::Bool(true))));
+        }
+    }
+    Ok(versions)
+}
+
+// The newest version which is neither yanked nor a pre-release.
+pub fn latest_release(name: &str) -> Result<Version, Box<dyn Error>> {
+    index_versions(name)?
+        .into_iter()
+        .filter(|(v, yanked)| !yanked && !v.is_prerelease())
+        .map(|(v, _)| v)
+        .max()
+        .ok_or_else(|| "no releases".into())
+}
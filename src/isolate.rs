@@ -0,0 +1,26 @@
+use std::process::{Command, Stdio};
+
+// On Linux, commands can be cut off from the network by running them in a new
+// network namespace, which has only an unconfigured loopback interface. This
+// needs unprivileged user namespaces, which some systems disable.
+const UNSHARE_ARGS: &[&str] = &["--map-root-user", "--net", "--"];
+
+pub fn available() -> bool {
+    cfg!(target_os = "linux")
+        && Command::new("unshare")
+            .args(UNSHARE_ARGS)
+            .arg("true")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+}
+
+// Returns a command which will run `program` without network access; its
+// arguments are to be appended by the caller.
+pub fn command(program: &str) -> Command {
+    let mut command = Command::new("unshare");
+    command.args(UNSHARE_ARGS).arg(program);
+    command
+}
@@ -0,0 +1,426 @@
+// Minimal JSON support, sufficient for reading the output of "cargo metadata"
+// and writing reports. Objects keep their keys in insertion order.
+
+use std::fmt::{self, Display, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    // Numbers are kept in their textual form.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    // Convenience for looking up a string member.
+    pub fn str_at(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Number(n.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map(Into::into).unwrap_or(Value::Null)
+    }
+}
+
+// Builds an object from key/value pairs.
+pub fn object<I: IntoIterator<Item = (&'static str, Value)>>(members: I) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect(),
+    )
+}
+
+fn write_string(out: &mut impl Write, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_value(out: &mut impl Write, value: &Value, indent: usize) -> fmt::Result {
+    let pad = |n| "  ".repeat(n);
+    match value {
+        Value::Null => out.write_str("null"),
+        Value::Bool(b) => write!(out, "{}", b),
+        Value::Number(n) => out.write_str(n),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) if items.is_empty() => out.write_str("[]"),
+        Value::Array(items) => {
+            out.write_str("[\n")?;
+            for (i, item) in items.iter().enumerate() {
+                out.write_str(&pad(indent + 1))?;
+                write_value(out, item, indent + 1)?;
+                out.write_str(if i + 1 < items.len() { ",\n" } else { "\n" })?;
+            }
+            write!(out, "{}]", pad(indent))
+        }
+        Value::Object(members) if members.is_empty() => out.write_str("{}"),
+        Value::Object(members) => {
+            out.write_str("{\n")?;
+            for (i, (key, item)) in members.iter().enumerate() {
+                out.write_str(&pad(indent + 1))?;
+                write_string(out, key)?;
+                out.write_str(": ")?;
+                write_value(out, item, indent + 1)?;
+                out.write_str(if i + 1 < members.len() { ",\n" } else { "\n" })?;
+            }
+            write!(out, "{}}}", pad(indent))
+        }
+    }
+}
+
+// Pretty-printed with two-space indentation.
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, 0)
+    }
+}
+
+// Arrays and objects nested deeper are rejected, instead of overflowing the
+// stack of the recursive parser.
+const MAX_DEPTH: usize = 128;
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: text.char_indices().peekable(),
+        text,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_ws();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some((pos, _)) => Err(format!("trailing characters at offset {}", pos)),
+    }
+}
+
+// Whether the text is a number in the JSON grammar: an optional minus sign,
+// an integer part without leading zeros, and optional fraction and exponent.
+fn is_number(text: &str) -> bool {
+    fn digits(text: &str) -> (&str, &str) {
+        let end = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        text.split_at(end)
+    }
+    let (int, rest) = digits(text.strip_prefix('-').unwrap_or(text));
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => match digits(fraction) {
+            ("", _) => return false,
+            (_, rest) => rest,
+        },
+        None => rest,
+    };
+    match rest.strip_prefix(['e', 'E']) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            matches!(digits(exponent), (digits, "") if !digits.is_empty())
+        }
+        None => rest.is_empty(),
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+    // Number of arrays and objects being parsed.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while let Some((_, ' ' | '\t' | '\n' | '\r')) = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    fn error(&mut self, what: &str) -> String {
+        match self.chars.peek() {
+            Some((pos, _)) => format!("{} at offset {}", what, pos),
+            None => format!("{} at end of input", what),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_ws();
+        match self.chars.next() {
+            Some((_, got)) if got == c => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", c))),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            match self.chars.next() {
+                Some((_, c)) if c == expected => (),
+                _ => return Err(self.error(&format!("invalid literal, expected {}", word))),
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('{' | '[') if self.depth == MAX_DEPTH => {
+                Err(self.error(&format!("nested deeper than {} levels", MAX_DEPTH)))
+            }
+            Some('{') => {
+                self.depth += 1;
+                let object = self.object();
+                self.depth -= 1;
+                object
+            }
+            Some('[') => {
+                self.depth += 1;
+                let array = self.array();
+                self.depth -= 1;
+                array
+            }
+            Some('"') => self.string().map(Value::String),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self
+            .chars
+            .peek()
+            .map(|&(pos, _)| pos)
+            .unwrap_or(self.text.len());
+        let mut end = start;
+        while let Some(&(pos, c)) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            end = pos + c.len_utf8();
+            self.chars.next();
+        }
+        let number = &self.text[start..end];
+        if !is_number(number) {
+            return Err(format!("invalid number \"{}\" at offset {}", number, start));
+        }
+        Ok(Value::Number(number.to_owned()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(s),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, 'b')) => s.push('\u{8}'),
+                    Some((_, 'f')) => s.push('\u{c}'),
+                    Some((_, 'u')) => {
+                        let mut code = self.hex4()?;
+                        // Surrogate pair.
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.literal("\\u", Value::Null)?;
+                            let low = self.hex4()?;
+                            code = 0x10000
+                                + ((code - 0xd800) << 10)
+                                + (low.wrapping_sub(0xdc00) & 0x3ff);
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some((_, c)) => s.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some((_, c)) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|(_, c)| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.error("invalid unicode escape")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_ws();
+        if let Some((_, ']')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.chars.next() {
+                Some((_, ',')) => (),
+                Some((_, ']')) => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.skip_ws();
+        if let Some((_, '}')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_ws();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_ws();
+            match self.chars.next() {
+                Some((_, ',')) => (),
+                Some((_, '}')) => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Value, MAX_DEPTH};
+
+    #[test]
+    fn parses_values() {
+        let value = parse(r#" { "a": [1, -2.5e3, true, false, null], "b": {} } "#).unwrap();
+        assert_eq!(
+            value.get("a").unwrap().as_array(),
+            [
+                Value::Number("1".to_owned()),
+                Value::Number("-2.5e3".to_owned()),
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Null,
+            ]
+        );
+        assert_eq!(value.get("b"), Some(&Value::Object(vec![])));
+    }
+
+    #[test]
+    fn string_escapes() {
+        let value = parse(r#""a\"b\\c\/d\n\t\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(value.as_str(), Some("a\"b\\c/d\n\té😀"));
+        let written = Value::from("a\"b\\c\n\u{1}").to_string();
+        assert_eq!(written, r#""a\"b\\c\n\u0001""#);
+        assert_eq!(parse(&written).unwrap().as_str(), Some("a\"b\\c\n\u{1}"));
+    }
+
+    #[test]
+    fn numbers() {
+        for number in ["0", "-0", "12", "1.5", "1e10", "1E+2", "-3.25e-4"] {
+            assert_eq!(parse(number), Ok(Value::Number(number.to_owned())));
+        }
+        for number in ["-", "01", "1.", ".5", "1e", "1e+", "1-2", "--1", "1.2.3"] {
+            assert!(parse(number).is_err(), "{}", number);
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{a: 1}",
+            "\"unterminated",
+            "\"\\u12\"",
+            "tru",
+            "nul",
+            "[] []",
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(parse(&"[{\"a\":".repeat(100_000)).is_err());
+    }
+}
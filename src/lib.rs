@@ -0,0 +1,155 @@
+//! Running single-file Rust programs with dependencies, as the
+//! `cargo single` subcommand does, from other tools.
+//!
+//! A program's project is found with [`SingleProject::locate`], which is given
+//! the path of its source file. The project is created and its manifest
+//! updated from the dependency comments with [`SingleProject::refresh`], after
+//! which it can be built with a Cargo command from
+//! [`SingleProject::cargo_command`], and the built binary found with
+//! [`SingleProject::binary_path`]:
+//!
+//! ```no_run
+//! use cargo_single::{CargoOptions, SingleProject};
+//!
+//! let project = SingleProject::locate("hello.rs")?;
+//! project.refresh()?;
+//! let options = CargoOptions::default();
+//! let status = project.cargo_command("build", &options).status()?;
+//! if status.success() {
+//!     println!("built {}", project.binary_path(&options).display());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+mod app;
+mod audit;
+mod bench;
+mod buildstd;
+mod cache;
+mod cfg;
+mod checksum;
+mod cli;
+mod codesign;
+mod collection;
+mod completions;
+mod config;
+mod container;
+mod debugger;
+mod deps;
+mod detach;
+mod doctest;
+mod doctor;
+mod edit;
+mod embed;
+mod eval;
+mod failure;
+mod fuzz;
+mod gitstamp;
+mod glob;
+pub mod header;
+mod headerfmt;
+mod hooks;
+mod import;
+mod index;
+mod isolate;
+mod json;
+mod library;
+mod licenses;
+mod lints;
+mod log;
+pub mod manifest;
+mod metadata;
+mod minimal;
+mod musl;
+mod native;
+mod net;
+mod pgo;
+mod plugin;
+mod proctree;
+mod profile;
+mod progress;
+mod project;
+mod record;
+mod remote;
+mod repl;
+mod requires;
+mod resources;
+mod restart;
+mod runall;
+pub mod runner;
+mod sandbox;
+mod sanitize;
+mod sbom;
+mod scaffold;
+mod search;
+mod selfupdate;
+mod settings;
+mod shim;
+mod sidefiles;
+mod suggest;
+mod timings;
+mod upgrade;
+mod version;
+mod wasm;
+mod watch;
+mod wildcard;
+mod wsl;
+
+pub use header::{parse_header, Header};
+pub use manifest::ManifestGenerator;
+pub use project::{CargoOptions, SingleProject};
+pub use runner::{Diagnostic, RunResult};
+
+// The command line interface, for the cargo-single binary.
+#[doc(hidden)]
+pub use app::main;
+
+pub(crate) fn tool_available(tool: &str) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// Returns the settings from the dependency comments, as keys and values
+// without quotes.
+pub(crate) fn header_settings(file_src: &Path) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let start = Instant::now();
+    let src = fs::read_to_string(file_src)?;
+    let settings = parse_header(&src)
+        .settings()
+        .map(|entry| {
+            (
+                entry.key.to_owned(),
+                entry.value.trim_matches('"').to_owned(),
+            )
+        })
+        .collect();
+    timings::add_header_time(start);
+    Ok(settings)
+}
+
+// Returns the values of a setting which may be repeated.
+pub(crate) fn header_values(file_src: &Path, key: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(header_settings(file_src)?
+        .into_iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, value)| value)
+        .collect())
+}
+
+pub(crate) fn header_setting(file_src: &Path, key: &str) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(header_settings(file_src)?
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value))
+}
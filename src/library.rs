@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::manifest;
+
+// Building the source file as a library, with --crate-type, uses a separate
+// manifest in a subdirectory of the project, like the doctests, so that the
+// program's manifest keeps building a binary.
+pub const MANIFEST: &str = "lib/Cargo.toml";
+
+pub const CRATE_TYPES: &[&str] = &["cdylib", "dylib", "rlib", "staticlib"];
+
+fn package_name(manifest: &str) -> Result<String, Box<dyn Error>> {
+    Ok(manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("name = "))
+        .ok_or("package name not found")?
+        .trim_matches('"')
+        .to_owned())
+}
+
+pub fn prepare(project_dir: &Path, crate_type: &str) -> Result<(), Box<dyn Error>> {
+    let target = format!(
+        "[lib]\npath = \"../src/main.rs\"\ncrate-type = [\"{}\"]\n",
+        crate_type
+    );
+    // Use the same dependency versions as the program.
+    manifest::write_nested(project_dir, MANIFEST, &target, true)?;
+    Ok(())
+}
+
+// The file built for the crate type in the target directory, whose name depends
+// on the platform of the target, or the host if no target is given.
+pub fn artifact(
+    project_dir: &Path,
+    target_dir: &Path,
+    crate_type: &str,
+    target: Option<&str>,
+    release: bool,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let name =
+        package_name(&fs::read_to_string(project_dir.join("Cargo.toml"))?)?.replace('-', "_");
+    let (windows, apple) = match target {
+        Some(target) => (target.contains("windows"), target.contains("apple")),
+        None => (cfg!(windows), cfg!(target_vendor = "apple")),
+    };
+    let file = match crate_type {
+        "rlib" => format!("lib{}.rlib", name),
+        "staticlib" if windows => format!("{}.lib", name),
+        "staticlib" => format!("lib{}.a", name),
+        _ if windows => format!("{}.dll", name),
+        _ if apple => format!("lib{}.dylib", name),
+        _ => format!("lib{}.so", name),
+    };
+    let mut path = target_dir.to_owned();
+    if let Some(target) = target {
+        path.push(target);
+    }
+    path.push(if release { "release" } else { "debug" });
+    path.push(file);
+    Ok(path)
+}
@@ -0,0 +1,34 @@
+use crate::metadata::Metadata;
+use crate::sbom::license_expression;
+
+pub const UNKNOWN: &str = "(unknown)";
+
+// Groups the dependencies by their license expression, sorted by expression.
+pub fn report(metadata: &Metadata) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+    for package in metadata.packages.iter().filter(|p| p.id != metadata.root) {
+        let license = license_expression(package).unwrap_or_else(|| UNKNOWN.to_owned());
+        let name = format!("{} {}", package.name, package.version);
+        match groups.iter_mut().find(|(l, _)| *l == license) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((license, vec![name])),
+        }
+    }
+    for (_, names) in groups.iter_mut() {
+        names.sort();
+    }
+    groups.sort();
+    groups
+}
+
+// An expression is denied if every alternative joined with OR includes a denied
+// license. Parentheses are ignored, which is adequate for the usual expressions.
+pub fn is_denied(expression: &str, denylist: &[String]) -> bool {
+    let expression = expression.replace(['(', ')'], " ");
+    expression.split(" OR ").all(|alternative| {
+        alternative
+            .split(" AND ")
+            .map(|license| license.split(" WITH ").next().unwrap_or_default().trim())
+            .any(|license| denylist.iter().any(|denied| denied == license))
+    })
+}
@@ -0,0 +1,106 @@
+// Checks of the version requirements in the dependency comments for forms which
+// make builds of the program irreproducible or fragile, and of the optional
+// dependencies for ones which no feature enables.
+
+use crate::deps::quoted;
+use crate::header::{parse_header, Kind};
+use crate::upgrade::find_requirements;
+
+fn problem(req: &str) -> Option<&'static str> {
+    let terms: Vec<_> = req.split(',').map(str::trim).collect();
+    if terms.contains(&"*") {
+        return Some("is a wildcard, so the version used depends on when it's resolved");
+    }
+    if terms.iter().any(|term| term.starts_with('>'))
+        && !terms.iter().any(|term| term.starts_with('<'))
+    {
+        return Some("has no upper bound, which accepts incompatible versions");
+    }
+    if terms.iter().any(|term| term.contains('-')) {
+        return Some("names a pre-release, which may change incompatibly");
+    }
+    None
+}
+
+// Returns a description of each suspicious requirement.
+pub fn lint(source: &str) -> Vec<String> {
+    let lines: Vec<_> = source.lines().collect();
+    find_requirements(source)
+        .into_iter()
+        .filter_map(|req| {
+            let text = &lines[req.line][req.range.0..req.range.1];
+            problem(text).map(|problem| {
+                format!(
+                    "line {}: the version requirement \"{}\" of {} {}",
+                    req.line + 1,
+                    text,
+                    req.name,
+                    problem
+                )
+            })
+        })
+        .collect()
+}
+
+// Whether an inline dependency table has "optional = true".
+fn is_optional(value: &str) -> bool {
+    let Some(table) = value.strip_prefix('{') else {
+        return false;
+    };
+    table.split(',').any(|item| {
+        item.split_once('=').is_some_and(|(key, value)| {
+            key.trim() == "optional" && value.trim().trim_end_matches('}').trim() == "true"
+        })
+    })
+}
+
+// The quoted strings in an array value of the [features] table.
+fn feature_items(value: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut rest = value;
+    while let Some((start, end)) = quoted(rest) {
+        items.push(&rest[start..end]);
+        rest = &rest[end + 1..];
+    }
+    items
+}
+
+// Returns a description of each optional dependency which isn't named by any
+// feature in the [features] table of the header, so that it can only be
+// enabled by its implicit feature, which Cargo drops once any feature refers to
+// another dependency with "dep:", as it's easy to forget.
+pub fn optional_deps(source: &str) -> Vec<String> {
+    let header = parse_header(source);
+    let items: Vec<_> = header
+        .entries
+        .iter()
+        .filter(|entry| entry.table == "features" && entry.kind != Kind::Table)
+        .flat_map(|entry| feature_items(entry.value))
+        .collect();
+    header
+        .entries
+        .iter()
+        .filter(|entry| {
+            matches!(entry.kind, Kind::Dependency | Kind::PlatformDependency)
+                && is_optional(entry.value)
+        })
+        .filter(|entry| {
+            let name = entry.key.trim_matches('"');
+            !items.iter().any(|item| {
+                let item = item.strip_prefix("dep:").unwrap_or(item);
+                let item = item.split_once('/').map_or(item, |(dep, _)| dep);
+                item.trim_end_matches('?') == name
+            })
+        })
+        .map(|entry| {
+            format!(
+                "line {}: the optional dependency {} isn't enabled by any feature; \
+                 add one like `{} = [\"dep:{}\"]` to a [features] table",
+                entry.line + 1,
+                entry.key,
+                entry.key,
+                entry.key
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+// Logging of cargo-single's own operations, such as creating the project or
+// refreshing the manifest, for finding out why a build behaved unexpectedly.
+// The level is set with CARGO_SINGLE_LOG or --log-level, the option taking
+// precedence, and messages are written to the standard error. Nothing is
+// logged by default.
+
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const LOG_ENV: &str = "CARGO_SINGLE_LOG";
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+pub const LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+
+impl Level {
+    fn name(self) -> &'static str {
+        LEVELS[self as usize]
+    }
+}
+
+fn parse(name: &str) -> Option<Level> {
+    let level = match name.trim().to_ascii_lowercase().as_str() {
+        "off" => Level::Off,
+        "error" => Level::Error,
+        "warn" => Level::Warn,
+        "info" => Level::Info,
+        "debug" => Level::Debug,
+        "trace" => Level::Trace,
+        _ => return None,
+    };
+    Some(level)
+}
+
+pub fn set_level(name: &str) -> Result<(), String> {
+    let level = parse(name).ok_or_else(|| {
+        format!(
+            "unknown log level \"{}\", expected one of: {}",
+            name,
+            LEVELS.join(", ")
+        )
+    })?;
+    LEVEL.store(level as u8, Ordering::Relaxed);
+    // Commands run by cargo-single which run it again, like "watch", log at
+    // the same level.
+    env::set_var(LOG_ENV, level.name());
+    Ok(())
+}
+
+// Sets the level from the environment. An invalid value is reported, but
+// doesn't stop the program.
+pub fn init() {
+    if let Some(name) = env::var(LOG_ENV).ok().filter(|name| !name.is_empty()) {
+        if let Err(e) = set_level(&name) {
+            eprintln!("cargo-single: warning: {}: {}", LOG_ENV, e);
+        }
+    }
+}
+
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn write(level: Level, message: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("cargo-single: [{}] {}", level.name(), message);
+    }
+}
+
+// Logs a message at the given level, formatted like format!().
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::$level, format_args!($($arg)*))
+    };
+}
+
+pub(crate) use log;
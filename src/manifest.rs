@@ -0,0 +1,245 @@
+//! Generation of a project's manifest from the header of its source file.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::deps;
+use crate::header::{self, Header};
+use crate::resources;
+
+/// Merges the header into the manifest created by `cargo new`, or one
+/// generated before. The `[package]` table is kept, except for the version
+/// and the edition given in the header, and everything from the
+/// `[dependencies]` table on is replaced with the header lines, followed by
+/// the target tables of the dependencies for a platform, and the `[lints]`
+/// table if the header sets lint levels.
+pub struct ManifestGenerator<'a> {
+    header: &'a Header<'a>,
+    src_dir: &'a Path,
+}
+
+impl<'a> ManifestGenerator<'a> {
+    /// Relative paths in the header are taken to be relative to `src_dir`,
+    /// the directory of the source file, which should be absolute, since the
+    /// manifest is in another directory.
+    pub fn new(header: &'a Header<'a>, src_dir: &'a Path) -> ManifestGenerator<'a> {
+        ManifestGenerator { header, src_dir }
+    }
+
+    pub fn generate(&self, manifest: &str) -> Result<String, String> {
+        let mut deps = String::new();
+        for entry in self.header.manifest_lines() {
+            deps.push_str(&deps::expand(entry.text, self.src_dir)?);
+            deps.push('\n');
+        }
+        // Target tables, in the order of their first dependency.
+        let mut targets: Vec<(&str, String)> = vec![];
+        for entry in self.header.platform_deps() {
+            let (condition, dep) = header::platform_dep(entry.text).expect("platform dependency");
+            let dep = deps::expand(dep, self.src_dir)?;
+            match targets.iter_mut().find(|(other, _)| *other == condition) {
+                Some((_, deps)) => deps.push_str(&format!("{}\n", dep)),
+                None => targets.push((condition, format!("{}\n", dep))),
+            }
+        }
+        let mut lints = String::new();
+        for entry in self.header.lints() {
+            let key = header::lints_key(entry.text).expect("lints key");
+            lints.push_str(&format!("{} = {}\n", key, entry.value));
+        }
+        let package_key = |key| {
+            self.header
+                .package_keys()
+                .find(|entry| entry.key == key)
+                .map(|entry| entry.value)
+        };
+        let self_version = package_key("self");
+        let edition = package_key("edition");
+        let resources = self
+            .header
+            .settings()
+            .any(|entry| resources::is_setting(entry.key));
+        let mut generated = String::new();
+        for line in manifest.lines() {
+            match (self_version, edition) {
+                (Some(version), _) if line.starts_with("version = ") => {
+                    generated.push_str(&format!("version = {}", version))
+                }
+                (_, Some(edition)) if line.starts_with("edition = ") => {
+                    generated.push_str(&format!("edition = {}", edition))
+                }
+                _ => generated.push_str(line),
+            }
+            generated.push('\n');
+            if line == "[dependencies]" {
+                generated.push_str(&deps);
+                if resources {
+                    generated.push_str(resources::BUILD_DEPENDENCY);
+                }
+                for (condition, deps) in &targets {
+                    generated.push_str(&format!(
+                        "\n[target.'{}'.dependencies]\n{}",
+                        condition, deps
+                    ));
+                }
+                if !lints.is_empty() {
+                    generated.push_str("\n[lints]\n");
+                    generated.push_str(&lints);
+                }
+                break;
+            }
+        }
+        Ok(generated)
+    }
+}
+
+/// The project's manifest for a target built from the source file in a
+/// subdirectory of the project: `target` is appended, and the build script
+/// in the project directory, which Cargo would only find next to the
+/// manifest, is given by its path.
+fn nested_manifest(manifest: &str, build_script: bool, target: &str) -> String {
+    let mut nested = String::new();
+    for line in manifest.lines() {
+        nested.push_str(line);
+        nested.push('\n');
+        if build_script && line == "[package]" {
+            nested.push_str("build = \"../build.rs\"\n");
+        }
+    }
+    nested.push('\n');
+    nested.push_str(target);
+    nested
+}
+
+/// Writes the manifest for a target built in a subdirectory of the project,
+/// at `manifest`, relative to the project directory, and returns its path.
+/// With `keep_lock`, the program's lock file is copied next to it, so that
+/// the same dependency versions are used; otherwise a lock file left there
+/// is removed, so that the dependencies are resolved anew.
+pub fn write_nested(
+    project_dir: &Path,
+    manifest: &str,
+    target: &str,
+    keep_lock: bool,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let nested = nested_manifest(
+        &fs::read_to_string(project_dir.join("Cargo.toml"))?,
+        project_dir.join("build.rs").exists(),
+        target,
+    );
+    let path = project_dir.join(manifest);
+    fs::create_dir_all(path.parent().expect("manifest dir"))?;
+    fs::write(&path, nested)?;
+    let lock_path = path.with_file_name("Cargo.lock");
+    if keep_lock {
+        let program_lock = project_dir.join("Cargo.lock");
+        if program_lock.exists() {
+            fs::copy(program_lock, lock_path)?;
+        }
+    } else if lock_path.exists() {
+        fs::remove_file(lock_path)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{nested_manifest, ManifestGenerator};
+    use crate::header::parse_header;
+
+    const MANIFEST: &str =
+        "[package]\nname = \"prog\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n";
+
+    fn generate(source: &str) -> String {
+        let header = parse_header(source);
+        ManifestGenerator::new(&header, Path::new("/src"))
+            .generate(MANIFEST)
+            .unwrap()
+    }
+
+    #[test]
+    fn header_replaces_dependencies() {
+        let manifest = "[package]\nname = \"prog\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                        [dependencies]\nold = \"1\"\n\n[dev-dependencies]\nold-dev = \"1\"\n";
+        let header =
+            parse_header("// rand = \"0.8\"\n// [dev-dependencies]\n// tempfile = \"3\"\n");
+        let generated = ManifestGenerator::new(&header, Path::new("/src"))
+            .generate(manifest)
+            .unwrap();
+        assert_eq!(
+            generated,
+            "[package]\nname = \"prog\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\nrand = \"0.8\"\n[dev-dependencies]\ntempfile = \"3\"\n"
+        );
+    }
+
+    #[test]
+    fn package_keys() {
+        let manifest = generate("// self = \"1.2.0\"\n// edition = \"2018\"\n");
+        assert!(manifest.contains("version = \"1.2.0\"\nedition = \"2018\"\n"));
+        assert!(!manifest.contains("self"));
+    }
+
+    #[test]
+    fn expanded_dependencies() {
+        let manifest =
+            generate("// helper = { path = \"../helper\" }\n// fork = git:github.com/a/b#tag=v1\n");
+        let helper = Path::new("/src").join("../helper").display().to_string();
+        assert!(manifest.contains(&format!("helper = {{ path = \"{}\" }}\n", helper)));
+        assert!(manifest.contains("fork = { git = \"https://github.com/a/b\", tag = \"v1\" }\n"));
+        let header = parse_header("// fork = git:github.com/a/b#commit=1\n");
+        assert!(ManifestGenerator::new(&header, Path::new("/src"))
+            .generate(MANIFEST)
+            .is_err());
+    }
+
+    #[test]
+    fn platform_dependencies_and_lints() {
+        let manifest = generate(
+            "// [unix] nix = \"0.29\"\n// [windows] winreg = \"0.52\"\n// [unix] libc = \"0.2\"\n\
+             // lints.clippy = { pedantic = \"warn\" }\n// rand = \"0.8\"\n",
+        );
+        assert!(manifest.ends_with(
+            "[dependencies]\nrand = \"0.8\"\n\n\
+             [target.'cfg(unix)'.dependencies]\nnix = \"0.29\"\nlibc = \"0.2\"\n\n\
+             [target.'cfg(windows)'.dependencies]\nwinreg = \"0.52\"\n\n\
+             [lints]\nclippy = { pedantic = \"warn\" }\n"
+        ));
+    }
+
+    #[test]
+    fn windows_crate_is_a_dependency() {
+        let manifest = generate(
+            "// windows = \"0.58\"\n// windows.icon = \"app.ico\"\n\
+             // [target.'cfg(windows)'.dependencies]\n// windows = \"0.58\"\nfn main() {}\n",
+        );
+        assert!(manifest.contains("[dependencies]\nwindows = \"0.58\"\n"));
+        assert!(manifest.contains("[target.'cfg(windows)'.dependencies]\nwindows = \"0.58\"\n"));
+        assert!(!manifest.contains("windows.icon"));
+    }
+
+    #[test]
+    fn windows_dotted_dependency_keys() {
+        let manifest = generate("// windows.features = [\"Win32_Foundation\"]\nfn main() {}\n");
+        assert!(manifest.contains("[dependencies]\nwindows.features = [\"Win32_Foundation\"]\n"));
+    }
+
+    #[test]
+    fn nested_manifest_finds_the_build_script() {
+        let manifest = "[package]\nname = \"prog\"\n\n[dependencies]\nrand = \"0.8\"";
+        let target = "[lib]\npath = \"../src/main.rs\"\n";
+        assert_eq!(
+            nested_manifest(manifest, true, target),
+            "[package]\nbuild = \"../build.rs\"\nname = \"prog\"\n\n[dependencies]\n\
+             rand = \"0.8\"\n\n[lib]\npath = \"../src/main.rs\"\n"
+        );
+        assert_eq!(
+            nested_manifest(manifest, false, target),
+            "[package]\nname = \"prog\"\n\n[dependencies]\nrand = \"0.8\"\n\n\
+             [lib]\npath = \"../src/main.rs\"\n"
+        );
+    }
+}
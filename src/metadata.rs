@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use crate::json;
+
+// A package from the resolved dependency graph of the project.
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub description: Option<String>,
+    // Registry or repository the package comes from; none for local packages.
+    pub source: Option<String>,
+    // Ids of the direct dependencies.
+    pub deps: Vec<String>,
+}
+
+pub struct Metadata {
+    // Id of the program's own package.
+    pub root: String,
+    pub packages: Vec<Package>,
+}
+
+impl Metadata {
+    pub fn root_package(&self) -> &Package {
+        self.packages
+            .iter()
+            .find(|package| package.id == self.root)
+            .expect("root package")
+    }
+}
+
+// Runs "cargo metadata" for the manifest, resolving the dependencies if needed.
+pub fn load(toolchain: Option<&str>, manifest: &Path) -> Result<Metadata, Box<dyn Error>> {
+    let mut cargo = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cargo.arg(toolchain);
+    }
+    let output = cargo
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(manifest)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    let value = json::parse(&String::from_utf8_lossy(&output.stdout))?;
+    let resolve = value.get("resolve").ok_or("no dependency graph")?;
+    let root = resolve.str_at("root").ok_or("no root package")?.to_owned();
+    let mut packages = vec![];
+    for node in resolve
+        .get("nodes")
+        .map(json::Value::as_array)
+        .unwrap_or_default()
+    {
+        let id = node.str_at("id").ok_or("package id missing")?;
+        let package = value
+            .get("packages")
+            .map(json::Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .find(|package| package.str_at("id") == Some(id))
+            .ok_or("package missing from metadata")?;
+        packages.push(Package {
+            id: id.to_owned(),
+            name: package.str_at("name").unwrap_or_default().to_owned(),
+            version: package.str_at("version").unwrap_or_default().to_owned(),
+            license: package.str_at("license").map(str::to_owned),
+            description: package.str_at("description").map(str::to_owned),
+            source: package.str_at("source").map(str::to_owned),
+            deps: node
+                .get("dependencies")
+                .map(json::Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|dep| dep.as_str().map(str::to_owned))
+                .collect(),
+        });
+    }
+    Ok(Metadata { root, packages })
+}
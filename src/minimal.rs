@@ -0,0 +1,23 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::manifest;
+
+// Checking with the minimal dependency versions uses a separate manifest in a
+// subdirectory of the project, so that its lock file doesn't replace the
+// program's. The lock file is removed before each check, so that the
+// dependencies are resolved anew.
+pub const MANIFEST: &str = "minimal-versions/Cargo.toml";
+
+pub fn prepare(project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let manifest = fs::read_to_string(project_dir.join("Cargo.toml"))?;
+    let name = manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("name = "))
+        .ok_or("package name not found")?
+        .trim_matches('"');
+    let target = format!("[[bin]]\nname = \"{}\"\npath = \"../src/main.rs\"\n", name);
+    manifest::write_nested(project_dir, MANIFEST, &target, false)?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Passed to Cargo with --config only with --static, like the sanitizer
+// settings.
+pub const CONFIG: &str = ".cargo/static.toml";
+
+// The musl target for the architecture of the host. Hosts other than Linux get
+// the Linux target for their architecture, which cross can build for.
+pub fn target(host: &str) -> Option<String> {
+    let arch = host.split('-').next()?;
+    match arch {
+        "arm" if host.ends_with("hf") => Some("arm-unknown-linux-musleabihf".to_owned()),
+        "arm" => Some("arm-unknown-linux-musleabi".to_owned()),
+        "armv7" => Some("armv7-unknown-linux-musleabihf".to_owned()),
+        "aarch64" | "i686" | "powerpc64le" | "riscv64gc" | "s390x" | "x86_64" => {
+            Some(format!("{}-unknown-linux-musl", arch))
+        }
+        _ => None,
+    }
+}
+
+// The C runtime is linked statically by default on musl targets, but making it
+// explicit keeps the binary static should the default change.
+pub fn write_config(project_dir: &Path, target: &str) -> Result<(), Box<dyn Error>> {
+    let config = format!(
+        "[target.{}]\nrustflags = [\"-C\", \"target-feature=+crt-static\"]\n",
+        target
+    );
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Passed to Cargo with --config only with --native, like the sanitizer
+// settings.
+pub const CONFIG: &str = ".cargo/native.toml";
+
+// The flags are given for all targets with cfg(all()), since those are joined
+// with the ones for the target triple written by --static, while build.rustflags
+// would be ignored in favour of them.
+pub fn write_config(project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let config = "[target.'cfg(all())']\nrustflags = [\"-C\", \"target-cpu=native\"]\n";
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
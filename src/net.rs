@@ -0,0 +1,100 @@
+use std::env;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Network access goes through curl, which is available nearly everywhere and
+// spares the program a TLS stack.
+const TIMEOUT_SECS: &str = "20";
+const DOWNLOAD_TIMEOUT_SECS: &str = "300";
+
+// Cargo's own offline setting also disables cargo-single's network access.
+pub fn offline() -> bool {
+    matches!(
+        env::var("CARGO_NET_OFFLINE").as_deref(),
+        Ok("true") | Ok("1")
+    )
+}
+
+fn curl(url: &str, body: Option<&str>) -> Result<String, Box<dyn Error>> {
+    if offline() {
+        return Err("network access is disabled".into());
+    }
+    let mut curl = Command::new("curl");
+    curl.args(["-sSfL", "--max-time", TIMEOUT_SECS])
+        .args(["-A", concat!("cargo-single/", env!("CARGO_PKG_VERSION"))])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if body.is_some() {
+        curl.args([
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+        ])
+        .stdin(Stdio::piped());
+    }
+    let mut child = curl.arg(url).spawn().map_err(|e| format!("curl: {}", e))?;
+    if let Some(body) = body {
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(body.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn get(url: &str) -> Result<String, Box<dyn Error>> {
+    curl(url, None)
+}
+
+pub fn post_json(url: &str, body: &str) -> Result<String, Box<dyn Error>> {
+    curl(url, Some(body))
+}
+
+// Writes the response to a file, for binary contents.
+pub fn download(url: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    if offline() {
+        return Err("network access is disabled".into());
+    }
+    let output = Command::new("curl")
+        .args(["-sSfL", "--max-time", DOWNLOAD_TIMEOUT_SECS, "-o"])
+        .arg(path)
+        .arg(url)
+        .output()
+        .map_err(|e| format!("curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Percent-encodes text for use in a URL query.
+pub fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
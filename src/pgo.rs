@@ -0,0 +1,112 @@
+// Profile-guided optimization: the program is built instrumented, run to
+// collect a profile, and built again optimized with the profile. The settings
+// for each build are passed to Cargo with --config, and the builds are for the
+// host target given explicitly, so that build scripts and procedural macros
+// aren't instrumented.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const GENERATE_CONFIG: &str = ".cargo/pgo-generate.toml";
+pub const USE_CONFIG: &str = ".cargo/pgo-use.toml";
+
+// Directory in the project for the raw profiles and the merged one.
+const PROFILE_DIR: &str = "target/pgo-profiles";
+
+pub fn profile_dir(project_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(fs::canonicalize(project_dir)?.join(PROFILE_DIR))
+}
+
+fn write_config(
+    project_dir: &Path,
+    config: &str,
+    target: &str,
+    flag: &str,
+) -> Result<(), Box<dyn Error>> {
+    let config_path = project_dir.join(config);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    // The path is written as a TOML literal string, which needs no escaping of
+    // Windows path separators.
+    fs::write(
+        config_path,
+        format!("[target.{}]\nrustflags = ['{}']\n", target, flag),
+    )?;
+    Ok(())
+}
+
+// Writes the settings for both builds, and empties the profile directory.
+pub fn prepare(project_dir: &Path, target: &str) -> Result<(), Box<dyn Error>> {
+    let profiles = profile_dir(project_dir)?;
+    if profiles.exists() {
+        fs::remove_dir_all(&profiles)?;
+    }
+    fs::create_dir_all(&profiles)?;
+    write_config(
+        project_dir,
+        GENERATE_CONFIG,
+        target,
+        &format!("-Cprofile-generate={}", profiles.display()),
+    )?;
+    write_config(
+        project_dir,
+        USE_CONFIG,
+        target,
+        &format!(
+            "-Cprofile-use={}",
+            profiles.join("merged.profdata").display()
+        ),
+    )
+}
+
+// llvm-profdata from the llvm-tools component of the toolchain, which matches
+// the LLVM version of rustc, or else from the PATH.
+fn profdata_tool(toolchain: Option<&str>, host: &str) -> PathBuf {
+    let mut rustc = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        rustc.arg(toolchain);
+    }
+    let sysroot = rustc
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned());
+    let tool = format!("llvm-profdata{}", std::env::consts::EXE_SUFFIX);
+    sysroot
+        .map(|sysroot| {
+            Path::new(&sysroot)
+                .join("lib/rustlib")
+                .join(host)
+                .join("bin")
+                .join(&tool)
+        })
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(tool))
+}
+
+// Merges the raw profiles from the training run.
+pub fn merge(
+    project_dir: &Path,
+    toolchain: Option<&str>,
+    host: &str,
+) -> Result<(), Box<dyn Error>> {
+    let profiles = profile_dir(project_dir)?;
+    let status = Command::new(profdata_tool(toolchain, host))
+        .arg("merge")
+        .arg("-o")
+        .arg(profiles.join("merged.profdata"))
+        .arg(&profiles)
+        .status()
+        .map_err(|e| {
+            format!(
+                "error executing llvm-profdata: {}; install it with \"rustup component add llvm-tools\"",
+                e
+            )
+        })?;
+    if !status.success() {
+        return Err("llvm-profdata failed".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// External subcommands: "cargo single <name>" runs "cargo-single-<name>" from
+// the PATH, like Cargo runs "cargo-<name>". If one of the arguments is a source
+// file, its project is created or refreshed first, and passed to the plugin in
+// the environment.
+
+use std::env;
+use std::error::Error;
+use std::path::{self, Path, PathBuf};
+use std::process::Command;
+
+use crate::project::SingleProject;
+
+pub const PREFIX: &str = "cargo-single-";
+
+pub fn find(name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}{}", PREFIX, name, env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+}
+
+// The first argument naming an existing source file.
+pub fn project(args: &[String]) -> Option<SingleProject> {
+    args.iter()
+        .filter(|arg| arg.ends_with(".rs"))
+        .find_map(|arg| SingleProject::locate(arg).ok())
+}
+
+pub fn command(plugin: &Path, project: Option<&SingleProject>) -> Result<Command, Box<dyn Error>> {
+    let mut command = Command::new(plugin);
+    if let Ok(exe) = env::current_exe() {
+        command.env("CARGO_SINGLE", exe);
+    }
+    if let Some(project) = project {
+        project.prepare()?;
+        command
+            .env("CARGO_SINGLE_SOURCE", path::absolute(project.source())?)
+            .env("CARGO_SINGLE_PROJECT_DIR", path::absolute(project.dir())?)
+            .env(
+                "CARGO_SINGLE_MANIFEST_PATH",
+                path::absolute(project.dir().join("Cargo.toml"))?,
+            );
+    }
+    Ok(command)
+}
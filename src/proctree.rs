@@ -0,0 +1,327 @@
+// Running Cargo and the program it runs so that they don't outlive
+// cargo-single. On Unix, termination signals received by cargo-single are
+// passed on to the program and anything it started, and cargo-single exits
+// after them, with their exit status.
+//
+// When the standard input is a terminal, the program stays in the process
+// group of cargo-single, so that it can read from the terminal and be stopped
+// with Ctrl-Z; Ctrl-C then reaches it directly, and the other signals are sent
+// to its descendants found with pgrep. Otherwise, it gets a process group of
+// its own, which all the signals are sent to, reaching even processes which
+// were left behind by their parents.
+//
+// On Windows, the program is put into a job object, which kills the processes
+// in it when its last handle is closed, as happens when cargo-single is
+// terminated. Ctrl-C and Ctrl-Break reach the program through the console, and
+// cargo-single waits for it; any processes it leaves behind are then killed
+// with the job. Otherwise, they are kept, as on Unix.
+
+#[cfg(not(any(unix, windows)))]
+use std::io;
+#[cfg(not(any(unix, windows)))]
+use std::process::{Command, ExitStatus};
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{self, IsTerminal};
+    use std::os::raw::c_int;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, ExitStatus};
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    // The numbers are the same on Linux, macOS and the BSDs.
+    const SIGHUP: c_int = 1;
+    const SIGINT: c_int = 2;
+    const SIGQUIT: c_int = 3;
+    const SIGTERM: c_int = 15;
+    const SIGNALS: &[c_int] = &[SIGHUP, SIGINT, SIGQUIT, SIGTERM];
+
+    const SIG_DFL: usize = 0;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+        fn kill(pid: i32, sig: c_int) -> c_int;
+    }
+
+    // The last signal received, which the handler can only record; it's passed
+    // on by a thread watching it.
+    static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+    // Whether a signal was received while the last command ran.
+    static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_signal(signum: c_int) {
+        RECEIVED.store(signum, Ordering::SeqCst);
+        TERMINATED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn terminated() -> bool {
+        TERMINATED.load(Ordering::SeqCst)
+    }
+
+    fn set_handlers(handler: usize) {
+        for &signum in SIGNALS {
+            // SAFETY: the handler only stores to an atomic, which is
+            // async-signal-safe.
+            unsafe {
+                signal(signum, handler);
+            }
+        }
+    }
+
+    fn forward(pid: u32, own_group: bool, signum: c_int) {
+        if own_group {
+            // SAFETY: kill() has no memory safety requirements.
+            unsafe {
+                kill(-(pid as i32), signum);
+            }
+            return;
+        }
+        // Ctrl-C and Ctrl-\ are sent by the terminal to the whole process group.
+        if signum == SIGINT || signum == SIGQUIT {
+            return;
+        }
+        // The descendants are found before the process is signalled, since
+        // they may be reparented after that.
+        let mut pids = crate::watch::descendants(pid);
+        pids.push(pid);
+        for pid in pids {
+            // SAFETY: as above.
+            unsafe {
+                kill(pid as i32, signum);
+            }
+        }
+    }
+
+    pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+        let own_group = !io::stdin().is_terminal();
+        if own_group {
+            command.process_group(0);
+        }
+        RECEIVED.store(0, Ordering::SeqCst);
+        TERMINATED.store(false, Ordering::SeqCst);
+        set_handlers(on_signal as extern "C" fn(c_int) as usize);
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                set_handlers(SIG_DFL);
+                return Err(e);
+            }
+        };
+        let pid = child.id();
+        let done = Arc::new(AtomicBool::new(false));
+        let forwarder = {
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    thread::sleep(POLL_INTERVAL);
+                    match RECEIVED.swap(0, Ordering::SeqCst) {
+                        0 => (),
+                        signum => forward(pid, own_group, signum),
+                    }
+                }
+            })
+        };
+        let status = child.wait();
+        done.store(true, Ordering::SeqCst);
+        let _ = forwarder.join();
+        set_handlers(SIG_DFL);
+        status
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+    use std::io;
+    use std::mem;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::{Command, ExitStatus};
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    type Handle = *mut c_void;
+    type Bool = i32;
+
+    const CTRL_C_EVENT: u32 = 0;
+    const CTRL_BREAK_EVENT: u32 = 1;
+
+    const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION: i32 = 9;
+    // The program may still start processes which outlive it, with
+    // CREATE_BREAKAWAY_FROM_JOB, as it may with setsid() on Unix.
+    const JOB_OBJECT_LIMIT_BREAKAWAY_OK: u32 = 0x0000_0800;
+    const JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x0000_2000;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct BasicLimitInformation {
+        per_process_user_time_limit: i64,
+        per_job_user_time_limit: i64,
+        limit_flags: u32,
+        minimum_working_set_size: usize,
+        maximum_working_set_size: usize,
+        active_process_limit: u32,
+        affinity: usize,
+        priority_class: u32,
+        scheduling_class: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct IoCounters {
+        read_operation_count: u64,
+        write_operation_count: u64,
+        other_operation_count: u64,
+        read_transfer_count: u64,
+        write_transfer_count: u64,
+        other_transfer_count: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct ExtendedLimitInformation {
+        basic_limit_information: BasicLimitInformation,
+        io_info: IoCounters,
+        process_memory_limit: usize,
+        job_memory_limit: usize,
+        peak_process_memory_used: usize,
+        peak_job_memory_used: usize,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateJobObjectW(attributes: *mut c_void, name: *const u16) -> Handle;
+        fn SetInformationJobObject(
+            job: Handle,
+            class: i32,
+            information: *const c_void,
+            length: u32,
+        ) -> Bool;
+        fn AssignProcessToJobObject(job: Handle, process: Handle) -> Bool;
+        fn CloseHandle(handle: Handle) -> Bool;
+        fn SetConsoleCtrlHandler(
+            handler: Option<unsafe extern "system" fn(u32) -> Bool>,
+            add: Bool,
+        ) -> Bool;
+    }
+
+    // Whether Ctrl-C or Ctrl-Break was pressed while the last command ran.
+    static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+    // Ctrl-C and Ctrl-Break are left to the program, which also receives
+    // them. Closing the console, logging off and shutting down terminate
+    // cargo-single as usual, and the program with the job.
+    unsafe extern "system" fn on_ctrl(event: u32) -> Bool {
+        if event == CTRL_C_EVENT || event == CTRL_BREAK_EVENT {
+            TERMINATED.store(true, Ordering::SeqCst);
+            return 1;
+        }
+        0
+    }
+
+    pub fn terminated() -> bool {
+        TERMINATED.load(Ordering::SeqCst)
+    }
+
+    struct Job(Handle);
+
+    impl Job {
+        fn new() -> Option<Job> {
+            // SAFETY: null attributes and name are allowed.
+            let handle = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+            if handle.is_null() {
+                return None;
+            }
+            let job = Job(handle);
+            job.kill_on_close(true).then_some(job)
+        }
+
+        fn kill_on_close(&self, kill: bool) -> bool {
+            let mut information = ExtendedLimitInformation::default();
+            information.basic_limit_information.limit_flags = if kill {
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE | JOB_OBJECT_LIMIT_BREAKAWAY_OK
+            } else {
+                JOB_OBJECT_LIMIT_BREAKAWAY_OK
+            };
+            // SAFETY: the information is of the class given, and its size.
+            unsafe {
+                SetInformationJobObject(
+                    self.0,
+                    JOB_OBJECT_EXTENDED_LIMIT_INFORMATION,
+                    &information as *const _ as *const c_void,
+                    mem::size_of::<ExtendedLimitInformation>() as u32,
+                ) != 0
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle is owned by the job.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    fn run(command: &mut Command) -> io::Result<ExitStatus> {
+        let mut child = command.spawn()?;
+        // Without a job, as when cargo-single is itself in one which doesn't
+        // allow it, the program just runs unmanaged. The processes it starts
+        // before it's assigned escape the job, but Cargo starts none that
+        // early.
+        let job = Job::new().filter(|job| {
+            // SAFETY: both handles are valid while the job and the child are.
+            unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as Handle) != 0 }
+        });
+        let status = child.wait();
+        if let Some(job) = &job {
+            if !terminated() {
+                job.kill_on_close(false);
+            }
+        }
+        status
+    }
+
+    pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+        TERMINATED.store(false, Ordering::SeqCst);
+        // SAFETY: the handler only stores to an atomic.
+        unsafe {
+            SetConsoleCtrlHandler(Some(on_ctrl), 1);
+        }
+        let status = run(command);
+        // SAFETY: as above.
+        unsafe {
+            SetConsoleCtrlHandler(Some(on_ctrl), 0);
+        }
+        status
+    }
+}
+
+// Runs the command like Command::status().
+#[cfg(unix)]
+pub use unix::status;
+#[cfg(windows)]
+pub use windows::status;
+
+// Whether cargo-single was asked to terminate while the last command ran, so
+// that its failure is no reason to run it again.
+#[cfg(unix)]
+pub use unix::terminated;
+#[cfg(windows)]
+pub use windows::terminated;
+
+#[cfg(not(any(unix, windows)))]
+pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+    command.status()
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn terminated() -> bool {
+    false
+}
@@ -0,0 +1,59 @@
+// Overrides of the settings of the Cargo profile used for the build, given
+// with options like --lto, and passed to Cargo with --config, so that the
+// manifest is left as it is.
+
+// The settings, and the values allowed for them.
+const SETTINGS: &[(&str, &[&str])] = &[
+    ("lto", &["fat", "thin", "off"]),
+    ("strip", &["none", "debuginfo", "symbols"]),
+    ("opt-level", &["0", "1", "2", "3", "s", "z"]),
+    ("panic", &["unwind", "abort"]),
+    // Written as the "debug" setting, with "line-tables-only" for "line-tables".
+    ("debuginfo", &["none", "line-tables", "full"]),
+    ("split-debuginfo", &["off", "packed", "unpacked"]),
+    // Any positive number.
+    ("codegen-units", &[]),
+];
+
+pub fn check(key: &str, value: &str) -> Result<(), String> {
+    let allowed = SETTINGS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, allowed)| *allowed)
+        .expect("profile setting");
+    if allowed.is_empty() {
+        return match value.parse::<u32>() {
+            Ok(n) if n > 0 => Ok(()),
+            _ => Err(format!(
+                "invalid {} \"{}\", expected a positive number",
+                key, value
+            )),
+        };
+    }
+    if !allowed.contains(&value) {
+        return Err(format!(
+            "invalid {} \"{}\", expected one of: {}",
+            key,
+            value,
+            allowed.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+// The argument of --config for a setting of the release profile, or else of
+// the dev profile, which the test profile inherits.
+pub fn config_arg(release: bool, key: &str, value: &str) -> String {
+    let profile = if release { "release" } else { "dev" };
+    let (key, value) = match (key, value) {
+        ("debuginfo", "line-tables") => ("debug", "line-tables-only"),
+        ("debuginfo", value) => ("debug", value),
+        _ => (key, value),
+    };
+    let value = if value.parse::<u32>().is_ok() {
+        value.to_owned()
+    } else {
+        format!("\"{}\"", value)
+    };
+    format!("profile.{}.{}={}", profile, key, value)
+}
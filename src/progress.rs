@@ -0,0 +1,71 @@
+// Progress of the steps which can take a while, shown on a terminal unless
+// --quiet is given. cargo-single's own steps get a status line, erased when the
+// step is done. Cargo is passed --quiet by default to keep its status messages
+// out of the program's output, but that also hides its progress bar, which is
+// turned back on with its configuration.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::process::Command;
+
+pub struct Status {
+    shown: bool,
+}
+
+impl Status {
+    pub fn start(enabled: bool, message: &str) -> Status {
+        let shown = enabled && io::stderr().is_terminal();
+        if shown {
+            eprint!("cargo-single: {}...", message);
+            let _ = io::stderr().flush();
+        }
+        Status { shown }
+    }
+}
+
+impl Drop for Status {
+    fn drop(&mut self) {
+        if self.shown {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+// Cargo only draws the progress bar in quiet mode if its width is given, so
+// it's left off when the width of the terminal can't be found.
+fn terminal_width() -> Option<usize> {
+    if let Some(width) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(width);
+    }
+    if cfg!(windows) {
+        return None;
+    }
+    let output = Command::new("stty")
+        .arg("size")
+        .stdin(File::open("/dev/tty").ok()?)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+// Configuration arguments which make Cargo show its progress bar in spite of
+// --quiet.
+pub fn cargo_args() -> Vec<String> {
+    if !io::stderr().is_terminal() {
+        return vec![];
+    }
+    match terminal_width() {
+        Some(width) => vec![
+            "--config".to_owned(),
+            "term.progress.when=\"always\"".to_owned(),
+            "--config".to_owned(),
+            format!("term.progress.width={}", width),
+        ],
+        None => vec![],
+    }
+}
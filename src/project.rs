@@ -0,0 +1,453 @@
+// The project of a source file, created next to it, and the operations on it
+// shared by the command line and the library interface.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config;
+use crate::header::parse_header;
+use crate::log::log;
+use crate::manifest::ManifestGenerator;
+use crate::metadata;
+use crate::resources;
+use crate::runner::{self, RunResult};
+use crate::sidefiles::{self, BuildScript};
+use crate::wildcard;
+use crate::wsl;
+use crate::{checksum, header_setting, header_settings, header_values};
+
+/// The Cargo project of a single-file program, in the directory named like
+/// the source file without the extension, next to it.
+#[derive(Clone, Debug)]
+pub struct SingleProject {
+    source: PathBuf,
+    dir: PathBuf,
+}
+
+/// Options for the commands from [`SingleProject::cargo_command`], a subset of
+/// those of `cargo single`.
+#[derive(Clone, Debug)]
+pub struct CargoOptions {
+    /// Name of a toolchain installed with Rustup, without the `+`.
+    pub toolchain: Option<String>,
+    pub release: bool,
+    pub target: Option<String>,
+    /// The target directory, passed to Cargo with `--target-dir`. Without it,
+    /// the project's own is used, which is one per target with the
+    /// `build.separate-targets` setting.
+    pub target_dir: Option<PathBuf>,
+    /// Whether `--quiet` is passed to Cargo, as `cargo single` does by default.
+    pub quiet: bool,
+    /// Whether [`SingleProject::run`] collects the compiler's messages, which
+    /// are then not printed.
+    pub json_diagnostics: bool,
+}
+
+impl Default for CargoOptions {
+    fn default() -> CargoOptions {
+        CargoOptions {
+            toolchain: None,
+            release: false,
+            target: None,
+            target_dir: None,
+            quiet: true,
+            json_diagnostics: false,
+        }
+    }
+}
+
+impl SingleProject {
+    /// Finds the project of the source file, which must exist. The project
+    /// itself needn't exist yet.
+    pub fn locate(source: impl AsRef<Path>) -> io::Result<SingleProject> {
+        let source = source.as_ref();
+        if !fs::metadata(source)?.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: not a regular file", source.display()),
+            ));
+        }
+        Ok(SingleProject {
+            source: source.to_owned(),
+            dir: source.with_extension(""),
+        })
+    }
+
+    /// Uses the directory for the project instead of the one next to the
+    /// source file, for instance to keep projects for several configurations.
+    pub fn with_dir(self, dir: impl Into<PathBuf>) -> SingleProject {
+        SingleProject {
+            dir: dir.into(),
+            ..self
+        }
+    }
+
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn exists(&self) -> bool {
+        self.dir.is_dir()
+    }
+
+    /// Creates the project if it doesn't exist, updates its manifest from the
+    /// dependency comments, and links the side files into it, like
+    /// `cargo single refresh`.
+    pub fn refresh(&self) -> Result<(), Box<dyn Error>> {
+        if !self.exists() {
+            create(&self.source, &self.dir, true)?;
+        }
+        refresh_project(&self.source, &self.dir)?;
+        relink_source(&self.source, &self.dir)
+    }
+
+    /// Creates and refreshes the project if it doesn't exist, or else only
+    /// makes sure that the source file and the side files are linked into it,
+    /// which is what `cargo single` does before building.
+    pub fn prepare(&self) -> Result<(), Box<dyn Error>> {
+        if self.exists() {
+            relink_source(&self.source, &self.dir)
+        } else {
+            self.refresh()
+        }
+    }
+
+    /// A Cargo command for the project, like `build` or `run`. Arguments for
+    /// the command, or for the program after `--`, can be added to it.
+    pub fn cargo_command(&self, cmd: &str, options: &CargoOptions) -> Command {
+        let mut command = Command::new("cargo");
+        if let Some(toolchain) = options.toolchain.as_ref() {
+            command.arg(format!("+{}", toolchain));
+        }
+        command.arg(cmd);
+        if options.release {
+            command.arg("--release");
+        }
+        let build_dir = BuildDir::new(
+            &self.dir,
+            options.target_dir.clone(),
+            options.target.clone(),
+        );
+        command.args(build_dir.cargo_args());
+        if options.quiet {
+            command.arg("--quiet");
+        }
+        command
+            .arg("--manifest-path")
+            .arg(self.dir.join("Cargo.toml"));
+        command
+    }
+
+    /// Runs a Cargo command for the project, like `build`, `run` or `test`,
+    /// with arguments for the command, or for the program with `run`. The
+    /// project is created first if needed, and the outcome is returned
+    /// instead of exiting on a failure, like `cargo single` does.
+    pub fn run<I, S>(
+        &self,
+        cmd: &str,
+        options: &CargoOptions,
+        args: I,
+    ) -> Result<RunResult, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.prepare()?;
+        runner::run(self, cmd, options, args)
+    }
+
+    /// The path of the binary built with the options.
+    pub fn binary_path(&self, options: &CargoOptions) -> PathBuf {
+        let name = self.dir.file_name().unwrap_or_default().to_string_lossy();
+        BuildDir::new(
+            &self.dir,
+            options.target_dir.clone(),
+            options.target.clone(),
+        )
+        .binary(&name, options.release)
+    }
+}
+
+// The name of the project's target directory for the target: "target", or
+// with "build.separate-targets", "target-<target>" for another target than the
+// default one, so that switching targets doesn't rebuild the artifacts of the
+// other.
+pub(crate) fn target_subdir(target: Option<&str>) -> String {
+    let separate_targets = config::load()
+        .ok()
+        .and_then(|config| config.get_bool("build.separate-targets"))
+        .unwrap_or(false);
+    match target {
+        Some(target) if separate_targets => format!("target-{}", target),
+        _ => "target".to_owned(),
+    }
+}
+
+// Where the builds of a project for a target go, for the commands which build
+// the program and then use the binary.
+pub(crate) struct BuildDir {
+    target: Option<String>,
+    // The directory given with --target-dir.
+    target_dir: Option<PathBuf>,
+    dir: PathBuf,
+}
+
+impl BuildDir {
+    pub fn new(
+        project_dir: &Path,
+        target_dir: Option<PathBuf>,
+        target: Option<String>,
+    ) -> BuildDir {
+        let dir = match target_dir.as_ref() {
+            Some(dir) => dir.clone(),
+            None => project_dir.join(target_subdir(target.as_deref())),
+        };
+        BuildDir {
+            target,
+            target_dir,
+            dir,
+        }
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    pub fn target_dir(&self) -> Option<&Path> {
+        self.target_dir.as_deref()
+    }
+
+    // The --target and --target-dir arguments for Cargo.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(target) = self.target.as_ref() {
+            args.extend(["--target".to_owned(), target.clone()]);
+        }
+        if self.target_dir.is_some() || !self.dir.ends_with("target") {
+            args.push("--target-dir".to_owned());
+            args.push(self.dir.to_string_lossy().into_owned());
+        }
+        args
+    }
+
+    pub fn binary(&self, name: &str, release: bool) -> PathBuf {
+        let windows = match self.target.as_ref() {
+            Some(target) => target.contains("windows"),
+            None => cfg!(windows),
+        };
+        let mut path = self.dir.clone();
+        if let Some(target) = self.target.as_ref() {
+            path.push(target);
+        }
+        path.push(if release { "release" } else { "debug" });
+        path.push(format!("{}{}", name, if windows { ".exe" } else { "" }));
+        path
+    }
+}
+
+// Cargo not running at all is told apart from it failing to create the
+// project, since the command line exits with different statuses for them.
+#[derive(Debug)]
+pub(crate) enum CreateError {
+    Cargo(io::Error),
+    Setup(String),
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CreateError::Cargo(e) => write!(f, "error executing \"cargo new\": {}", e),
+            CreateError::Setup(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for CreateError {}
+
+// Creates the project with "cargo new", replacing its main.rs with a hard link
+// to the source file.
+pub(crate) fn create(file_src: &Path, project_dir: &Path, quiet: bool) -> Result<(), CreateError> {
+    log!(Info, "creating the project in {}", project_dir.display());
+    let new_args = if quiet {
+        &["new", "--quiet", "--bin"][..]
+    } else {
+        &["new", "--bin"][..]
+    };
+    // Cargo's messages are kept for the error, which would otherwise only say
+    // that "cargo new" failed.
+    let output = Command::new("cargo")
+        .args(new_args)
+        .arg(project_dir)
+        .stdout(Stdio::inherit())
+        .output()
+        .map_err(CreateError::Cargo)?;
+    if !output.status.success() {
+        return Err(CreateError::Setup(format!(
+            "error creating the project in {}: {}",
+            project_dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    io::stderr().write_all(&output.stderr).unwrap_or_default();
+    let main_src = project_dir.join("src").join("main.rs");
+    fs::remove_file(&main_src)
+        .map_err(|e| CreateError::Setup(format!("error removing main.rs: {}", e)))?;
+    fs::hard_link(file_src, &main_src).map_err(|e| {
+        CreateError::Setup(match wsl::link_hint(file_src) {
+            Some(hint) => format!("error hardlinking to main.rs: {}; {}", e, hint),
+            None => format!("error hardlinking to main.rs: {}", e),
+        })
+    })
+}
+
+// Wildcard versions are resolved to the latest releases, and recorded in the
+// manifest or, if so configured, in the source file.
+pub(crate) fn refresh_project(file_src: &Path, project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let cargo_path = project_dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&cargo_path)?;
+    let mut known = match manifest.split_once("[dependencies]\n") {
+        Some((_, deps)) => wildcard::previous(deps),
+        None => HashMap::new(),
+    };
+    let src = fs::read_to_string(file_src)?;
+    let in_source = config::load()?.get_str("refresh.wildcards") == Some("source");
+    let (resolved, failures) = wildcard::resolve(&src, &mut known, !in_source);
+    for failure in failures {
+        eprintln!(
+            "cargo-single: warning: can't resolve the \"*\" version of {}",
+            failure
+        );
+    }
+    // Writing in place keeps the hard link to main.rs.
+    if in_source && resolved != src {
+        fs::write(file_src, &resolved)?;
+    }
+    let src_dir = std::path::absolute(file_src)?
+        .parent()
+        .expect("source dir")
+        .to_path_buf();
+    copy_deps(
+        &resolved,
+        &src_dir,
+        cargo_path,
+        project_dir.join(".Cargo.tmp"),
+    )
+}
+
+// Editors which save by writing a new file and renaming it over the old one
+// break the hard link to main.rs, so compare the contents and re-link if needed.
+pub(crate) fn relink_source(file_src: &Path, project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut main_src = project_dir.join("src");
+    main_src.push("main.rs");
+    if fs::read(file_src)? != fs::read(&main_src)? {
+        log!(
+            Info,
+            "the hard link to {} is broken, relinking",
+            file_src.display()
+        );
+        fs::remove_file(&main_src)?;
+        fs::hard_link(file_src, &main_src)?;
+    }
+    link_side_files(file_src, project_dir)
+}
+
+pub(crate) fn side_files(file_src: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    Ok(sidefiles::paths(
+        file_src,
+        &header_values(file_src, "mod")?,
+        &header_values(file_src, "include")?,
+    )?)
+}
+
+pub(crate) fn link_side_files(file_src: &Path, project_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let build = header_setting(file_src, "build")?;
+    let resources = resources::build_script(file_src, &header_settings(file_src)?)?;
+    let build = match (build, resources) {
+        (Some(_), Some(_)) => {
+            return Err("the build setting can't be combined with windows settings".into())
+        }
+        (Some(build), None) => BuildScript::File(build),
+        (None, Some(script)) => BuildScript::Generated(script),
+        (None, None) => BuildScript::None,
+    };
+    sidefiles::link(file_src, project_dir, &side_files(file_src)?, build)
+}
+
+// Resolves the dependencies, and verifies the checksums of those pinned with
+// "checksum.<crate>" settings against the lock file.
+pub(crate) fn verify_checksums(
+    file_src: &Path,
+    project_dir: &Path,
+    toolchain: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let pins: Vec<_> = header_settings(file_src)?
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let name = key.strip_prefix("checksum.")?.trim().trim_matches('"');
+            Some((name.to_owned(), value))
+        })
+        .collect();
+    if pins.is_empty() {
+        return Ok(());
+    }
+    metadata::load(toolchain, &project_dir.join("Cargo.toml"))?;
+    let lock = fs::read_to_string(project_dir.join("Cargo.lock"))?;
+    let errors = checksum::verify(&lock, &pins);
+    if !errors.is_empty() {
+        return Err(errors.join("\n").into());
+    }
+    Ok(())
+}
+
+fn copy_deps(
+    src: &str,
+    src_dir: &Path,
+    cargo_path: PathBuf,
+    cargo_tmp: PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    let manifest = fs::read_to_string(&cargo_path)?;
+    let header = parse_header(src);
+    let generated = ManifestGenerator::new(&header, src_dir).generate(&manifest)?;
+    fs::write(&cargo_tmp, generated)?;
+    fs::rename(&cargo_tmp, &cargo_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::BuildDir;
+
+    #[test]
+    fn build_dir_with_target_dir() {
+        let build_dir = BuildDir::new(
+            Path::new("prog"),
+            Some(PathBuf::from("/tmp/build")),
+            Some("x86_64-pc-windows-gnu".to_owned()),
+        );
+        assert_eq!(
+            build_dir.cargo_args(),
+            [
+                "--target",
+                "x86_64-pc-windows-gnu",
+                "--target-dir",
+                "/tmp/build"
+            ]
+        );
+        assert_eq!(
+            build_dir.binary("prog", true),
+            Path::new("/tmp/build/x86_64-pc-windows-gnu/release/prog.exe")
+        );
+    }
+}
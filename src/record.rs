@@ -0,0 +1,64 @@
+// Recording a profile of a program with the native profiler, for "profile":
+// perf on Linux, and the Time Profiler of Instruments, with xctrace, on macOS.
+// The program is built in release mode with debug symbols and frame pointers,
+// so that the profilers can walk the stacks and name the functions.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::tool_available;
+
+// Passed to Cargo with --config, so that the manifest is left alone.
+pub const BUILD_CONFIGS: &[&str] = &[
+    "profile.release.debug=true",
+    "target.'cfg(all())'.rustflags=['-Cforce-frame-pointers=yes']",
+];
+
+// Where the profile of the program built from the source file is written: next
+// to the source file, named after it.
+pub fn output_path(file_src: &Path) -> Result<PathBuf, String> {
+    let extension = if cfg!(target_os = "linux") {
+        "perf.data"
+    } else if cfg!(target_os = "macos") {
+        "trace"
+    } else {
+        return Err("profile is only supported on Linux and macOS".to_owned());
+    };
+    Ok(file_src.with_extension(extension))
+}
+
+// The profiler command, which runs the binary with the arguments.
+pub fn command(binary: &Path, output: &Path, args: &[String]) -> Result<Command, String> {
+    if cfg!(target_os = "linux") {
+        if !tool_available("perf") {
+            return Err("profile needs perf".to_owned());
+        }
+        let mut perf = Command::new("perf");
+        perf.args(["record", "--call-graph", "fp", "--output"])
+            .arg(output)
+            .arg("--")
+            .arg(binary)
+            .args(args);
+        Ok(perf)
+    } else {
+        if !tool_available("xcrun") {
+            return Err("profile needs xctrace, from Xcode".to_owned());
+        }
+        // xctrace refuses to overwrite a trace.
+        let _ = std::fs::remove_dir_all(output);
+        let mut xctrace = Command::new("xcrun");
+        xctrace
+            .args([
+                "xctrace",
+                "record",
+                "--template",
+                "Time Profiler",
+                "--output",
+            ])
+            .arg(output)
+            .args(["--launch", "--"])
+            .arg(binary)
+            .args(args);
+        Ok(xctrace)
+    }
+}
@@ -0,0 +1,106 @@
+// Programs run from a URL, like a gist: the source file is downloaded once,
+// after a confirmation, and kept in the "remote" subdirectory of the cache
+// directory, in a directory named after a hash of the URL, so that the program
+// keeps its name and is only built once.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::cache;
+use crate::checksum;
+use crate::config;
+use crate::net;
+
+pub fn is_url(arg: &str) -> bool {
+    arg.starts_with("https://") || arg.starts_with("http://")
+}
+
+// The name of the source file, from the last component of the URL's path.
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let stem = name.strip_suffix(".rs").unwrap_or(name);
+    let stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() || !stem.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        format!("remote_{}.rs", stem)
+    } else {
+        format!("{}.rs", stem)
+    }
+}
+
+fn confirm(url: &str, source: &str) -> Result<bool, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        return Err(format!(
+            "{}: not confirmed; use --yes to build a downloaded program without asking",
+            url
+        )
+        .into());
+    }
+    eprint!(
+        "cargo-single: downloaded {} lines from {}; build and run it? [y/N] ",
+        source.lines().count(),
+        url
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+// Returns the path of the cached source file, downloading it if it isn't in the
+// cache. The checksum, "sha256:<hex>", is verified each time.
+pub fn fetch(url: &str, checksum: Option<&str>, yes: bool) -> Result<PathBuf, Box<dyn Error>> {
+    let expected = match checksum {
+        Some(checksum) => Some(
+            checksum
+                .strip_prefix(checksum::PREFIX)
+                .ok_or_else(|| format!("checksum must start with \"{}\"", checksum::PREFIX))?
+                .to_ascii_lowercase(),
+        ),
+        None => None,
+    };
+    let dir = config::cache_dir()
+        .ok_or("no cache directory")?
+        .join("remote")
+        .join(format!("{:016x}", cache::hash(url)));
+    let path = dir.join(file_name(url));
+    let cached = path.is_file();
+    let source = if cached {
+        fs::read_to_string(&path)?
+    } else {
+        net::get(url)?
+    };
+    if let Some(expected) = expected {
+        let actual = checksum::sha256(source.as_bytes());
+        if actual != expected {
+            return Err(format!(
+                "checksum mismatch for {}: expected {}{}, found {}{}",
+                url,
+                checksum::PREFIX,
+                expected,
+                checksum::PREFIX,
+                actual
+            )
+            .into());
+        }
+    }
+    if !cached {
+        if !yes && !confirm(url, &source)? {
+            return Err(format!("{}: not confirmed", url).into());
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(&path, source)?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,176 @@
+// A line-based REPL. The items and statements entered so far are kept in a
+// program in the cache directory, which is rebuilt and run for each input, so
+// that Cargo's incremental compilation keeps it reasonably fast. Input which
+// fails to build or run is discarded.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::cache;
+use crate::config;
+use crate::eval;
+use crate::scaffold::DepSpec;
+
+// Printed by the program before running the new input, so that the output of
+// the statements entered earlier, which run again, can be left out.
+const MARKER: &str = "\u{1}cargo-single-repl\u{1}";
+
+const ITEM_KEYWORDS: &[&str] = &[
+    "#[",
+    "const ",
+    "enum ",
+    "extern ",
+    "fn ",
+    "impl ",
+    "impl<",
+    "macro_rules!",
+    "mod ",
+    "pub ",
+    "static ",
+    "struct ",
+    "trait ",
+    "type ",
+    "union ",
+    "unsafe fn ",
+    "unsafe impl ",
+    "use ",
+];
+
+const HELP: &str = "Enter items, statements ending with \";\", or expressions to print.\n\
+                    Commands: :show prints the program, :reset forgets the input, :quit exits.";
+
+#[derive(Default)]
+struct Session {
+    header: String,
+    items: Vec<String>,
+    statements: Vec<String>,
+}
+
+impl Session {
+    fn source(&self, items: &[&str], code: &str) -> String {
+        // Items and variables entered early on are often unused for a while.
+        let mut source = self.header.clone();
+        source.push_str("#![allow(unused)]\n\n");
+        for item in self
+            .items
+            .iter()
+            .map(String::as_str)
+            .chain(items.iter().copied())
+        {
+            source.push_str(item);
+            source.push_str("\n\n");
+        }
+        source.push_str("fn main() {\n");
+        for statement in &self.statements {
+            source.push_str(&format!("    {}\n", statement));
+        }
+        source.push_str(&format!("    println!(\"{}\");\n", MARKER.escape_default()));
+        if !code.is_empty() {
+            source.push_str(&format!("    {}\n", code));
+        }
+        source.push_str("}\n");
+        source
+    }
+}
+
+// Whether the brackets in the input are balanced, so that it's complete.
+fn is_complete(input: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in input.chars() {
+        match (c, in_string) {
+            (_, true) if escaped => escaped = false,
+            ('\\', true) => escaped = true,
+            ('"', _) => in_string = !in_string,
+            ('(' | '[' | '{', false) => depth += 1,
+            (')' | ']' | '}', false) => depth -= 1,
+            _ => (),
+        }
+    }
+    depth <= 0 && !in_string
+}
+
+// Builds and runs the program, printing its output after the marker. Returns
+// whether it succeeded.
+fn run(path: &Path, source: &str) -> Result<bool, Box<dyn Error>> {
+    fs::write(path, source)?;
+    let output = Command::new(env::current_exe()?)
+        .args(["single", "run"])
+        .arg(path)
+        .stderr(io::stderr())
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Some((_, new)) = stdout.split_once(&format!("{}\n", MARKER)) {
+        print!("{}", new);
+    }
+    Ok(output.status.success())
+}
+
+pub fn repl(deps: &[DepSpec]) -> Result<(), Box<dyn Error>> {
+    let mut session = Session {
+        header: eval::header(deps),
+        ..Session::default()
+    };
+    // The program is kept between sessions with the same dependencies, so that
+    // they don't have to be built again.
+    let dir = config::cache_dir()
+        .ok_or("no cache directory")?
+        .join("repl");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("repl_{:016x}.rs", cache::hash(&session.header)));
+    eprintln!("{}", HELP);
+    let stdin = io::stdin();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { ">> " } else { ".. " });
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        input.push_str(&line);
+        if !is_complete(&input) {
+            continue;
+        }
+        let entry = input.trim().to_owned();
+        input.clear();
+        match entry.as_str() {
+            "" => continue,
+            ":quit" | ":q" => return Ok(()),
+            ":show" => {
+                print!("{}", session.source(&[], ""));
+                continue;
+            }
+            ":reset" => {
+                session.items.clear();
+                session.statements.clear();
+                continue;
+            }
+            ":help" => {
+                eprintln!("{}", HELP);
+                continue;
+            }
+            _ => (),
+        }
+        if ITEM_KEYWORDS
+            .iter()
+            .any(|keyword| entry.starts_with(keyword))
+        {
+            if run(&path, &session.source(&[&entry], ""))? {
+                session.items.push(entry);
+            }
+        } else if eval::is_statement(&entry) {
+            if run(&path, &session.source(&[], &entry))? {
+                session.statements.push(entry);
+            }
+        } else {
+            run(&path, &session.source(&[], &eval::print_value(&entry)))?;
+        }
+    }
+}
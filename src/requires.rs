@@ -0,0 +1,123 @@
+// Programs which must be run before a program, listed with a "requires" setting
+// like `// requires = ["./gen_data.rs"]`. The paths are relative to the
+// directory of the source file which lists them.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Set when running the required programs, which are all run by the program
+// which needs them, so that they don't run their own requirements again.
+pub const SKIP_ENV: &str = "CARGO_SINGLE_SKIP_REQUIRES";
+
+// Written to the project of a required program after it has run successfully.
+const STAMP: &str = "target/cargo-single-requires.stamp";
+
+// Returns the value of the "requires" setting of a source file.
+pub type ReadSetting<'a> = &'a dyn Fn(&Path) -> Result<Option<String>, Box<dyn Error>>;
+
+// Parses the value of the setting, an array of strings.
+fn parse(value: &str) -> Result<Vec<String>, String> {
+    let items = value
+        .trim()
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .ok_or_else(|| format!("requires must be an array of paths, not {}", value))?;
+    items
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.strip_prefix('"')
+                .and_then(|item| item.strip_suffix('"'))
+                .map(str::to_owned)
+                .ok_or_else(|| format!("requires: {} is not a quoted path", item))
+        })
+        .collect()
+}
+
+// The programs directly required by a source file.
+fn direct(file_src: &Path, setting: Option<&str>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let setting = match setting {
+        Some(setting) => setting,
+        None => return Ok(vec![]),
+    };
+    let dir = file_src.parent().unwrap_or(Path::new(""));
+    let mut paths = vec![];
+    for path in parse(setting)? {
+        let joined = dir.join(&path);
+        let path = fs::canonicalize(&joined)
+            .map_err(|e| format!("{}: {}", joined.to_string_lossy(), e))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// Returns the programs required by the source file, directly or through other
+// required programs, each of them after the programs it requires.
+pub fn order(file_src: &Path, setting: ReadSetting) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fn visit(
+        file_src: &Path,
+        setting: ReadSetting,
+        visiting: &mut Vec<PathBuf>,
+        order: &mut Vec<PathBuf>,
+    ) -> Result<(), Box<dyn Error>> {
+        if order.iter().any(|done| done == file_src) {
+            return Ok(());
+        }
+        if let Some(pos) = visiting.iter().position(|path| path == file_src) {
+            let cycle: Vec<_> = visiting[pos..]
+                .iter()
+                .chain(Some(&file_src.to_owned()))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            return Err(format!("circular requirements: {}", cycle.join(" -> ")).into());
+        }
+        visiting.push(file_src.to_owned());
+        for path in direct(file_src, setting(file_src)?.as_deref())? {
+            visit(&path, setting, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(file_src.to_owned());
+        Ok(())
+    }
+    let file_src = fs::canonicalize(file_src)?;
+    let mut order = vec![];
+    visit(&file_src, setting, &mut vec![], &mut order)?;
+    order.pop();
+    Ok(order)
+}
+
+fn stamp(file_src: &Path) -> Option<SystemTime> {
+    fs::metadata(file_src.with_extension("").join(STAMP))
+        .and_then(|md| md.modified())
+        .ok()
+}
+
+// Whether the program has run since its source file, and the programs it
+// requires, were last changed or run.
+pub fn is_up_to_date(file_src: &Path, setting: Option<&str>) -> Result<bool, Box<dyn Error>> {
+    let ran = match stamp(file_src) {
+        Some(ran) => ran,
+        None => return Ok(false),
+    };
+    if fs::metadata(file_src)?.modified()? > ran {
+        return Ok(false);
+    }
+    for path in direct(file_src, setting)? {
+        if stamp(&path).is_none_or(|required| required > ran) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+pub fn mark(file_src: &Path) -> Result<(), Box<dyn Error>> {
+    let path = file_src.with_extension("").join(STAMP);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, "")?;
+    Ok(())
+}
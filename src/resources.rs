@@ -0,0 +1,72 @@
+// Windows resources: an icon and the version information shown by Explorer and
+// Task Manager, given with `// windows.icon = "app.ico"` and similar settings.
+// They are compiled into the executable by a build script, generated in the
+// project, which uses the winres crate. The build script does nothing when not
+// building on Windows for Windows, so the settings don't get in the way
+// elsewhere.
+
+use std::fs;
+use std::path::Path;
+
+// Settings for the fields of the version information, and the names of the
+// fields. The file and product versions are taken by winres from the package
+// version, which can be set with the "self" setting.
+const FIELDS: &[(&str, &str)] = &[
+    ("windows.company", "CompanyName"),
+    ("windows.copyright", "LegalCopyright"),
+    ("windows.description", "FileDescription"),
+    ("windows.product-name", "ProductName"),
+];
+
+pub const BUILD_DEPENDENCY: &str =
+    "\n[target.'cfg(windows)'.build-dependencies]\nwinres = \"0.1\"\n";
+
+// Whether a header key is one of the settings, and not a dotted key of a
+// dependency on the windows crate, like `windows.features`.
+pub fn is_setting(key: &str) -> bool {
+    key == "windows.icon" || FIELDS.iter().any(|(setting, _)| *setting == key)
+}
+
+// Returns the build script for the "windows.*" settings, or None if there are
+// none.
+pub fn build_script(
+    file_src: &Path,
+    settings: &[(String, String)],
+) -> Result<Option<String>, String> {
+    let mut calls = String::new();
+    let mut icon = None;
+    for (key, value) in settings.iter().filter(|(key, _)| is_setting(key)) {
+        if key == "windows.icon" {
+            let dir = match file_src.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let path = fs::canonicalize(dir.join(value))
+                .map_err(|e| format!("windows.icon: {}: {}", value, e))?;
+            icon = Some(path.to_string_lossy().into_owned());
+            continue;
+        }
+        if let Some((_, field)) = FIELDS.iter().find(|(setting, _)| setting == key) {
+            calls.push_str(&format!("        res.set({:?}, {:?});\n", field, value));
+        }
+    }
+    if calls.is_empty() && icon.is_none() {
+        return Ok(None);
+    }
+    let mut script = String::from(
+        "// Generated by cargo-single from the windows settings of the source file.\n\n\
+         fn main() {\n    #[cfg(windows)]\n    \
+         if std::env::var(\"CARGO_CFG_TARGET_OS\").as_deref() == Ok(\"windows\") {\n        \
+         let mut res = winres::WindowsResource::new();\n",
+    );
+    if let Some(icon) = icon {
+        script.push_str(&format!(
+            "        println!(\"cargo:rerun-if-changed={{}}\", {:?});\n        res.set_icon({:?});\n",
+            icon, icon
+        ));
+    }
+    script.push_str(&calls);
+    script
+        .push_str("        res.compile().expect(\"compiling the Windows resources\");\n    }\n}\n");
+    Ok(Some(script))
+}
@@ -0,0 +1,83 @@
+// Running the program again when it fails, for "run --restart on-failure",
+// waiting twice as long before each restart, up to a minute.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::log::log;
+use crate::proctree;
+
+const FIRST_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+pub struct Policy {
+    // The most restarts, if limited.
+    pub max: Option<u32>,
+}
+
+pub fn parse(value: &str) -> Result<Policy, String> {
+    let invalid = || {
+        format!(
+            "invalid restart policy \"{}\", expected on-failure or on-failure:max=<n>",
+            value
+        )
+    };
+    match value.split_once(':') {
+        None if value == "on-failure" => Ok(Policy { max: None }),
+        Some(("on-failure", max)) => match max.strip_prefix("max=").map(str::parse) {
+            Some(Ok(max)) => Ok(Policy { max: Some(max) }),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+fn describe(status: ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("exited with status {}", code),
+        None => "was terminated by a signal".to_owned(),
+    }
+}
+
+// Runs the command until it succeeds, cargo-single is asked to terminate, or
+// the restarts run out, and returns its last exit status.
+pub fn supervise(name: &str, command: &mut Command, policy: &Policy) -> io::Result<ExitStatus> {
+    let mut restarts = 0;
+    let mut delay = FIRST_DELAY;
+    loop {
+        let started = Instant::now();
+        let status = proctree::status(command)?;
+        if status.success() || proctree::terminated() {
+            return Ok(status);
+        }
+        if policy.max.is_some_and(|max| restarts >= max) {
+            eprintln!(
+                "cargo-single: {} {}, not restarting it again",
+                name,
+                describe(status)
+            );
+            return Ok(status);
+        }
+        // A program which ran for a while before failing is restarted soon.
+        if started.elapsed() > MAX_DELAY {
+            delay = FIRST_DELAY;
+        }
+        restarts += 1;
+        eprintln!(
+            "cargo-single: {} {}, restarting it in {} s (restart {}{})",
+            name,
+            describe(status),
+            delay.as_secs(),
+            restarts,
+            policy
+                .max
+                .map(|max| format!(" of {}", max))
+                .unwrap_or_default()
+        );
+        log!(Info, "restarting {} in {:?}", name, delay);
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_DELAY);
+    }
+}
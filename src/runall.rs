@@ -0,0 +1,463 @@
+// Running every program in a directory as a batch, for "run-all": the source
+// files with dependency comments are all built first, and those which built
+// are run, each with cargo-single itself, one at a time or several at once.
+// Instead of being built and run, they may also be checked or tested. With
+// several at once, the output of each is printed when it finishes. With the
+// summary printed as JSON, the standard output of the programs goes to the
+// standard error instead.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli;
+use crate::deps;
+use crate::glob;
+use crate::json::{self, Value};
+
+// Patterns in the directory which exclude source files, one per line, like
+// --exclude.
+pub const IGNORE_FILE: &str = ".csingleignore";
+
+// The commands which can be run for each source file.
+pub const COMMANDS: &[&str] = &["run", "check", "test"];
+
+// The formats of the reports written with --report.
+pub const REPORT_FORMATS: &[&str] = &["junit", "json"];
+
+// The arguments of run-all, other than --format.
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub dir: String,
+    pub jobs: usize,
+    pub command: String,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub reports: Vec<(String, PathBuf)>,
+}
+
+// Parses the arguments: the directory is the one which is neither an option
+// nor the value of one, so the options may come before or after it.
+pub fn parse_args(args: Vec<String>) -> Result<Args, String> {
+    let mut dir = None;
+    let mut parsed = Args {
+        dir: String::new(),
+        jobs: 1,
+        command: "run".to_owned(),
+        include: vec![],
+        exclude: vec![],
+        reports: vec![],
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if dir.is_some() {
+                return Err(format!("run-all takes one directory, not also \"{}\"", arg));
+            }
+            dir = Some(arg);
+            continue;
+        }
+        if !["--jobs", "--command", "--include", "--exclude", "--report"].contains(&arg.as_str()) {
+            return Err(format!("unknown option \"{}\" for run-all", arg));
+        }
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("{} needs a value", arg)),
+        };
+        match arg.as_str() {
+            "--command" if COMMANDS.contains(&value.as_str()) => parsed.command = value,
+            "--command" => {
+                return Err(format!(
+                    "run-all can't run \"{}\", expected one of: {}",
+                    value,
+                    COMMANDS.join(", ")
+                ))
+            }
+            "--report" => parsed.reports.push(parse_report(&value)?),
+            "--jobs" => match value.parse() {
+                Ok(n) if n > 0 => parsed.jobs = n,
+                _ => return Err(format!("--jobs needs a positive number, not \"{}\"", value)),
+            },
+            "--include" => parsed.include.push(value),
+            _ => parsed.exclude.push(value),
+        }
+    }
+    match dir {
+        Some(dir) => Ok(Args { dir, ..parsed }),
+        None => Err("run-all needs a source directory".to_owned()),
+    }
+}
+
+pub struct Outcome {
+    pub source: PathBuf,
+    // The command which was run last, which is "build" if a program to be run
+    // failed to build.
+    pub stage: &'static str,
+    pub success: bool,
+    // None if it was terminated by a signal or couldn't be started.
+    pub code: Option<i32>,
+    // Of all the commands run for the source file.
+    pub duration: Duration,
+}
+
+// Whether the path, relative to the directory, matches the pattern, which is
+// matched against the file name only if it has no "/".
+fn excluded_by(pattern: &str, relative: &Path) -> bool {
+    if pattern.contains('/') {
+        glob::path_matches(pattern, relative)
+    } else {
+        relative
+            .file_name()
+            .is_some_and(|name| glob::matches(pattern, &name.to_string_lossy()))
+    }
+}
+
+// The source files which start with dependency comments, in the order of
+// their paths: those in the directory, not its subdirectories, or those
+// matching the include patterns, relative to the directory, less those
+// matching the exclude patterns or the ones in the ignore file. Files in Cargo
+// projects, including those of the source files, are skipped.
+pub fn scripts(dir: &Path, include: &[String], exclude: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut exclude = exclude.to_vec();
+    match fs::read_to_string(dir.join(IGNORE_FILE)) {
+        Ok(text) => exclude.extend(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_owned),
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+    let mut candidates = vec![];
+    if include.is_empty() {
+        for entry in fs::read_dir(dir)? {
+            candidates.push(PathBuf::from(entry?.file_name()));
+        }
+    } else {
+        for pattern in include {
+            candidates.extend(glob::expand(dir, pattern));
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+    let mut scripts = vec![];
+    for relative in candidates {
+        let path = dir.join(&relative);
+        if !path.is_file()
+            || path.extension().unwrap_or_default() != "rs"
+            || exclude
+                .iter()
+                .any(|pattern| excluded_by(pattern, &relative))
+            || relative
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| dir.join(ancestor).join("Cargo.toml").is_file())
+        {
+            continue;
+        }
+        let has_header = fs::read_to_string(&path)
+            .map(|source| deps::header(&source).next().is_some())
+            .unwrap_or(false);
+        if has_header {
+            scripts.push(path);
+        }
+    }
+    Ok(scripts)
+}
+
+// Runs cargo-single with the command for each source file, and returns the
+// exit statuses and durations in the same order.
+fn each(
+    exe: &Path,
+    cmd: &str,
+    sources: &[&Path],
+    jobs: usize,
+    to_stderr: bool,
+) -> Vec<(bool, Option<i32>, Duration)> {
+    let command = |source: &Path| {
+        let mut command = Command::new(exe);
+        command.args(["single", cmd]).arg(source);
+        if to_stderr {
+            command.stdout(io::stderr());
+        }
+        command
+    };
+    if jobs <= 1 {
+        return sources
+            .iter()
+            .map(|source| {
+                let start = Instant::now();
+                match command(source).status() {
+                    Ok(status) => (status.success(), status.code(), start.elapsed()),
+                    Err(e) => {
+                        eprintln!("cargo-single: {}: {}", source.display(), e);
+                        (false, None, start.elapsed())
+                    }
+                }
+            })
+            .collect();
+    }
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![(false, None, Duration::ZERO); sources.len()]);
+    let print = Mutex::new(());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(sources.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(source) = sources.get(i) else {
+                    break;
+                };
+                let start = Instant::now();
+                let output = command(source).stdin(Stdio::null()).output();
+                let duration = start.elapsed();
+                let _lock = print.lock().expect("print lock");
+                eprintln!("cargo-single: {} {}", cmd, source.display());
+                let result = match output {
+                    Ok(Output {
+                        status,
+                        stdout,
+                        stderr,
+                    }) => {
+                        if to_stderr {
+                            let _ = io::stderr().write_all(&stdout);
+                        } else {
+                            let _ = io::stdout().write_all(&stdout);
+                        }
+                        let _ = io::stderr().write_all(&stderr);
+                        (status.success(), status.code(), duration)
+                    }
+                    Err(e) => {
+                        eprintln!("cargo-single: {}: {}", source.display(), e);
+                        (false, None, duration)
+                    }
+                };
+                results.lock().expect("results lock")[i] = result;
+            });
+        }
+    });
+    results.into_inner().expect("results")
+}
+
+// Runs the command, one of COMMANDS, for each source file; programs are all
+// built before any is run.
+pub fn run_all(
+    exe: &Path,
+    cmd: &str,
+    scripts: &[PathBuf],
+    jobs: usize,
+    to_stderr: bool,
+) -> Vec<Outcome> {
+    let cmd = COMMANDS
+        .iter()
+        .find(|command| **command == cmd)
+        .expect("run-all command");
+    let sources: Vec<&Path> = scripts.iter().map(PathBuf::as_path).collect();
+    if *cmd != "run" {
+        return scripts
+            .iter()
+            .zip(each(exe, cmd, &sources, jobs, to_stderr))
+            .map(|(source, (success, code, duration))| Outcome {
+                source: source.clone(),
+                stage: cmd,
+                success,
+                code,
+                duration,
+            })
+            .collect();
+    }
+    let built = each(exe, "build", &sources, jobs, to_stderr);
+    let runnable: Vec<&Path> = sources
+        .iter()
+        .zip(&built)
+        .filter(|(_, (success, _, _))| *success)
+        .map(|(source, _)| *source)
+        .collect();
+    let mut ran = each(exe, "run", &runnable, jobs, to_stderr).into_iter();
+    scripts
+        .iter()
+        .zip(built)
+        .map(|(source, (built, code, build_time))| {
+            let (stage, (success, code, run_time)) = if built {
+                ("run", ran.next().expect("run outcome"))
+            } else {
+                ("build", (false, code, Duration::ZERO))
+            };
+            Outcome {
+                source: source.clone(),
+                stage,
+                success,
+                code,
+                duration: build_time + run_time,
+            }
+        })
+        .collect()
+}
+
+// How the source file fared, for the summary and the reports.
+pub fn describe(outcome: &Outcome) -> String {
+    match (outcome.success, outcome.code) {
+        (true, _) => "ok".to_owned(),
+        (false, Some(code)) => format!("{} failed with status {}", outcome.stage, code),
+        (false, None) => format!("{} failed", outcome.stage),
+    }
+}
+
+pub fn to_json(outcomes: &[Outcome]) -> Value {
+    let scripts = outcomes
+        .iter()
+        .map(|outcome| {
+            json::object([
+                ("source", outcome.source.to_string_lossy().as_ref().into()),
+                ("stage", outcome.stage.into()),
+                ("success", outcome.success.into()),
+                ("code", outcome.code.map(i64::from).into()),
+                (
+                    "duration",
+                    Value::Number(format!("{:.3}", outcome.duration.as_secs_f64())),
+                ),
+            ])
+        })
+        .collect();
+    json::object([("scripts", Value::Array(scripts))])
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A JUnit XML report, with a test suite named after the directory, and a test
+// case for each source file.
+pub fn to_junit(dir: &Path, outcomes: &[Outcome]) -> String {
+    let failures = outcomes.iter().filter(|outcome| !outcome.success).count();
+    let time: Duration = outcomes.iter().map(|outcome| outcome.duration).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"cargo-single\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+        outcomes.len(),
+        failures,
+        time.as_secs_f64()
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+        escape_xml(&dir.to_string_lossy()),
+        outcomes.len(),
+        failures,
+        time.as_secs_f64()
+    );
+    for outcome in outcomes {
+        let _ = write!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"cargo-single.{}\" time=\"{:.3}\"",
+            escape_xml(&outcome.source.to_string_lossy()),
+            outcome.stage,
+            outcome.duration.as_secs_f64()
+        );
+        if outcome.success {
+            xml.push_str("/>\n");
+        } else {
+            let _ = writeln!(
+                xml,
+                ">\n      <failure message=\"{}\"/>\n    </testcase>",
+                escape_xml(&describe(outcome))
+            );
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+// Parses the value of --report, "<format>:<file>".
+pub fn parse_report(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once(':') {
+        Some((format, path)) if REPORT_FORMATS.contains(&format) && !path.is_empty() => {
+            Ok((format.to_owned(), PathBuf::from(path)))
+        }
+        _ => Err(format!(
+            "invalid report \"{}\", expected {{{}}}:<file>",
+            value,
+            REPORT_FORMATS.join("|")
+        )),
+    }
+}
+
+pub fn write_report(
+    format: &str,
+    path: &Path,
+    dir: &Path,
+    outcomes: &[Outcome],
+) -> Result<(), Box<dyn Error>> {
+    let report = match format {
+        "junit" => to_junit(dir, outcomes),
+        _ => format!("{}\n", cli::versioned(to_json(outcomes))),
+    };
+    fs::write(path, report)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, Args};
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn options_before_or_after_the_directory() {
+        let expected = Args {
+            dir: "scripts/".to_owned(),
+            jobs: 4,
+            command: "check".to_owned(),
+            include: vec!["*.rs".to_owned()],
+            exclude: vec![],
+            reports: vec![("junit".to_owned(), PathBuf::from("out.xml"))],
+        };
+        let options = [
+            "--jobs",
+            "4",
+            "--command",
+            "check",
+            "--include",
+            "*.rs",
+            "--report",
+            "junit:out.xml",
+        ];
+        let mut after = vec!["scripts/"];
+        after.extend(options);
+        let mut before = options.to_vec();
+        before.push("scripts/");
+        let mut between = options[..4].to_vec();
+        between.push("scripts/");
+        between.extend(&options[4..]);
+        assert_eq!(parse(&after), Ok(expected));
+        assert_eq!(parse(&before), parse(&after));
+        assert_eq!(parse(&between), parse(&after));
+        let defaults = parse(&["scripts"]).unwrap();
+        assert_eq!((defaults.jobs, defaults.command.as_str()), (1, "run"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--jobs", "4"]).is_err());
+        assert!(parse(&["scripts", "--jobs"]).is_err());
+        assert!(parse(&["scripts", "--jobs", "0"]).is_err());
+        assert!(parse(&["scripts", "--command", "bench"]).is_err());
+        assert!(parse(&["scripts", "--color", "always"]).is_err());
+        assert!(parse(&["scripts", "more"]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Running a Cargo command for a project and collecting its outcome, for
+//! tools which embed cargo-single and can't have it exit the process.
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+
+use crate::json::{self, Value};
+use crate::project::{CargoOptions, SingleProject};
+
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    pub status: ExitStatus,
+    /// The built binary, if the command built one and it exists. With JSON
+    /// diagnostics, this is the last executable reported by Cargo, which may
+    /// also be a test binary.
+    pub binary: Option<PathBuf>,
+    /// The compiler's messages, collected if [`CargoOptions::json_diagnostics`]
+    /// is set.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// Like `error` or `warning`.
+    pub level: String,
+    pub message: String,
+    /// The message as the compiler would print it.
+    pub rendered: Option<String>,
+    /// File and one-based line of the primary span, if any.
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    fn from_json(message: &Value) -> Option<Diagnostic> {
+        let span = message
+            .get("spans")?
+            .as_array()
+            .iter()
+            .find(|span| matches!(span.get("is_primary"), Some(Value::Bool(true))));
+        let line = span.and_then(|span| match span.get("line_start")? {
+            Value::Number(n) => n.parse().ok(),
+            _ => None,
+        });
+        Some(Diagnostic {
+            level: message.str_at("level")?.to_owned(),
+            message: message.str_at("message")?.to_owned(),
+            rendered: message.str_at("rendered").map(str::to_owned),
+            file: span.and_then(|span| span.str_at("file_name").map(str::to_owned)),
+            line,
+        })
+    }
+}
+
+// With JSON diagnostics, Cargo's standard output has a message on each line,
+// mixed with the output of the program for "run", which is passed on.
+pub(crate) fn run<I, S>(
+    project: &SingleProject,
+    cmd: &str,
+    options: &CargoOptions,
+    args: I,
+) -> Result<RunResult, Box<dyn Error>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = project.cargo_command(cmd, options);
+    if !options.json_diagnostics {
+        if cmd == "run" {
+            command.arg("--");
+        }
+        let status = command.args(args).status()?;
+        let binary = project.binary_path(options);
+        return Ok(RunResult {
+            status,
+            binary: (matches!(cmd, "build" | "run") && binary.exists()).then_some(binary),
+            diagnostics: vec![],
+        });
+    }
+    command.arg("--message-format=json");
+    if cmd == "run" {
+        command.arg("--");
+    }
+    let mut child = command.args(args).stdout(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().expect("piped stdout");
+    let mut binary = None;
+    let mut diagnostics = vec![];
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let message = match json::parse(&line) {
+            Ok(message) if message.str_at("reason").is_some() => message,
+            _ => {
+                println!("{}", line);
+                continue;
+            }
+        };
+        match message.str_at("reason") {
+            Some("compiler-message") => {
+                diagnostics.extend(message.get("message").and_then(Diagnostic::from_json))
+            }
+            Some("compiler-artifact") => {
+                if let Some(executable) = message.str_at("executable") {
+                    binary = Some(PathBuf::from(executable));
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(RunResult {
+        status: child.wait()?,
+        binary,
+        diagnostics,
+    })
+}
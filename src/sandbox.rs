@@ -0,0 +1,182 @@
+// Running the program with restricted access to the file system and the
+// network, for trying programs from untrusted sources. The sandbox is given to
+// Cargo as the runner of the program, so that only the program itself runs in
+// it, not build scripts or procedural macros.
+//
+// On Linux, the program is run with bubblewrap in new namespaces, with the
+// system directories and the project mounted read-only, and nothing else but
+// an empty /tmp. On macOS, it's run with sandbox-exec, denying writes and
+// reading the home directories. More paths are made accessible with
+// `// sandbox.fs = ["./data:ro", "out:rw"]`, relative to the directory of the
+// source file, and the network with `// sandbox.net = true`.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::tool_available;
+
+// Passed to Cargo with --config only with --sandbox, like the sanitizer
+// settings.
+pub const CONFIG: &str = ".cargo/sandbox.toml";
+
+// Mounted read-only on Linux, if they exist, for the dynamic linker, shared
+// libraries and system configuration like time zones and certificates.
+const SYSTEM_DIRS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc"];
+
+pub struct Access {
+    pub path: PathBuf,
+    pub writable: bool,
+}
+
+pub struct Policy {
+    pub paths: Vec<Access>,
+    pub net: bool,
+}
+
+// Parses the value of "sandbox.fs", an array of paths, each optionally
+// followed by ":ro", the default, or ":rw".
+fn parse_fs(value: &str) -> Result<Vec<(String, bool)>, String> {
+    let items = value
+        .trim()
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .ok_or_else(|| format!("sandbox.fs must be an array of paths, not {}", value))?;
+    items
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let item = item
+                .strip_prefix('"')
+                .and_then(|item| item.strip_suffix('"'))
+                .ok_or_else(|| format!("sandbox.fs: {} is not a quoted path", item))?;
+            Ok(match item.rsplit_once(':') {
+                Some((path, "ro")) => (path.to_owned(), false),
+                Some((path, "rw")) => (path.to_owned(), true),
+                _ => (item.to_owned(), false),
+            })
+        })
+        .collect()
+}
+
+pub fn policy(
+    file_src: &Path,
+    fs_setting: Option<&str>,
+    net_setting: Option<&str>,
+) -> Result<Policy, Box<dyn Error>> {
+    let dir = file_src.parent().unwrap_or(Path::new(""));
+    let mut paths = vec![];
+    if let Some(setting) = fs_setting {
+        for (path, writable) in parse_fs(setting)? {
+            let joined = dir.join(&path);
+            let path = fs::canonicalize(&joined)
+                .map_err(|e| format!("sandbox.fs: {}: {}", joined.to_string_lossy(), e))?;
+            paths.push(Access { path, writable });
+        }
+    }
+    let net = match net_setting {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(value) => {
+            return Err(format!("sandbox.net must be true or false, not {}", value).into())
+        }
+    };
+    Ok(Policy { paths, net })
+}
+
+fn path_arg(path: &Path) -> Result<String, String> {
+    path.to_str()
+        .map(str::to_owned)
+        .ok_or_else(|| format!("{} is not valid UTF-8", path.to_string_lossy()))
+}
+
+// The runner command, which is followed by the program and its arguments.
+// `readable` are the directories the program is built into.
+pub fn runner(policy: &Policy, readable: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+    if cfg!(target_os = "linux") {
+        if !tool_available("bwrap") {
+            return Err("--sandbox needs bwrap, from bubblewrap".into());
+        }
+        let mut args: Vec<String> = ["bwrap", "--unshare-all", "--die-with-parent"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        if policy.net {
+            args.push("--share-net".to_owned());
+        }
+        for arg in ["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"] {
+            args.push(arg.to_owned());
+        }
+        // The current directory is created empty, unless it's mounted, so that
+        // relative paths to the mounted directories work.
+        let cwd = path_arg(&env::current_dir()?)?;
+        args.extend(["--dir".to_owned(), cwd.clone()]);
+        for dir in SYSTEM_DIRS {
+            args.extend(["--ro-bind-try".to_owned(), dir.to_string(), dir.to_string()]);
+        }
+        for dir in readable {
+            let dir = path_arg(dir)?;
+            args.extend(["--ro-bind".to_owned(), dir.clone(), dir]);
+        }
+        for access in &policy.paths {
+            let path = path_arg(&access.path)?;
+            let bind = if access.writable {
+                "--bind"
+            } else {
+                "--ro-bind"
+            };
+            args.extend([bind.to_owned(), path.clone(), path]);
+        }
+        args.extend(["--chdir".to_owned(), cwd]);
+        Ok(args)
+    } else if cfg!(target_os = "macos") {
+        let subpaths = |paths: &mut dyn Iterator<Item = &Path>| -> Result<String, String> {
+            paths
+                .map(|path| Ok(format!(" (subpath \"{}\")", path_arg(path)?)))
+                .collect()
+        };
+        let mut profile = "(version 1) (allow default)".to_owned();
+        if !policy.net {
+            profile.push_str(" (deny network*) (allow network* (local unix))");
+        }
+        let mut readable = readable
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(policy.paths.iter().map(|access| access.path.as_path()));
+        profile.push_str(&format!(
+            " (deny file-read* (subpath \"/Users\")) (allow file-read*{})",
+            subpaths(&mut readable)?
+        ));
+        let mut writable = policy
+            .paths
+            .iter()
+            .filter(|access| access.writable)
+            .map(|access| access.path.as_path());
+        profile.push_str(&format!(
+            " (deny file-write*) (allow file-write* (subpath \"/dev\"){})",
+            subpaths(&mut writable)?
+        ));
+        Ok(vec!["sandbox-exec".to_owned(), "-p".to_owned(), profile])
+    } else {
+        Err("--sandbox is only supported on Linux and macOS".into())
+    }
+}
+
+pub fn write_config(project_dir: &Path, runner: &[String]) -> Result<(), Box<dyn Error>> {
+    // The arguments are written as TOML literal strings, which need no
+    // escaping of the quotes in the macOS profile.
+    let mut quoted = vec![];
+    for arg in runner {
+        if arg.contains(['\'', '\n']) {
+            return Err(format!("can't pass {} to the sandbox", arg).into());
+        }
+        quoted.push(format!("'{}'", arg));
+    }
+    let config = format!("[target.'cfg(all())']\nrunner = [{}]\n", quoted.join(", "));
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+pub const SANITIZERS: &[&str] = &["address", "thread", "leak"];
+
+// Passed to Cargo with --config only when sanitizing, so that the settings
+// don't affect other builds of the program.
+pub const CONFIG: &str = ".cargo/sanitize.toml";
+
+// Sanitizers need an explicit target, so that build scripts and procedural
+// macros aren't instrumented; by default, it's the host.
+pub fn host_target(toolchain: Option<&str>) -> Option<String> {
+    let mut rustc = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        rustc.arg(toolchain);
+    }
+    let output = rustc.arg("-vV").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_owned)
+}
+
+pub fn write_config(
+    project_dir: &Path,
+    sanitizer: &str,
+    target: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = format!(
+        "[target.{}]\nrustflags = [\"-Zsanitizer={}\"]\n",
+        target, sanitizer
+    );
+    // The thread sanitizer requires the standard library to be instrumented too.
+    if sanitizer == "thread" {
+        config.push_str("\n[unstable]\nbuild-std = [\"std\"]\n");
+    }
+    let config_path = project_dir.join(CONFIG);
+    fs::create_dir_all(config_path.parent().expect("config dir"))?;
+    fs::write(config_path, config)?;
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json::{object, Value};
+use crate::metadata::{Metadata, Package};
+
+pub const FORMATS: &[&str] = &["cyclonedx", "spdx"];
+
+const TOOL_NAME: &str = env!("CARGO_PKG_NAME");
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Current time in the ISO 8601 format, e.g. "2024-05-01T12:00:00Z".
+pub fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn purl(package: &Package) -> String {
+    format!("pkg:cargo/{}@{}", package.name, package.version)
+}
+
+// Old-style license fields use "/" instead of "OR".
+pub fn license_expression(package: &Package) -> Option<String> {
+    package.license.as_ref().map(|license| {
+        license
+            .split('/')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    })
+}
+
+fn cyclonedx_component(package: &Package, kind: &str) -> Value {
+    let mut component = vec![
+        ("type", kind.into()),
+        ("bom-ref", package.id.as_str().into()),
+        ("name", package.name.as_str().into()),
+        ("version", package.version.as_str().into()),
+        ("purl", purl(package).into()),
+    ];
+    if let Some(license) = license_expression(package) {
+        component.push((
+            "licenses",
+            Value::Array(vec![object([("expression", license.into())])]),
+        ));
+    }
+    object(component)
+}
+
+pub fn cyclonedx(metadata: &Metadata) -> Value {
+    let root = metadata.root_package();
+    object([
+        ("bomFormat", "CycloneDX".into()),
+        ("specVersion", "1.5".into()),
+        ("version", 1.into()),
+        (
+            "metadata",
+            object([
+                ("timestamp", timestamp().into()),
+                (
+                    "tools",
+                    Value::Array(vec![object([
+                        ("name", TOOL_NAME.into()),
+                        ("version", TOOL_VERSION.into()),
+                    ])]),
+                ),
+                ("component", cyclonedx_component(root, "application")),
+            ]),
+        ),
+        (
+            "components",
+            Value::Array(
+                metadata
+                    .packages
+                    .iter()
+                    .filter(|package| package.id != metadata.root)
+                    .map(|package| cyclonedx_component(package, "library"))
+                    .collect(),
+            ),
+        ),
+        (
+            "dependencies",
+            Value::Array(
+                metadata
+                    .packages
+                    .iter()
+                    .map(|package| {
+                        object([
+                            ("ref", package.id.as_str().into()),
+                            (
+                                "dependsOn",
+                                Value::Array(
+                                    package.deps.iter().map(|dep| dep.as_str().into()).collect(),
+                                ),
+                            ),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn spdx_id(package: &Package) -> String {
+    let id: String = format!("{}-{}", package.name, package.version)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-Package-{}", id)
+}
+
+fn relationship(from: String, kind: &str, to: String) -> Value {
+    object([
+        ("spdxElementId", from.into()),
+        ("relationshipType", kind.into()),
+        ("relatedSpdxElement", to.into()),
+    ])
+}
+
+pub fn spdx(metadata: &Metadata) -> Value {
+    let root = metadata.root_package();
+    let created = timestamp();
+    let packages = metadata
+        .packages
+        .iter()
+        .map(|package| {
+            object([
+                ("name", package.name.as_str().into()),
+                ("SPDXID", spdx_id(package).into()),
+                ("versionInfo", package.version.as_str().into()),
+                ("downloadLocation", "NOASSERTION".into()),
+                ("licenseConcluded", "NOASSERTION".into()),
+                (
+                    "licenseDeclared",
+                    license_expression(package)
+                        .unwrap_or_else(|| "NOASSERTION".to_owned())
+                        .into(),
+                ),
+                ("copyrightText", "NOASSERTION".into()),
+                (
+                    "externalRefs",
+                    Value::Array(vec![object([
+                        ("referenceCategory", "PACKAGE-MANAGER".into()),
+                        ("referenceType", "purl".into()),
+                        ("referenceLocator", purl(package).into()),
+                    ])]),
+                ),
+            ])
+        })
+        .collect();
+    let mut relationships = vec![relationship(
+        "SPDXRef-DOCUMENT".to_owned(),
+        "DESCRIBES",
+        spdx_id(root),
+    )];
+    for package in &metadata.packages {
+        for dep in &package.deps {
+            if let Some(dep) = metadata.packages.iter().find(|p| &p.id == dep) {
+                relationships.push(relationship(spdx_id(package), "DEPENDS_ON", spdx_id(dep)));
+            }
+        }
+    }
+    object([
+        ("spdxVersion", "SPDX-2.3".into()),
+        ("dataLicense", "CC0-1.0".into()),
+        ("SPDXID", "SPDXRef-DOCUMENT".into()),
+        ("name", format!("{}-{}", root.name, root.version).into()),
+        (
+            "documentNamespace",
+            format!(
+                "https://spdx.org/spdxdocs/{}-{}-{}",
+                root.name,
+                root.version,
+                created.replace(':', "")
+            )
+            .into(),
+        ),
+        (
+            "creationInfo",
+            object([
+                ("created", created.into()),
+                (
+                    "creators",
+                    Value::Array(vec![format!("Tool: {}-{}", TOOL_NAME, TOOL_VERSION).into()]),
+                ),
+            ]),
+        ),
+        ("packages", Value::Array(packages)),
+        ("relationships", Value::Array(relationships)),
+    ])
+}
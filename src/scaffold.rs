@@ -0,0 +1,173 @@
+// Generating the source file of a new program.
+
+use crate::header::{parse_header, Kind};
+
+// Lets the program be run directly on Unix-like systems.
+const SHEBANG: &str = "#!/usr/bin/env -S cargo single run";
+
+pub const EDITIONS: &[&str] = &["2015", "2018", "2021", "2024"];
+
+// Appended to a crate name to turn off its default features.
+const NO_DEFAULT_SUFFIX: &str = "-nodefault";
+
+// A crate name and the features to enable.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DepSpec {
+    pub name: String,
+    pub features: Vec<String>,
+    pub default_features: bool,
+}
+
+// Parses a comma-separated list of dependencies, each of them a crate name
+// optionally followed by features, separated by "+". A name ending with
+// "-nodefault", like "tokio-nodefault+rt", turns off the default features.
+pub fn parse_deps(spec: &str) -> Result<Vec<DepSpec>, String> {
+    let mut deps = vec![];
+    for dep in spec.split(',').map(str::trim).filter(|dep| !dep.is_empty()) {
+        let mut parts = dep.split('+').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let (name, default_features) = match name.strip_suffix(NO_DEFAULT_SUFFIX) {
+            Some(name) => (name, false),
+            None => (name, true),
+        };
+        let valid = |s: &str| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        let features: Vec<_> = parts.map(str::to_owned).collect();
+        if !valid(name) || !features.iter().all(|feature| valid(feature)) {
+            return Err(format!("invalid dependency \"{}\"", dep));
+        }
+        deps.push(DepSpec {
+            name: name.to_owned(),
+            features,
+            default_features,
+        });
+    }
+    Ok(deps)
+}
+
+// The dependency comment for the dependency, with the version requirement.
+pub fn dep_line(dep: &DepSpec, version: &str) -> String {
+    if dep.features.is_empty() && dep.default_features {
+        return format!("// {} = \"{}\"\n", dep.name, version);
+    }
+    let mut table = format!("version = \"{}\"", version);
+    if !dep.default_features {
+        table.push_str(", default-features = false");
+    }
+    if !dep.features.is_empty() {
+        let features: Vec<_> = dep.features.iter().map(|f| format!("\"{}\"", f)).collect();
+        table.push_str(&format!(", features = [{}]", features.join(", ")));
+    }
+    format!("// {} = {{ {} }}\n", dep.name, table)
+}
+
+// Dependency comments for the dependencies, each with its version requirement.
+fn header(deps: &[(DepSpec, String)], edition: Option<&str>) -> String {
+    let mut header = String::new();
+    if let Some(edition) = edition {
+        header.push_str(&format!("// edition = \"{}\"\n", edition));
+    }
+    for (dep, version) in deps {
+        header.push_str(&dep_line(dep, version));
+    }
+    header
+}
+
+// Returns the source for the dependencies. If anyhow is among them, main()
+// returns its Result.
+pub fn script(deps: &[(DepSpec, String)], edition: Option<&str>) -> String {
+    let mut script = format!("{}\n{}\n", SHEBANG, header(deps, edition));
+    if deps.iter().any(|(dep, _)| dep.name == "anyhow") {
+        script.push_str("fn main() -> anyhow::Result<()> {\n    Ok(())\n}\n");
+    } else {
+        script.push_str("fn main() {\n}\n");
+    }
+    script
+}
+
+// Returns the source from a template, with "{{<name>}}" placeholders replaced
+// by the values of `vars`. The dependencies are added to the template's
+// dependency comments like with add_deps().
+pub fn from_template(
+    template: &str,
+    vars: &[(&str, String)],
+    deps: &[(DepSpec, String)],
+    edition: Option<&str>,
+) -> String {
+    let mut script = template.to_owned();
+    for (name, value) in vars {
+        script = script.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    add_deps(&script, deps, edition)
+}
+
+// Returns the source with the dependencies added to the end of the top-level
+// dependency section of its dependency comments, before any table like
+// [dev-dependencies].
+pub fn add_deps(script: &str, deps: &[(DepSpec, String)], edition: Option<&str>) -> String {
+    let extra = header(deps, edition);
+    if extra.is_empty() {
+        return script.to_owned();
+    }
+    let mut lines: Vec<_> = script.split_inclusive('\n').map(str::to_owned).collect();
+    let has_shebang = lines
+        .first()
+        .is_some_and(|line| line.starts_with("#!") && !line.starts_with("#!["));
+    let entries = parse_header(script).entries;
+    let first_table = entries.iter().find(|entry| entry.kind == Kind::Table);
+    if let Some(table) = first_table {
+        lines.insert(table.line, extra);
+        return lines.concat();
+    }
+    let at = match entries.last() {
+        Some(entry) => entry.line + 1,
+        None if has_shebang => 1,
+        None => 0,
+    };
+    // Dependency comments must be followed by a blank line.
+    if !lines.get(at).is_some_and(|line| line.trim().is_empty()) {
+        lines.insert(at, "\n".to_owned());
+    }
+    lines.insert(at, extra);
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_deps, DepSpec};
+
+    fn anyhow() -> Vec<(DepSpec, String)> {
+        let dep = DepSpec {
+            name: "anyhow".to_owned(),
+            features: vec![],
+            default_features: true,
+        };
+        vec![(dep, "1".to_owned())]
+    }
+
+    #[test]
+    fn deps_go_before_the_first_table() {
+        let script = "#!/usr/bin/env -S cargo single run\n// rand = \"0.8\"\n\
+                      // [dev-dependencies]\n// tempfile = \"3\"\n\nfn main() {}\n";
+        assert_eq!(
+            add_deps(script, &anyhow(), Some("2021")),
+            "#!/usr/bin/env -S cargo single run\n// rand = \"0.8\"\n// edition = \"2021\"\n\
+             // anyhow = \"1\"\n// [dev-dependencies]\n// tempfile = \"3\"\n\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn deps_go_after_the_header() {
+        assert_eq!(
+            add_deps("// rand = \"0.8\"\nfn main() {}\n", &anyhow(), None),
+            "// rand = \"0.8\"\n// anyhow = \"1\"\n\nfn main() {}\n"
+        );
+        assert_eq!(
+            add_deps("#!/bin/prog\nfn main() {}\n", &anyhow(), None),
+            "#!/bin/prog\n// anyhow = \"1\"\n\nfn main() {}\n"
+        );
+    }
+}
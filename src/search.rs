@@ -0,0 +1,75 @@
+// Searching crates.io, with the results formatted as dependency comments.
+
+use std::error::Error;
+
+use crate::json::{self, Value};
+use crate::net;
+use crate::version::Version;
+
+const API_URL: &str = "https://crates.io/api/v1";
+const RESULTS: usize = 10;
+
+// The requirement for a version, as it would be written by hand: major and
+// minor components only.
+pub fn requirement(version: &str) -> String {
+    match Version::parse(version) {
+        Some(v) if !v.is_prerelease() => format!("{}.{}", v.major, v.minor),
+        _ => version.to_owned(),
+    }
+}
+
+pub struct Found {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+impl Found {
+    // The dependency line, with the description in a trailing comment.
+    pub fn line(&self) -> String {
+        let mut line = format!("// {} = \"{}\"", self.name, requirement(&self.version));
+        if let Some(description) = self.description.as_ref() {
+            line.push_str(&format!(" # {}", description));
+        }
+        line
+    }
+
+    pub fn to_json(&self) -> Value {
+        json::object([
+            ("name", self.name.as_str().into()),
+            ("version", self.version.as_str().into()),
+            ("requirement", requirement(&self.version).into()),
+            ("description", self.description.as_deref().into()),
+        ])
+    }
+}
+
+pub fn search(query: &str) -> Result<Vec<Found>, Box<dyn Error>> {
+    let response = json::parse(&net::get(&format!(
+        "{}/crates?q={}&per_page={}",
+        API_URL,
+        net::encode(query),
+        RESULTS
+    ))?)?;
+    let mut found = vec![];
+    for krate in response
+        .get("crates")
+        .map(Value::as_array)
+        .unwrap_or_default()
+    {
+        let name = krate.str_at("name").ok_or("crate name missing")?;
+        let version = krate
+            .str_at("max_stable_version")
+            .or_else(|| krate.str_at("max_version"))
+            .unwrap_or("*");
+        let description = krate
+            .str_at("description")
+            .map(|description| description.split_whitespace().collect::<Vec<_>>().join(" "));
+        found.push(Found {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            description,
+        });
+    }
+    Ok(found)
+}
@@ -0,0 +1,185 @@
+// Updating cargo-single itself with "self update". The latest release is
+// taken from crates.io, and its notes from the GitHub releases. A binary
+// installed by Cargo is updated with "cargo install", to keep Cargo's record
+// of it; any other is replaced with the release archive for the platform, if
+// there is one, and otherwise also with "cargo install".
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use crate::index;
+use crate::json::{self, Value};
+use crate::net;
+use crate::version::Version;
+
+const CRATE: &str = "cargo-single";
+const RELEASES_URL: &str = "https://api.github.com/repos/inejge/cargo-single/releases";
+const RELEASES_PAGE: &str = "https://github.com/inejge/cargo-single/releases";
+
+pub fn current() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("package version")
+}
+
+// The newest release, if it's newer than the running version.
+pub fn newer_release() -> Result<Option<Version>, Box<dyn Error>> {
+    let latest = index::latest_release(CRATE)?;
+    Ok((latest > current()).then_some(latest))
+}
+
+fn releases() -> Result<Vec<Value>, Box<dyn Error>> {
+    Ok(json::parse(&net::get(RELEASES_URL)?)?.as_array().to_vec())
+}
+
+fn release_version(release: &Value) -> Option<Version> {
+    Version::parse(release.str_at("tag_name")?.trim_start_matches('v'))
+}
+
+// The notes of the releases after the running version, up to `latest`,
+// newest first.
+pub fn changelog(latest: &Version) -> String {
+    let releases = match releases() {
+        Ok(releases) => releases,
+        Err(_) => return format!("The release notes are at {}\n", RELEASES_PAGE),
+    };
+    let current = current();
+    let mut notes: Vec<_> = releases
+        .iter()
+        .filter_map(|release| Some((release_version(release)?, release)))
+        .filter(|(version, _)| *version > current && version <= latest)
+        .collect();
+    notes.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let mut text = String::new();
+    for (version, release) in notes {
+        text.push_str(&format!("## {}\n\n", version));
+        // GitHub keeps the line endings of the browser which wrote the notes.
+        let body = release.str_at("body").unwrap_or_default();
+        text.push_str(body.replace("\r\n", "\n").trim());
+        text.push_str("\n\n");
+    }
+    if text.is_empty() {
+        text = format!("The release notes are at {}\n", RELEASES_PAGE);
+    }
+    text
+}
+
+// Whether the running binary is in Cargo's bin directory.
+fn installed_by_cargo(exe: &Path) -> bool {
+    let cargo_home = env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .map(|home| PathBuf::from(home).join(".cargo"))
+    });
+    match (
+        cargo_home.and_then(|home| fs::canonicalize(home.join("bin")).ok()),
+        exe.parent(),
+    ) {
+        (Some(bin), Some(dir)) => fs::canonicalize(dir).is_ok_and(|dir| dir == bin),
+        _ => false,
+    }
+}
+
+fn cargo_install(version: &Version) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("cargo")
+        .args(["install", "--locked", CRATE, "--version"])
+        .arg(version.to_string())
+        .status()
+        .map_err(|e| format!("error executing \"cargo install\": {}", e))?;
+    if !status.success() {
+        return Err("\"cargo install\" failed".into());
+    }
+    Ok(())
+}
+
+// The archive of the release for the platform, by its name, like
+// "cargo-single-x86_64-unknown-linux-gnu.tar.gz".
+fn archive_url(version: &Version) -> Option<String> {
+    let os_names: &[&str] = match env::consts::OS {
+        "macos" => &["apple", "darwin", "macos"],
+        os => &[os],
+    };
+    releases()
+        .ok()?
+        .iter()
+        .find(|release| release_version(release).as_ref() == Some(version))?
+        .get("assets")?
+        .as_array()
+        .iter()
+        .filter(|asset| {
+            asset.str_at("name").is_some_and(|name| {
+                name.contains(env::consts::ARCH)
+                    && os_names.iter().any(|os| name.contains(os))
+                    && (name.ends_with(".tar.gz") || name.ends_with(".zip"))
+            })
+        })
+        .find_map(|asset| asset.str_at("browser_download_url").map(str::to_owned))
+}
+
+// Downloads and unpacks the archive, and replaces the running binary with the
+// one from it.
+fn replace_binary(url: &str, exe: &Path) -> Result<(), Box<dyn Error>> {
+    let dir = env::temp_dir().join(format!("cargo-single-update-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let archive = dir.join(url.rsplit('/').next().unwrap_or("archive"));
+        net::download(url, &archive)?;
+        let status = Command::new("tar")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&dir)
+            .status()
+            .map_err(|e| format!("error executing tar: {}", e))?;
+        if !status.success() {
+            return Err("can't unpack the release archive".into());
+        }
+        let name = format!("{}{}", CRATE, env::consts::EXE_SUFFIX);
+        let binary = find_file(&dir, &name).ok_or("no binary in the release archive")?;
+        // The new binary is copied next to the old one first, so that the
+        // replacement is a rename. Windows can't replace a running binary, but
+        // can rename it.
+        let staged = exe.with_file_name(format!(".{}.new", name));
+        fs::copy(&binary, &staged)?;
+        if cfg!(windows) {
+            let old = exe.with_extension("old");
+            let _ = fs::remove_file(&old);
+            fs::rename(exe, old)?;
+        }
+        fs::rename(&staged, exe)?;
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name().is_some_and(|file| file == name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+pub fn update(version: &Version) -> Result<(), Box<dyn Error>> {
+    let exe = env::current_exe()?;
+    if installed_by_cargo(&exe) {
+        return cargo_install(version);
+    }
+    match archive_url(version) {
+        Some(url) => replace_binary(&url, &exe).or_else(|e| {
+            eprintln!(
+                "cargo-single: warning: {}; installing with \"cargo install\"",
+                e
+            );
+            cargo_install(version)
+        }),
+        None => cargo_install(version),
+    }
+}
@@ -0,0 +1,307 @@
+// Settings which can be given in several places, taking precedence in this
+// order: the command line, the environment, the entry of the program in
+// cargo-single.toml when it's given as "@<name>", the header of the source
+// file, and the [defaults] table of the configuration file.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{self, Config, ConfigValue};
+use crate::header::parse_header;
+use crate::json::{self, Value};
+
+pub struct Setting {
+    pub name: &'static str,
+    // The options which set it, for "config show".
+    pub options: Option<&'static str>,
+    pub env: &'static str,
+    pub in_header: bool,
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        name: "toolchain",
+        options: Some("+toolchain"),
+        env: "CARGO_SINGLE_TOOLCHAIN",
+        in_header: true,
+    },
+    Setting {
+        name: "target",
+        options: Some("--target"),
+        env: "CARGO_SINGLE_TARGET",
+        in_header: true,
+    },
+    Setting {
+        name: "quiet",
+        options: Some("--quiet, --no-quiet"),
+        env: "CARGO_SINGLE_QUIET",
+        in_header: true,
+    },
+    Setting {
+        name: "cache-dir",
+        options: None,
+        env: "CARGO_SINGLE_CACHE_DIR",
+        in_header: false,
+    },
+    Setting {
+        name: "editor",
+        options: None,
+        env: "CARGO_SINGLE_EDITOR",
+        in_header: false,
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment,
+    Collection,
+    Header,
+    Config,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::CommandLine => "command line",
+            Source::Environment => "environment",
+            Source::Collection => "cargo-single.toml",
+            Source::Header => "header",
+            Source::Config => "configuration file",
+        }
+    }
+}
+
+// The settings from all the sources. Those from the command line are given by
+// the callers, since each setting has its own options.
+pub struct Layers {
+    command_line: Vec<(String, String)>,
+    collection: Vec<(String, String)>,
+    header: Vec<(String, String)>,
+    config: Config,
+}
+
+impl Layers {
+    pub fn load(file_src: Option<&Path>) -> Result<Layers, String> {
+        let header = match file_src {
+            Some(file_src) => {
+                let src = fs::read_to_string(file_src)
+                    .map_err(|e| format!("{}: {}", file_src.display(), e))?;
+                parse_header(&src)
+                    .settings()
+                    .map(|entry| {
+                        (
+                            entry.key.to_owned(),
+                            entry.value.trim_matches('"').to_owned(),
+                        )
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+        Ok(Layers {
+            command_line: vec![],
+            collection: vec![],
+            header,
+            config: config::load()?,
+        })
+    }
+
+    // The settings given with options, like "toolchain" for +toolchain.
+    pub fn set_command_line(&mut self, settings: Vec<(String, String)>) {
+        self.command_line = settings;
+    }
+
+    // The settings of the program in cargo-single.toml.
+    pub fn set_collection(&mut self, settings: Vec<(String, String)>) {
+        self.collection = settings;
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<(String, Source)> {
+        self.resolve_with(name, |var| env::var(var).ok())
+    }
+
+    // Resolves a setting with the environment variables given by `env_var`.
+    fn resolve_with(
+        &self,
+        name: &str,
+        env_var: impl Fn(&str) -> Option<String>,
+    ) -> Option<(String, Source)> {
+        let setting = SETTINGS.iter().find(|setting| setting.name == name)?;
+        if let Some((_, value)) = self.command_line.iter().find(|(key, _)| key == name) {
+            return Some((value.clone(), Source::CommandLine));
+        }
+        if let Some(value) = env_var(setting.env).filter(|value| !value.is_empty()) {
+            return Some((value, Source::Environment));
+        }
+        if let Some((_, value)) = self.collection.iter().find(|(key, _)| key == name) {
+            return Some((value.clone(), Source::Collection));
+        }
+        if setting.in_header {
+            if let Some((_, value)) = self.header.iter().find(|(key, _)| key == name) {
+                return Some((value.clone(), Source::Header));
+            }
+        }
+        let value = match self.config.get(&format!("defaults.{}", name))? {
+            ConfigValue::String(value) => value.clone(),
+            ConfigValue::Bool(value) => value.to_string(),
+            ConfigValue::Integer(value) => value.to_string(),
+            ConfigValue::List(_) => return None,
+        };
+        Some((value, Source::Config))
+    }
+
+    // The effective value of "quiet", which must be a boolean, or 1 or 0 as
+    // usual for environment variables.
+    pub fn quiet(&self) -> Result<Option<bool>, String> {
+        match self.resolve("quiet") {
+            Some((value, source)) => match value.as_str() {
+                "true" | "1" => Ok(Some(true)),
+                "false" | "0" => Ok(Some(false)),
+                _ => Err(format!(
+                    "invalid quiet setting \"{}\" in the {}, expected true or false, or 1 or 0",
+                    value,
+                    source.name()
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+// For "config show", the value of each setting and where it comes from, or
+// the default.
+pub fn show(layers: &Layers) -> Vec<(&'static str, Option<String>, &'static str)> {
+    SETTINGS
+        .iter()
+        .map(|setting| match layers.resolve(setting.name) {
+            Some((value, source)) => (setting.name, Some(value), source.name()),
+            None if setting.name == "cache-dir" => (
+                setting.name,
+                config::default_cache_dir().map(|dir| dir.display().to_string()),
+                "default",
+            ),
+            None => (setting.name, None, "default"),
+        })
+        .collect()
+}
+
+pub fn to_json(shown: &[(&'static str, Option<String>, &'static str)]) -> Value {
+    let settings = shown
+        .iter()
+        .map(|(name, value, source)| {
+            json::object([
+                ("name", (*name).into()),
+                ("value", value.as_deref().into()),
+                ("source", (*source).into()),
+            ])
+        })
+        .collect();
+    json::object([("settings", Value::Array(settings))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layers, Source};
+    use crate::config::Config;
+
+    fn with_config(config: &str) -> Layers {
+        Layers {
+            command_line: vec![],
+            collection: vec![],
+            header: vec![],
+            config: Config::parse(config).unwrap(),
+        }
+    }
+
+    fn setting(name: &str, value: &str) -> Vec<(String, String)> {
+        vec![(name.to_owned(), value.to_owned())]
+    }
+
+    fn env_target(var: &str) -> Option<String> {
+        (var == "CARGO_SINGLE_TARGET").then(|| "env-target".to_owned())
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn precedence() {
+        let mut layers = with_config("[defaults]\ntarget = \"config-target\"\n");
+        assert_eq!(
+            layers.resolve_with("target", no_env),
+            Some(("config-target".to_owned(), Source::Config))
+        );
+        layers.header = setting("target", "header-target");
+        assert_eq!(
+            layers.resolve_with("target", no_env),
+            Some(("header-target".to_owned(), Source::Header))
+        );
+        layers.set_collection(setting("target", "collection-target"));
+        assert_eq!(
+            layers.resolve_with("target", no_env),
+            Some(("collection-target".to_owned(), Source::Collection))
+        );
+        assert_eq!(
+            layers.resolve_with("target", env_target),
+            Some(("env-target".to_owned(), Source::Environment))
+        );
+        layers.set_command_line(setting("target", "cli-target"));
+        assert_eq!(
+            layers.resolve_with("target", env_target),
+            Some(("cli-target".to_owned(), Source::CommandLine))
+        );
+    }
+
+    #[test]
+    fn unset_layers() {
+        let mut layers = with_config("");
+        assert_eq!(layers.resolve_with("toolchain", no_env), None);
+        assert_eq!(layers.resolve_with("unknown", no_env), None);
+        // An empty environment variable is taken as unset.
+        let empty = |_: &str| Some(String::new());
+        layers.header = setting("toolchain", "nightly");
+        assert_eq!(
+            layers.resolve_with("toolchain", empty),
+            Some(("nightly".to_owned(), Source::Header))
+        );
+    }
+
+    #[test]
+    fn header_only_settings() {
+        let mut layers = with_config("[defaults]\ncache-dir = \"/cache\"\n");
+        layers.header = setting("cache-dir", "/header");
+        assert_eq!(
+            layers.resolve_with("cache-dir", no_env),
+            Some(("/cache".to_owned(), Source::Config))
+        );
+    }
+
+    #[test]
+    fn editor() {
+        let layers = with_config("[defaults]\neditor = \"code --wait\"\n");
+        assert_eq!(
+            layers.resolve_with("editor", no_env),
+            Some(("code --wait".to_owned(), Source::Config))
+        );
+        let env_editor = |var: &str| (var == "CARGO_SINGLE_EDITOR").then(|| "nano".to_owned());
+        assert_eq!(
+            layers.resolve_with("editor", env_editor),
+            Some(("nano".to_owned(), Source::Environment))
+        );
+    }
+
+    #[test]
+    fn config_values() {
+        let layers = with_config("[defaults]\nquiet = true\ntarget = [\"a\"]\n");
+        assert_eq!(layers.quiet(), Ok(Some(true)));
+        assert_eq!(layers.resolve_with("target", no_env), None);
+        let mut layers = with_config("");
+        layers.header = setting("quiet", "maybe");
+        assert!(layers.quiet().is_err());
+        layers.set_command_line(setting("quiet", "false"));
+        assert_eq!(layers.quiet(), Ok(Some(false)));
+    }
+}
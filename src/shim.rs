@@ -0,0 +1,108 @@
+// Launchers installed into Cargo's bin directory, for "shim", which run a
+// program under a name of its own. On Unix, the launcher is a shell script
+// which builds the program in release mode when the source file is newer than
+// the binary, and then runs the binary; on Windows, it's a batch file which
+// runs the program with cargo-single, leaving the freshness check to Cargo.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Marks the launchers, so that only they are replaced.
+const MARKER: &str = "installed by cargo-single shim";
+
+pub struct Shim<'a> {
+    pub name: &'a str,
+    pub exe: &'a Path,
+    pub file_src: &'a Path,
+    pub binary: &'a Path,
+    pub toolchain: Option<&'a str>,
+    // The --target and --target-dir arguments the program is built with.
+    pub build_args: &'a [String],
+}
+
+fn bin_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+                .map(|home| PathBuf::from(home).join(".cargo"))
+        })
+        .ok_or("can't find the Cargo home directory")?;
+    Ok(cargo_home.join("bin"))
+}
+
+fn sh_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+fn script(shim: &Shim) -> String {
+    let toolchain = shim
+        .toolchain
+        .map(|toolchain| format!(" {}", toolchain))
+        .unwrap_or_default();
+    if cfg!(windows) {
+        let build_args: String = shim
+            .build_args
+            .iter()
+            .map(|arg| format!(" \"{}\"", arg))
+            .collect();
+        return format!(
+            "@echo off\r\nrem {} from {}\r\n\"{}\" single run{}{} --release \"{}\" %*\r\n",
+            MARKER,
+            shim.file_src.display(),
+            shim.exe.display(),
+            toolchain,
+            build_args,
+            shim.file_src.display()
+        );
+    }
+    let build_args: String = shim
+        .build_args
+        .iter()
+        .map(|arg| format!(" {}", sh_quote(Path::new(arg))))
+        .collect();
+    format!(
+        "#!/bin/sh\n\
+         # {marker} from {src_display}\n\
+         src={src}\n\
+         bin={bin}\n\
+         if [ ! -x \"$bin\" ] || [ \"$src\" -nt \"$bin\" ]; then\n\
+         \x20   {exe} single build{toolchain}{build_args} --release \"$src\" >&2 || exit $?\n\
+         fi\n\
+         exec \"$bin\" \"$@\"\n",
+        marker = MARKER,
+        src_display = shim.file_src.display(),
+        src = sh_quote(shim.file_src),
+        bin = sh_quote(shim.binary),
+        exe = sh_quote(shim.exe),
+        toolchain = toolchain,
+        build_args = build_args,
+    )
+}
+
+// Writes the launcher, replacing an earlier one but no other file, and returns
+// its path.
+pub fn install(shim: &Shim) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = bin_dir()?;
+    let path = dir.join(if cfg!(windows) {
+        format!("{}.cmd", shim.name)
+    } else {
+        shim.name.to_owned()
+    });
+    if path.exists() {
+        let ours = fs::read_to_string(&path).is_ok_and(|text| text.contains(MARKER));
+        if !ours {
+            return Err(format!("{} exists, and isn't a launcher", path.display()).into());
+        }
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, script(shim))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,129 @@
+// Side files of a program: modules declared with `// mod = "helpers.rs"`
+// settings, and data files matched by `// include = "templates/*.html"`
+// settings, both of which may be repeated. They are linked into the src
+// directory of the project, at the same path relative to it as to the source
+// file, so that `mod helpers;` and `include_str!("templates/index.html")` find
+// them. A build script given with `// build = "build.rs"` is linked as the
+// build script of the project, unless one is generated for the Windows
+// resources.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::glob;
+use crate::log::log;
+
+pub fn source_dir(file_src: &Path) -> &Path {
+    match file_src.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn relative_path(key: &str, value: &str) -> Result<PathBuf, String> {
+    let relative = PathBuf::from(value);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!(
+            "{}: {} must be a path below the directory of the source file",
+            key, value
+        ));
+    }
+    Ok(relative)
+}
+
+// The side files, as paths relative to the directory of the source file, from
+// the values of the "mod" and "include" settings. A pattern which matches no
+// files is an error, since the program would likely fail to build.
+pub fn paths(
+    file_src: &Path,
+    modules: &[String],
+    includes: &[String],
+) -> Result<Vec<PathBuf>, String> {
+    let mut paths = vec![];
+    for module in modules {
+        paths.push(relative_path("mod", module)?);
+    }
+    for pattern in includes {
+        relative_path("include", pattern)?;
+        let found = glob::expand(source_dir(file_src), pattern);
+        if found.is_empty() {
+            return Err(format!("include: no files match {}", pattern));
+        }
+        paths.extend(found);
+    }
+    Ok(paths)
+}
+
+// The path of the build script, which may be anywhere relative to the source
+// file.
+pub fn build_script(file_src: &Path, value: &str) -> PathBuf {
+    source_dir(file_src).join(value)
+}
+
+// Links the file to the target, replacing a link broken by an editor which
+// replaced the file. Falls back to copying if the file can't be linked.
+fn link_file(path: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if fs::read(target).is_ok_and(|linked| linked == contents) {
+        return Ok(());
+    }
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    log!(Debug, "linking {} to {}", path.display(), target.display());
+    if target.exists() {
+        fs::remove_file(target)?;
+    }
+    if fs::hard_link(path, target).is_err() {
+        fs::write(target, contents)?;
+    }
+    Ok(())
+}
+
+pub enum BuildScript {
+    None,
+    // The value of the "build" setting.
+    File(String),
+    // The contents of a generated build script.
+    Generated(String),
+}
+
+// Links the side files and the build script into the project. Without a build
+// script, one left from an earlier setting is removed.
+pub fn link(
+    file_src: &Path,
+    project_dir: &Path,
+    paths: &[PathBuf],
+    build: BuildScript,
+) -> Result<(), Box<dyn Error>> {
+    for relative in paths {
+        link_file(
+            &source_dir(file_src).join(relative),
+            &project_dir.join("src").join(relative),
+        )?;
+    }
+    let target = project_dir.join("build.rs");
+    match build {
+        BuildScript::File(build) => link_file(&build_script(file_src, &build), &target)?,
+        // Written only when changed, to not rebuild the build script every time.
+        BuildScript::Generated(script) => {
+            if fs::read_to_string(&target).ok().as_ref() != Some(&script) {
+                log!(Debug, "writing the generated {}", target.display());
+                if target.exists() {
+                    fs::remove_file(&target)?;
+                }
+                fs::write(target, script)?;
+            }
+        }
+        BuildScript::None if target.exists() => {
+            log!(Debug, "removing {}", target.display());
+            fs::remove_file(target)?
+        }
+        BuildScript::None => (),
+    }
+    Ok(())
+}
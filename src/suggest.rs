@@ -0,0 +1,117 @@
+// Suggestions for misspelled subcommands, options, source files and crate
+// names.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::index;
+use crate::search;
+
+// The most crates suggested for a misspelled name.
+const CRATE_SUGGESTIONS: usize = 3;
+
+// Edit distance in characters, counting a transposition of adjacent
+// characters as one edit, since it's a common typo.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// A word is taken for a misspelling of a candidate if about a third of it
+// differs, at most.
+fn is_close(word: &str, candidate: &str) -> bool {
+    distance(word, candidate) <= (word.chars().count() / 3).max(1)
+}
+
+// The candidate nearest to the word, if it's close enough.
+pub fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .filter(|candidate| is_close(word, candidate))
+        .min_by_key(|candidate| distance(word, candidate))
+}
+
+// Source files in the directory of `path` whose names are close to its name,
+// or contain it.
+pub fn similar_files(path: &Path) -> Vec<String> {
+    let (dir, relative) = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => (dir, false),
+        _ => (Path::new("."), true),
+    };
+    let stem = match path.file_stem() {
+        Some(stem) => stem.to_string_lossy().to_lowercase(),
+        None => return vec![],
+    };
+    let mut found: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|ext| ext == "rs"))
+        .filter(|file| {
+            let other = file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_lowercase();
+            // Names too short to be misspelled are only matched when contained.
+            stem.len() > 2 && (is_close(&stem, &other) || other.contains(&stem))
+        })
+        .map(|file| match file.file_name() {
+            Some(name) if relative => name.to_string_lossy().into_owned(),
+            _ => file.display().to_string(),
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+// Crates on crates.io with names close to `name`, which isn't one, nearest
+// first. They are found by searching crates.io for the name, and by looking up
+// in the index the names given by swapping adjacent characters, or "-" and
+// "_", since the search doesn't find transposed letters.
+pub fn similar_crates(name: &str) -> Vec<String> {
+    let mut candidates = BTreeSet::new();
+    for found in search::search(name).unwrap_or_default() {
+        if is_close(name, &found.name) {
+            candidates.insert(found.name);
+        }
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let mut variants = BTreeSet::new();
+    for i in 1..chars.len() {
+        let mut swapped = chars.clone();
+        swapped.swap(i - 1, i);
+        variants.insert(swapped.into_iter().collect::<String>());
+    }
+    variants.insert(name.replace('-', "_"));
+    variants.insert(name.replace('_', "-"));
+    variants.remove(name);
+    for variant in variants {
+        if !candidates.contains(&variant) && index::exists(&variant).unwrap_or(false) {
+            candidates.insert(variant);
+        }
+    }
+    let mut candidates: Vec<_> = candidates.into_iter().collect();
+    candidates.sort_by_key(|candidate| distance(name, candidate));
+    candidates.truncate(CRATE_SUGGESTIONS);
+    candidates
+}
@@ -0,0 +1,65 @@
+// Time spent in the phases of a build, reported with --timings-self, to tell
+// whether slowness comes from cargo-single or from Cargo.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// The header is read in many places, so the time is summed here.
+static HEADER_NANOS: AtomicU64 = AtomicU64::new(0);
+
+// The phase of running Cargo, or cross or a container, and the program.
+pub const CARGO: &str = "cargo command";
+
+pub fn add_header_time(since: Instant) {
+    HEADER_NANOS.fetch_add(since.elapsed().as_nanos() as u64, Ordering::Relaxed);
+}
+
+pub struct Timings {
+    start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new(start: Instant) -> Timings {
+        Timings {
+            start,
+            phases: vec![],
+        }
+    }
+
+    // Adds the time since `since` to the phase.
+    pub fn add(&mut self, phase: &'static str, since: Instant) {
+        let elapsed = since.elapsed();
+        match self.phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => self.phases.push((phase, elapsed)),
+        }
+    }
+
+    // Prints the phases to the standard error, with the time spent by
+    // cargo-single itself, which is everything outside of the Cargo command.
+    pub fn report(&self) {
+        let total = self.start.elapsed();
+        let header = Duration::from_nanos(HEADER_NANOS.load(Ordering::Relaxed));
+        let mut rows = vec![("header parsing", header)];
+        rows.extend(self.phases.iter().copied());
+        let external = self
+            .phases
+            .iter()
+            .find(|(name, _)| *name == CARGO)
+            .map(|(_, time)| *time)
+            .unwrap_or_default();
+        rows.push(("cargo-single", total.saturating_sub(external)));
+        rows.push(("total", total));
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        eprintln!("cargo-single: timings:");
+        for (name, time) in rows {
+            eprintln!(
+                "  {:width$}  {:8.3}s",
+                name,
+                time.as_secs_f64(),
+                width = width
+            );
+        }
+    }
+}
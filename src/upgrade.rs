@@ -0,0 +1,306 @@
+// Upgrading the version requirements in the dependency comments. Only the
+// version strings are replaced, so that the formatting of the lines is kept.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::deps::{header, inline_value, quoted};
+use crate::index::index_versions;
+use crate::version::Version;
+
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+// Where a line of the dependency comments is, as far as dependencies are concerned.
+enum Table {
+    // A dependency table, with one dependency per line.
+    Dependencies,
+    // A table for a single dependency, like [dependencies.foo].
+    Dependency(String),
+    Other,
+}
+
+fn table_kind(header: &str) -> Table {
+    let name = header
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim();
+    for kind in DEPENDENCY_TABLES {
+        if name == *kind || name.ends_with(&format!(".{}", kind)) {
+            return Table::Dependencies;
+        }
+        let prefix = format!("{}.", kind);
+        if let Some(pos) = name.find(&prefix) {
+            if pos == 0 || name[..pos].ends_with('.') {
+                let dep = name[pos + prefix.len()..].trim_matches(['"', '\'']);
+                return Table::Dependency(dep.to_owned());
+            }
+        }
+    }
+    Table::Other
+}
+
+// Whether a caret requirement with the given numeric parts accepts `version`.
+fn caret_matches(parts: &[u64], version: &Version) -> bool {
+    let base = Version {
+        major: parts[0],
+        minor: parts.get(1).copied().unwrap_or(0),
+        patch: parts.get(2).copied().unwrap_or(0),
+        pre: String::new(),
+    };
+    if *version < base {
+        return false;
+    }
+    match parts {
+        [major, ..] if *major > 0 => version.major == *major,
+        [_] => version.major == 0,
+        [_, minor] | [_, minor, _] if *minor > 0 || parts.len() == 2 => {
+            version.major == 0 && version.minor == *minor
+        }
+        _ => *version == base,
+    }
+}
+
+// The upgraded form of a requirement, which keeps its number of components, or
+// None if there's no upgrade. Only caret requirements are upgraded.
+fn upgraded_requirement(req: &str, versions: &[(Version, bool)], latest: bool) -> Option<String> {
+    let (op, numbers) = match req.strip_prefix('^') {
+        Some(numbers) => ("^", numbers),
+        None => ("", req),
+    };
+    let parts = numbers
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let best = versions
+        .iter()
+        .filter(|(v, yanked)| !yanked && !v.is_prerelease())
+        .map(|(v, _)| v)
+        .filter(|v| latest || caret_matches(&parts, v))
+        .max()?;
+    let components = [best.major, best.minor, best.patch];
+    let new_req = format!(
+        "{}{}",
+        op,
+        components[..parts.len()]
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(".")
+    );
+    (new_req != req && caret_matches(&components[..parts.len()], best)).then_some(new_req)
+}
+
+// A version requirement found in the dependency comments.
+pub struct Requirement {
+    pub line: usize,
+    // Byte range of the requirement in the line.
+    pub range: (usize, usize),
+    // Name of the dependency, which differs from the crate name if it's renamed
+    // with the "package" key.
+    pub name: String,
+    // Name of the crate on crates.io.
+    pub package: String,
+}
+
+impl Requirement {
+    pub fn is_renamed(&self) -> bool {
+        self.name != self.package
+    }
+}
+
+pub fn find_requirements(source: &str) -> Vec<Requirement> {
+    let mut requirements: Vec<Requirement> = vec![];
+    let mut table = Table::Dependencies;
+    // Index of the first requirement in the current table, and the crate name
+    // given for a dependency table.
+    let mut table_start = 0;
+    let mut table_package = None;
+    // Settings are only found before the first table header.
+    let mut top_level = true;
+    for (line_no, dep) in header(source) {
+        let mut offset = "// ".len();
+        // A dependency for a platform is found like any other.
+        let dep = match crate::header::platform_dep(dep) {
+            Some((_, platform_dep)) if matches!(table, Table::Dependencies) => {
+                offset += dep.len() - platform_dep.len();
+                platform_dep
+            }
+            _ => dep,
+        };
+        if dep.trim_start().starts_with('[') {
+            table = table_kind(dep);
+            top_level = false;
+            table_start = requirements.len();
+            table_package = None;
+            continue;
+        }
+        let (key, value) = match dep.split_once('=') {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let key = key.trim().trim_matches('"');
+        let value_offset = offset + dep.len() - value.len();
+        let found = match &table {
+            Table::Dependencies
+                if top_level
+                    && (key == "self"
+                        || crate::header::setting_key(dep).is_some()
+                        || crate::header::lints_key(dep).is_some()) =>
+            {
+                None
+            }
+            Table::Dependencies if value.trim_start().starts_with('"') => {
+                quoted(value).map(|range| (range, key.to_owned()))
+            }
+            Table::Dependencies if value.trim_start().starts_with('{') => {
+                inline_value(value, "version").map(|range| {
+                    let package = inline_value(value, "package")
+                        .map(|(start, end)| value[start..end].to_owned())
+                        .unwrap_or_else(|| key.to_owned());
+                    (range, package)
+                })
+            }
+            Table::Dependency(name) if key == "version" => quoted(value).map(|range| {
+                let package = table_package.clone().unwrap_or_else(|| name.clone());
+                (range, package)
+            }),
+            // The "package" key of a dependency table may also follow its version.
+            Table::Dependency(_) if key == "package" => {
+                if let Some((start, end)) = quoted(value) {
+                    let package = value[start..end].to_owned();
+                    for req in &mut requirements[table_start..] {
+                        req.package = package.clone();
+                    }
+                    table_package = Some(package);
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(((start, end), package)) = found {
+            let name = match &table {
+                Table::Dependency(name) => name.clone(),
+                _ => key.to_owned(),
+            };
+            requirements.push(Requirement {
+                line: line_no,
+                range: (value_offset + start, value_offset + end),
+                name,
+                package,
+            });
+        }
+    }
+    requirements
+}
+
+// Upgrades the requirements in the dependency comments to the newest versions
+// which they accept or, with `latest`, to the newest versions. Renamed
+// dependencies are usually there to use another major version of a crate
+// alongside the regular one, so they only get compatible upgrades. Returns the
+// new source, and a description of each upgrade.
+pub fn upgrade(source: &str, latest: bool) -> Result<(String, Vec<String>), Box<dyn Error>> {
+    let mut lines: Vec<String> = source.split_inclusive('\n').map(str::to_owned).collect();
+    let mut changes = vec![];
+    let mut versions = HashMap::new();
+    for req in find_requirements(source) {
+        if !versions.contains_key(&req.package) {
+            let available = index_versions(&req.package)
+                .map_err(|e| format!("can't look up {}: {}", req.package, e))?;
+            versions.insert(req.package.clone(), available);
+        }
+        let line = &mut lines[req.line];
+        let old = line[req.range.0..req.range.1].to_owned();
+        let latest = latest && !req.is_renamed();
+        if let Some(new) = upgraded_requirement(&old, &versions[&req.package], latest) {
+            line.replace_range(req.range.0..req.range.1, &new);
+            let name = if req.is_renamed() {
+                format!("{} ({})", req.name, req.package)
+            } else {
+                req.name.clone()
+            };
+            changes.push(format!("{} {} -> {}", name, old, new));
+        }
+    }
+    Ok((lines.concat(), changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{caret_matches, find_requirements, upgraded_requirement};
+    use crate::version::Version;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn caret_requirements() {
+        let cases: &[(&[u64], &str, bool)] = &[
+            (&[1], "1.0.0", true),
+            (&[1], "1.9.3", true),
+            (&[1], "2.0.0", false),
+            (&[1, 2], "1.2.0", true),
+            (&[1, 2], "1.1.9", false),
+            (&[1, 2, 3], "1.2.2", false),
+            (&[1, 2, 3], "1.5.0", true),
+            (&[0], "0.9.0", true),
+            (&[0], "1.0.0", false),
+            (&[0, 2], "0.2.7", true),
+            (&[0, 2], "0.3.0", false),
+            (&[0, 2, 3], "0.2.2", false),
+            (&[0, 2, 3], "0.2.9", true),
+            (&[0, 0], "0.0.5", true),
+            (&[0, 0], "0.1.0", false),
+            (&[0, 0, 3], "0.0.3", true),
+            (&[0, 0, 3], "0.0.4", false),
+        ];
+        for (parts, v, matches) in cases {
+            assert_eq!(
+                caret_matches(parts, &version(v)),
+                *matches,
+                "{:?} {}",
+                parts,
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn upgrades() {
+        let versions: Vec<_> = [
+            ("1.0.0", false),
+            ("1.4.0", false),
+            ("1.5.0", true),
+            ("2.0.0-alpha.10", false),
+            ("2.1.0", false),
+        ]
+        .into_iter()
+        .map(|(v, yanked)| (version(v), yanked))
+        .collect();
+        assert_eq!(
+            upgraded_requirement("1.0", &versions, false),
+            Some("1.4".to_owned())
+        );
+        assert_eq!(
+            upgraded_requirement("^1", &versions, true),
+            Some("^2".to_owned())
+        );
+        assert_eq!(upgraded_requirement("2.1.0", &versions, false), None);
+        assert_eq!(upgraded_requirement("~1.0", &versions, false), None);
+    }
+
+    #[test]
+    fn setting_keys_in_other_tables_are_dependencies() {
+        let source = "// target = \"0.1\"\n// [dev-dependencies]\n// target = \"0.2\"\n\
+                      // env = \"1\"\nfn main() {}\n";
+        let found: Vec<_> = find_requirements(source)
+            .into_iter()
+            .map(|req| (req.line, req.name))
+            .collect();
+        assert_eq!(found, [(2, "target".to_owned()), (3, "env".to_owned())]);
+    }
+}
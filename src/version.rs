@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+// A semantic version; build metadata is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: String,
+}
+
+impl Version {
+    pub fn parse(text: &str) -> Option<Version> {
+        let text = text.trim().split('+').next().unwrap_or_default();
+        let (numbers, pre) = match text.split_once('-') {
+            Some((numbers, pre)) => (numbers, pre.to_owned()),
+            None => (text, String::new()),
+        };
+        let mut parts = numbers.split('.').map(|part| part.parse::<u64>());
+        let version = Version {
+            major: parts.next()?.ok()?,
+            minor: parts.next()?.ok()?,
+            patch: parts.next()?.ok()?,
+            pre,
+        };
+        match parts.next() {
+            None => Some(version),
+            Some(_) => None,
+        }
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    // Whether a caret requirement on `self` would accept `other`.
+    pub fn is_compatible(&self, other: &Version) -> bool {
+        match (self.major, self.minor) {
+            (0, 0) => other.major == 0 && other.minor == 0 && other.patch == self.patch,
+            (0, minor) => other.major == 0 && other.minor == minor,
+            (major, _) => other.major == major,
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => compare_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+// Pre-release tags are compared by their dot-separated identifiers: numeric
+// ones as numbers and below alphanumeric ones, others as text, and a tag which
+// is a prefix of another is lower, so that alpha.2 < alpha.10 < alpha.10.1.
+fn compare_pre(pre: &str, other: &str) -> Ordering {
+    let mut ids = pre.split('.');
+    let mut other_ids = other.split('.');
+    loop {
+        let ordering = match (ids.next(), other_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(id), Some(other_id)) => match (id.parse::<u64>(), other_id.parse::<u64>()) {
+                (Ok(n), Ok(other_n)) => n.cmp(&other_n),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => id.cmp(other_id),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Version;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(version("1.2.3-beta.1+build").to_string(), "1.2.3-beta.1");
+        assert!(Version::parse("1.2").is_none());
+        assert!(Version::parse("1.2.3.4").is_none());
+        assert!(Version::parse("1.x.3").is_none());
+    }
+
+    #[test]
+    fn ordering() {
+        // The order given in the semver specification, with numeric
+        // identifiers compared as numbers.
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.2",
+            "1.0.0-alpha.10",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.1.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                version(pair[0]) < version(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert_eq!(version("1.0.0-rc.1"), version("1.0.0-rc.1+build"));
+    }
+}
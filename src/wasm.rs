@@ -0,0 +1,41 @@
+// Building programs for WASI, and running them with a WebAssembly runtime,
+// which Cargo uses as the runner for the target.
+
+use std::env;
+
+use crate::config;
+use crate::tool_available;
+
+pub const TARGET: &str = "wasm32-wasip1";
+
+// Subcommands which can be used for WebAssembly.
+pub const COMMANDS: &[&str] = &["build", "check", "run", "test"];
+
+pub const RUNNER_ENV: &str = "CARGO_TARGET_WASM32_WASIP1_RUNNER";
+
+// Runtimes, and the commands which run a module with them.
+const RUNTIMES: &[(&str, &str)] = &[("wasmtime", "wasmtime"), ("wasmer", "wasmer run")];
+
+// The runner is taken from CARGO_SINGLE_WASM_RUNNER, the "runner" key in the
+// [wasm] table of the configuration file, or is the first available runtime.
+// It may include arguments separated by whitespace.
+pub fn runner() -> Result<String, String> {
+    if let Some(runner) = env::var("CARGO_SINGLE_WASM_RUNNER")
+        .ok()
+        .filter(|runner| !runner.trim().is_empty())
+    {
+        return Ok(runner);
+    }
+    if let Some(runner) = config::load()?.get_str("wasm.runner") {
+        return Ok(runner.to_owned());
+    }
+    RUNTIMES
+        .iter()
+        .find(|(runtime, _)| tool_available(runtime))
+        .map(|(_, runner)| (*runner).to_owned())
+        .ok_or_else(|| {
+            "no WebAssembly runtime found; install wasmtime or wasmer, or set \
+             CARGO_SINGLE_WASM_RUNNER"
+                .to_owned()
+        })
+}
@@ -0,0 +1,118 @@
+// Rerunning a command whenever the watched files change. The files are polled,
+// like the source file during "edit --watch".
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::log::log;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+// Editors may write a file in several steps, so a change is only acted on once
+// the files have been left alone for this long.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Subcommands which can be watched.
+pub const COMMANDS: &[&str] = &["build", "check", "nextest", "run", "test"];
+
+// Modification times of the files; the file may briefly disappear while an
+// editor replaces it.
+fn stamps(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|md| md.modified()).ok())
+        .collect()
+}
+
+// Process ids of the descendants of a process.
+pub fn descendants(pid: u32) -> Vec<u32> {
+    let output = match Command::new("pgrep")
+        .args(["-P", &pid.to_string()])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return vec![],
+    };
+    let children: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .filter_map(|pid| pid.parse().ok())
+        .collect();
+    let mut pids = children.clone();
+    for child in children {
+        pids.extend(descendants(child));
+    }
+    pids
+}
+
+// Kills the process together with the processes it started, since the command
+// runs Cargo, which runs the program.
+fn kill_tree(child: &mut Child) {
+    if cfg!(windows) {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .output();
+    } else {
+        // The descendants are found before the process is killed, since they
+        // are reparented after that.
+        let pids = descendants(child.id());
+        let _ = child.kill();
+        if !pids.is_empty() {
+            let _ = Command::new("kill")
+                .args(pids.iter().map(u32::to_string))
+                .output();
+        }
+    }
+    // In case taskkill isn't available.
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Runs the command, and whenever one of the files returned by `paths` changes,
+// kills it if it's still running, calls `on_change` and runs it again. Only
+// returns on errors.
+pub fn watch(
+    paths: &dyn Fn() -> Vec<PathBuf>,
+    command: &mut Command,
+    on_change: &mut dyn FnMut(),
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let mut current = stamps(&paths());
+        let mut child = Some(command.spawn()?);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if let Some(running) = child.as_mut() {
+                if let Some(status) = running.try_wait()? {
+                    eprintln!(
+                        "cargo-single: {}; waiting for changes",
+                        match status.code() {
+                            Some(0) => "finished".to_owned(),
+                            Some(code) => format!("exited with status {}", code),
+                            None => "terminated by a signal".to_owned(),
+                        }
+                    );
+                    child = None;
+                }
+            }
+            if stamps(&paths()) == current {
+                continue;
+            }
+            loop {
+                current = stamps(&paths());
+                thread::sleep(DEBOUNCE);
+                if stamps(&paths()) == current {
+                    break;
+                }
+            }
+            break;
+        }
+        log!(Info, "a watched file changed, running the command again");
+        if let Some(mut running) = child {
+            kill_tree(&mut running);
+        }
+        on_change();
+    }
+}
@@ -0,0 +1,66 @@
+// Resolution of wildcard ("*") version requirements in the dependency comments
+// to the latest release, so that the program doesn't get a different version of
+// a dependency each time the dependencies are refreshed.
+
+use std::collections::HashMap;
+
+use crate::index::latest_release;
+use crate::upgrade::find_requirements;
+
+// Appended to dependency lines in Cargo.toml whose version was resolved from a
+// wildcard, so that the version can be reused on the next refresh.
+const MARKER: &str = " # resolved from \"*\"";
+
+// Versions resolved on a previous refresh, by crate name, from the dependency
+// lines of the manifest.
+pub fn previous(manifest_deps: &str) -> HashMap<String, String> {
+    let lines: Vec<_> = manifest_deps
+        .lines()
+        .map(|line| format!("// {}", line))
+        .collect();
+    let header = lines.join("\n");
+    find_requirements(&header)
+        .into_iter()
+        .filter(|req| lines[req.line].ends_with(MARKER))
+        .map(|req| {
+            let version = lines[req.line][req.range.0..req.range.1].to_owned();
+            (req.package, version)
+        })
+        .collect()
+}
+
+// Replaces the wildcard requirements in `source` with versions from `known`,
+// or the latest releases. With `mark`, the lines are marked as resolved, for
+// writing to the manifest. Requirements which can't be resolved are kept, and
+// returned with the reason.
+pub fn resolve(
+    source: &str,
+    known: &mut HashMap<String, String>,
+    mark: bool,
+) -> (String, Vec<String>) {
+    let mut lines: Vec<String> = source.split_inclusive('\n').map(str::to_owned).collect();
+    let mut failures = vec![];
+    for req in find_requirements(source) {
+        let line = &mut lines[req.line];
+        if &line[req.range.0..req.range.1] != "*" {
+            continue;
+        }
+        if !known.contains_key(&req.package) {
+            match latest_release(&req.package) {
+                Ok(version) => {
+                    known.insert(req.package.clone(), version.to_string());
+                }
+                Err(e) => {
+                    failures.push(format!("{}: {}", req.package, e));
+                    continue;
+                }
+            }
+        }
+        line.replace_range(req.range.0..req.range.1, &known[&req.package]);
+        if mark {
+            let end = line.trim_end_matches(['\r', '\n']).len();
+            line.insert_str(end, MARKER);
+        }
+    }
+    (lines.concat(), failures)
+}
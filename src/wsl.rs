@@ -0,0 +1,78 @@
+// Source files across the boundary between Windows and WSL. From Windows, a
+// file in a WSL distribution, under \\wsl$\<distro> or \\wsl.localhost\<distro>,
+// is built by cargo-single in the distribution, run with wsl.exe, since the
+// hard links and the paths in the manifest don't work through the share. From
+// WSL, a file on a Windows drive, under /mnt/<drive>, is built as usual, but
+// a failure to link it into its project is explained.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const SHARES: &[&str] = &["wsl$", "wsl.localhost"];
+
+// The distribution and the path in it of a path on the WSL share, which may
+// also be in the \\?\UNC\ form of canonical paths.
+pub fn distro_path(path: &str) -> Option<(String, String)> {
+    let path = path.replace('/', "\\");
+    let rest = path
+        .strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix(r"\\"))?;
+    let (share, rest) = rest.split_once('\\')?;
+    if !SHARES.iter().any(|name| name.eq_ignore_ascii_case(share)) {
+        return None;
+    }
+    let (distro, rest) = rest.split_once('\\').unwrap_or((rest, ""));
+    if distro.is_empty() {
+        return None;
+    }
+    Some((distro.to_owned(), format!("/{}", rest.replace('\\', "/"))))
+}
+
+// The command running cargo-single with the arguments in the distribution,
+// with the paths on the share translated. The current directory is kept if
+// it's in the distribution, and otherwise is the home directory, as the
+// relative paths are then meaningless anyway. A login shell finds
+// cargo-single in ~/.cargo/bin.
+pub fn command(distro: &str, args: &[String]) -> Command {
+    let mut wsl = Command::new("wsl.exe");
+    wsl.args(["--distribution", distro, "--shell-type", "login", "--cd"]);
+    match env::current_dir()
+        .ok()
+        .and_then(|dir| distro_path(&dir.to_string_lossy()))
+    {
+        Some((cwd_distro, dir)) if cwd_distro.eq_ignore_ascii_case(distro) => wsl.arg(dir),
+        _ => wsl.arg("~"),
+    };
+    wsl.args(["--", "cargo", "single"]);
+    for arg in args {
+        match distro_path(arg) {
+            Some((arg_distro, path)) if arg_distro.eq_ignore_ascii_case(distro) => wsl.arg(path),
+            _ => wsl.arg(arg),
+        };
+    }
+    wsl
+}
+
+fn in_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && fs::read_to_string("/proc/sys/kernel/osrelease")
+            .is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+}
+
+// An explanation for a failure to link the source file into its project, if
+// it's on a Windows drive in WSL.
+pub fn link_hint(file_src: &Path) -> Option<String> {
+    let path = std::path::absolute(file_src).ok()?;
+    let mut components = path.to_str()?.strip_prefix("/mnt/")?.split('/');
+    let drive = components.next().filter(|drive| drive.len() == 1)?;
+    in_wsl().then(|| {
+        format!(
+            "{} is on the Windows drive {}:, which may not allow hard links from WSL; \
+             move it into the Linux file system, or build it with cargo-single on Windows",
+            file_src.display(),
+            drive.to_uppercase()
+        )
+    })
+}